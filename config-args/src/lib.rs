@@ -0,0 +1,485 @@
+//! The command-line arguments (and environment variables) for everything in [`discord_bot::Config`],
+//! shared by the binaries so that each one takes the same options in the same way
+
+use clap::{Args, ValueEnum};
+use std::{path::PathBuf, time::Duration};
+
+/// Where to reach Spotify, which is given to [`discord_bot::init`] separately from the rest of the configuration
+#[derive(Debug, Args)]
+pub struct SpotifyApiUrlArgs {
+    /// Where to reach Spotify's API, for going through a proxy or to a mock of it
+    #[arg(long, env, default_value_t = discord_bot::SpotifyApiUrls::default().api_base_url)]
+    spotify_api_base_url: String,
+    /// Where to reach Spotify's accounts service for tokens, for going through a proxy or to a mock of it
+    #[arg(long, env, default_value_t = discord_bot::SpotifyApiUrls::default().auth_base_url)]
+    spotify_auth_base_url: String,
+}
+
+impl From<SpotifyApiUrlArgs> for discord_bot::SpotifyApiUrls {
+    fn from(
+        SpotifyApiUrlArgs {
+            spotify_api_base_url,
+            spotify_auth_base_url,
+        }: SpotifyApiUrlArgs,
+    ) -> Self {
+        Self {
+            api_base_url: spotify_api_base_url,
+            auth_base_url: spotify_auth_base_url,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[arg(long, env)]
+    spotify_cross_search: bool,
+    #[arg(long, env, default_value_t = discord_bot::Config::default().spotify_cross_search_threshold)]
+    spotify_cross_search_threshold: f64,
+    /// Look albums up in this country's Spotify catalog (as a two-letter code, like `US`), falling back to any market
+    #[arg(long, env)]
+    spotify_market: Option<String>,
+
+    #[arg(long, env)]
+    force_command_registration: bool,
+    /// Only register and answer these commands (like `new-release,version`), instead of all of them.
+    /// Giving `--enabled-commands` with nothing after it registers none, leaving just the PINGs Discord sends to check the endpoint
+    #[arg(long, env, value_delimiter = ',', num_args = 0..)]
+    enabled_commands: Option<Vec<String>>,
+
+    #[arg(long, env, default_value_t = discord_bot::Config::default().report_footer_text)]
+    report_footer_text: String,
+    #[arg(long, env)]
+    report_url: Option<String>,
+
+    /// How many commands can be used per minute in each server (unlimited if not given)
+    #[arg(long, env)]
+    guild_rate_limit_per_minute: Option<u32>,
+    /// How many commands each user can use per minute (unlimited if not given)
+    #[arg(long, env)]
+    user_rate_limit_per_minute: Option<u32>,
+
+    /// Show the release image closest to this many pixels on its longer side (the largest if not given)
+    #[arg(long, env)]
+    preferred_image_size: Option<u32>,
+
+    /// Leave out the instructions on what to do with the formatted release
+    #[arg(long, env)]
+    hide_helper_text: bool,
+
+    /// What Spotify's albums are labelled as
+    #[arg(long, env, value_enum, default_value_t = SpotifyReleaseType::Lp)]
+    spotify_album_as: SpotifyReleaseType,
+    /// What Spotify's singles are labelled as
+    #[arg(long, env, value_enum, default_value_t = SpotifyReleaseType::SingleOrEp)]
+    spotify_single_as: SpotifyReleaseType,
+    /// What Spotify's compilations are labelled as
+    #[arg(long, env, value_enum, default_value_t = SpotifyReleaseType::Compilation)]
+    spotify_compilation_as: SpotifyReleaseType,
+    /// What albums Spotify only says an artist appears on are labelled as
+    #[arg(long, env, value_enum, default_value_t = SpotifyReleaseType::SingleOrEp)]
+    spotify_appears_on_as: SpotifyReleaseType,
+
+    /// Put at the start of each single (like an emoji)
+    #[arg(long, env)]
+    single_indicator: Option<String>,
+    /// Put at the start of each EP
+    #[arg(long, env)]
+    ep_indicator: Option<String>,
+    /// Put at the start of each LP
+    #[arg(long, env)]
+    lp_indicator: Option<String>,
+    /// Put at the start of each compilation
+    #[arg(long, env)]
+    compilation_indicator: Option<String>,
+    /// Put at the start of each remix release
+    #[arg(long, env)]
+    remixes_indicator: Option<String>,
+    /// Put at the start of each music video
+    #[arg(long, env)]
+    video_indicator: Option<String>,
+    /// Put at the start of each release of some other type. Defaults to 🎶 when any of the other indicators are given
+    #[arg(long, env)]
+    other_indicator: Option<String>,
+
+    /// Which record labels to show: none, only the ones with a role in the server, or all of them
+    #[arg(long, env, value_enum, default_value_t = LabelDisplay::WhenRole)]
+    label_display: LabelDisplay,
+
+    /// Where to credit artists who are both main artists and featured (or remixing): as a feature, as a main artist, or both
+    #[arg(long, env, value_enum, default_value_t = OverlappingCredits::Feature)]
+    overlapping_credits: OverlappingCredits,
+
+    /// What to do with releases that would be shown without any artist: show them with a warning, or refuse to
+    #[arg(long, env, value_enum, default_value_t = MissingArtist::Warn)]
+    missing_artist: MissingArtist,
+
+    /// Show the record label in the preview's footer as plain text, instead of in the brackets after the title
+    #[arg(long, env)]
+    label_in_footer: bool,
+
+    #[arg(long, env)]
+    show_explicit_marker: bool,
+
+    /// Say how long ago releases came out (or how soon they will) after their dates, like "(3 days ago)"
+    #[arg(long, env)]
+    show_relative_date: bool,
+
+    /// The most tracks a release can have to be labelled as a single
+    #[arg(long, env, default_value_t = discord_bot::TrackCountThresholds::default().single_max_tracks)]
+    single_max_tracks: usize,
+    /// The most tracks a release can have to be labelled as an EP
+    #[arg(long, env, default_value_t = discord_bot::TrackCountThresholds::default().ep_max_tracks)]
+    ep_max_tracks: usize,
+
+    /// Label Spotify albums that look like compilations (by "Various Artists", or with a title like "Label Sampler") as compilations
+    #[arg(long, env)]
+    detect_spotify_compilations: bool,
+
+    /// Also format pages about music videos (like premieres on YouTube), not just releases
+    #[arg(long, env)]
+    recognize_music_videos: bool,
+
+    #[arg(long, env, value_enum, default_value_t = SpotifyCredentialCheck::Skip)]
+    spotify_credential_check: SpotifyCredentialCheck,
+
+    /// How many additional artists to list before summarizing the rest (all of them if not given)
+    #[arg(long, env)]
+    max_additional_artists: Option<usize>,
+    /// Credit each track's artists on a line of its own instead of listing them all together
+    #[arg(long, env)]
+    per_track_credits: bool,
+
+    #[arg(long, env)]
+    show_language: bool,
+    #[arg(long, env, default_value_t = discord_bot::Config::default().primary_language)]
+    primary_language: String,
+
+    /// Show releases' barcodes (UPC or EAN) in the preview's footer
+    #[arg(long, env)]
+    show_upc: bool,
+
+    #[arg(long, env)]
+    show_genres: bool,
+    #[arg(long, env)]
+    ping_genre_roles: bool,
+
+    /// Lets people with this role use the debug command, as well as the bot's owners
+    #[arg(long, env)]
+    debug_role_id: Option<discord_bot::Id<discord_bot::RoleMarker>>,
+
+    /// Lets people with this role have new-release post in channels they can't send messages in themselves
+    #[arg(long, env)]
+    direct_post_role_id: Option<discord_bot::Id<discord_bot::RoleMarker>>,
+
+    /// Lets people with this role use the check-roles command, as well as people who can manage roles
+    #[arg(long, env)]
+    check_roles_role_id: Option<discord_bot::Id<discord_bot::RoleMarker>>,
+
+    /// Only handle commands in these servers, by ID separated by commas (every server if not given)
+    #[arg(long, env, value_delimiter = ',')]
+    allowed_guilds: Vec<discord_bot::Id<discord_bot::GuildMarker>>,
+
+    /// Artists whose names have ", " in them, so they aren't split apart, separated by semicolons (like `Tyler, The Creator;Earth, Wind & Fire`)
+    #[arg(long, env, value_delimiter = ';')]
+    comma_artist_names: Vec<String>,
+
+    /// Roles to ping for Spotify artists by ID, as `GUILD_ID:SPOTIFY_ARTIST_ID:ROLE_ID` separated by commas
+    #[arg(long, env, value_delimiter = ',')]
+    artist_roles: Vec<discord_bot::ArtistRole>,
+
+    /// Only fetch pages from these hosts (and their subdomains), separated by commas (any host if not given)
+    #[arg(long, env, value_delimiter = ',')]
+    allowed_hosts: Option<Vec<String>>,
+    /// Never fetch pages from these hosts (and their subdomains), separated by commas
+    #[arg(long, env, value_delimiter = ',')]
+    denied_hosts: Vec<String>,
+
+    /// How many releases can be fetched at once across every interaction
+    #[arg(long, env, default_value_t = discord_bot::Config::default().max_concurrent_fetches)]
+    max_concurrent_fetches: usize,
+
+    /// How many seconds to reuse each server's roles for before fetching them again (they're fetched every time if not given)
+    #[arg(long, env)]
+    roles_cache_seconds: Option<u64>,
+
+    /// Where to keep how many releases have been formatted in each server, for the stats command (they aren't kept if not given)
+    #[arg(long, env)]
+    usage_stats_path: Option<PathBuf>,
+
+    /// Put before every release posted directly in a channel, like a ping for a "new releases" role
+    #[arg(long, env, default_value = "")]
+    direct_post_prefix: String,
+    /// Put after every release posted directly in a channel
+    #[arg(long, env, default_value = "")]
+    direct_post_suffix: String,
+}
+
+impl From<ConfigArgs> for discord_bot::Config {
+    fn from(
+        ConfigArgs {
+            spotify_cross_search,
+            spotify_cross_search_threshold,
+            spotify_market,
+            force_command_registration,
+            enabled_commands,
+            report_footer_text,
+            report_url,
+            guild_rate_limit_per_minute,
+            user_rate_limit_per_minute,
+            preferred_image_size,
+            hide_helper_text,
+            spotify_album_as,
+            spotify_single_as,
+            spotify_compilation_as,
+            spotify_appears_on_as,
+            single_indicator,
+            ep_indicator,
+            lp_indicator,
+            compilation_indicator,
+            remixes_indicator,
+            video_indicator,
+            other_indicator,
+            label_display,
+            overlapping_credits,
+            missing_artist,
+            label_in_footer,
+            show_explicit_marker,
+            show_relative_date,
+            single_max_tracks,
+            ep_max_tracks,
+            detect_spotify_compilations,
+            recognize_music_videos,
+            spotify_credential_check,
+            max_additional_artists,
+            per_track_credits,
+            show_language,
+            primary_language,
+            show_upc,
+            show_genres,
+            ping_genre_roles,
+            debug_role_id,
+            direct_post_role_id,
+            check_roles_role_id,
+            allowed_guilds,
+            comma_artist_names,
+            artist_roles,
+            allowed_hosts,
+            denied_hosts,
+            max_concurrent_fetches,
+            roles_cache_seconds,
+            usage_stats_path,
+            direct_post_prefix,
+            direct_post_suffix,
+        }: ConfigArgs,
+    ) -> Self {
+        Self {
+            spotify_cross_search,
+            spotify_cross_search_threshold,
+            spotify_market,
+            force_command_registration,
+            // `ENABLED_COMMANDS=` (or a trailing comma) comes through as an empty name, which means no command rather than one called ""
+            enabled_commands: enabled_commands.map(|names| {
+                names
+                    .into_iter()
+                    .map(|name| name.trim().to_owned())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            }),
+            report_footer_text,
+            report_url,
+            guild_rate_limit: guild_rate_limit_per_minute.map(per_minute),
+            user_rate_limit: user_rate_limit_per_minute.map(per_minute),
+            preferred_image_size: preferred_image_size
+                .map_or(discord_bot::ImageSize::Largest, |pixels| {
+                    discord_bot::ImageSize::Closest { pixels }
+                }),
+            show_helper_text: !hide_helper_text,
+            label_display: label_display.into(),
+            overlapping_credits: overlapping_credits.into(),
+            missing_artist: missing_artist.into(),
+            label_in_footer,
+            show_explicit_marker,
+            show_relative_date,
+            track_count_thresholds: discord_bot::TrackCountThresholds {
+                single_max_tracks,
+                ep_max_tracks,
+            },
+            spotify_album_type_mapping: discord_bot::SpotifyAlbumTypeMapping {
+                album: spotify_album_as.into(),
+                single: spotify_single_as.into(),
+                compilation: spotify_compilation_as.into(),
+                appears_on: spotify_appears_on_as.into(),
+            },
+            release_type_indicators: discord_bot::ReleaseTypeIndicators {
+                single: single_indicator,
+                ep: ep_indicator,
+                lp: lp_indicator,
+                compilation: compilation_indicator,
+                remixes: remixes_indicator,
+                video: video_indicator,
+                other: other_indicator,
+            },
+            detect_spotify_compilations,
+            recognize_music_videos,
+            spotify_credential_check: spotify_credential_check.into(),
+            max_additional_artists,
+            per_track_credits,
+            show_language,
+            primary_language,
+            show_upc,
+            show_genres,
+            ping_genre_roles,
+            allowed_guilds: allowed_guilds.into_iter().collect(),
+            comma_artist_names,
+            artist_roles,
+            debug_role_id,
+            direct_post_role_id,
+            check_roles_role_id,
+            host_policy: discord_bot::HostPolicy {
+                allowed_hosts,
+                denied_hosts,
+            },
+            max_concurrent_fetches,
+            roles_cache_ttl: roles_cache_seconds.map(Duration::from_secs),
+            usage_stats_path,
+            direct_post_prefix,
+            direct_post_suffix,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SpotifyCredentialCheck {
+    Skip,
+    Required,
+    Optional,
+}
+
+impl From<SpotifyCredentialCheck> for discord_bot::SpotifyCredentialCheck {
+    fn from(value: SpotifyCredentialCheck) -> Self {
+        match value {
+            SpotifyCredentialCheck::Skip => Self::Skip,
+            SpotifyCredentialCheck::Required => Self::Required,
+            SpotifyCredentialCheck::Optional => Self::Optional,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LabelDisplay {
+    Never,
+    WhenRole,
+    Always,
+}
+
+impl From<LabelDisplay> for discord_bot::LabelDisplay {
+    fn from(value: LabelDisplay) -> Self {
+        match value {
+            LabelDisplay::Never => Self::Never,
+            LabelDisplay::WhenRole => Self::WhenRole,
+            LabelDisplay::Always => Self::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SpotifyReleaseType {
+    Single,
+    Ep,
+    Lp,
+    Compilation,
+    SingleOrEp,
+}
+
+impl From<SpotifyReleaseType> for discord_bot::SpotifyReleaseType {
+    fn from(value: SpotifyReleaseType) -> Self {
+        match value {
+            SpotifyReleaseType::Single => Self::Single,
+            SpotifyReleaseType::Ep => Self::EP,
+            SpotifyReleaseType::Lp => Self::LP,
+            SpotifyReleaseType::Compilation => Self::Compilation,
+            SpotifyReleaseType::SingleOrEp => Self::SingleOrEP,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OverlappingCredits {
+    Feature,
+    Main,
+    Both,
+}
+
+impl From<OverlappingCredits> for discord_bot::OverlappingCredits {
+    fn from(value: OverlappingCredits) -> Self {
+        match value {
+            OverlappingCredits::Feature => Self::Feature,
+            OverlappingCredits::Main => Self::Main,
+            OverlappingCredits::Both => Self::Both,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum MissingArtist {
+    Warn,
+    Error,
+}
+
+impl From<MissingArtist> for discord_bot::MissingArtist {
+    fn from(value: MissingArtist) -> Self {
+        match value {
+            MissingArtist::Warn => Self::Warn,
+            MissingArtist::Error => Self::Error,
+        }
+    }
+}
+
+fn per_minute(capacity: u32) -> discord_bot::RateLimit {
+    discord_bot::RateLimit {
+        capacity,
+        per: Duration::from_secs(60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, Parser};
+
+    /// Both sets of arguments, flattened in the way the binaries do
+    #[derive(Debug, Parser)]
+    struct BinaryArgs {
+        #[command(flatten)]
+        spotify_api_urls: SpotifyApiUrlArgs,
+        #[command(flatten)]
+        config: ConfigArgs,
+    }
+
+    #[test]
+    fn arguments_are_well_formed() {
+        BinaryArgs::command().debug_assert();
+    }
+
+    fn enabled_commands(args: &[&str]) -> Option<Vec<String>> {
+        let args = BinaryArgs::try_parse_from(["bot"].iter().chain(args)).unwrap();
+        let config = discord_bot::Config::from(args.config);
+
+        config.enabled_commands.map(|names| {
+            let mut names = Vec::from_iter(names);
+            names.sort();
+            names
+        })
+    }
+
+    #[test]
+    fn empty_command_names_are_dropped() {
+        assert_eq!(enabled_commands(&[]), None);
+        assert_eq!(enabled_commands(&["--enabled-commands="]), Some(vec![]));
+        assert_eq!(enabled_commands(&["--enabled-commands"]), Some(vec![]));
+        assert_eq!(
+            enabled_commands(&["--enabled-commands=version, new-release,"]),
+            Some(vec!["new-release".to_owned(), "version".to_owned()])
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use std::process::Command;
+
+/// Bakes the git commit being built into the binary as `GIT_SHA`, for the `version` command.
+/// Builds without git (or outside of the repo, like in the Docker image) can pass `GIT_SHA` in themselves instead
+fn main() {
+    println!("cargo::rerun-if-env-changed=GIT_SHA");
+    println!("cargo::rerun-if-changed=../.git/HEAD");
+    println!("cargo::rerun-if-changed=../.git/refs/heads");
+
+    let git_sha = std::env::var("GIT_SHA").ok().or_else(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+
+        String::from_utf8(output.stdout)
+            .ok()
+            .map(|stdout| stdout.trim().to_owned())
+    });
+
+    if let Some(git_sha) = git_sha.filter(|git_sha| !git_sha.is_empty()) {
+        println!("cargo::rustc-env=GIT_SHA={git_sha}");
+    }
+}
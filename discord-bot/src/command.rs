@@ -1,21 +1,253 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crate::State;
+use crate::{Config, State};
+use ahash::AHashSet;
 use futures::future::BoxFuture;
+use itertools::Itertools;
 use rart::{ArrayKey, VersionedAdaptiveRadixTree};
-use snafu::{OptionExt, Snafu};
+use snafu::{OptionExt, Report, ResultExt, Snafu, ensure};
 use twilight_model::{
     application::{
-        command::Command,
-        interaction::{Interaction, InteractionData},
+        command::{Command, CommandOption, CommandType},
+        interaction::{Interaction, InteractionContextType, InteractionData},
+    },
+    channel::message::{
+        Embed, MessageFlags,
+        component::{ActionRow, Button, ButtonStyle, Component},
     },
-    http::interaction::InteractionResponse,
+    guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{Id, marker::RoleMarker},
+};
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    embed::{EmbedBuilder, EmbedFooterBuilder},
 };
 
+mod batch;
+mod check_roles;
+mod debug;
 mod new_release;
+mod refresh_roles;
+mod stats;
+mod version;
+
+const COLOR_RED_500: u32 = 0xef4444;
+const COLOR_PINK_500: u32 = 0xec4899;
+
+const COLOR_ERROR: u32 = COLOR_RED_500;
+const COLOR_SUCCESS: u32 = COLOR_PINK_500;
+
+/// The embed every command uses to report an error, with the operator's configured footer (and link, if any)
+pub fn error_embed(error: impl std::error::Error, config: &Config) -> Embed {
+    let mut embed = EmbedBuilder::new()
+        .color(COLOR_ERROR)
+        .title("Error")
+        .description(Report::from_error(error).to_string())
+        .footer(EmbedFooterBuilder::new(&config.report_footer_text).build());
+
+    if let Some(report_url) = &config.report_url {
+        embed = embed.url(report_url);
+    }
+
+    embed.build()
+}
+
+/// What a command responded with, along with whether it failed,
+/// since a failure that comes after the response was deferred is sent differently from what was asked for
+#[derive(Debug)]
+pub enum CommandResponse {
+    Succeeded(InteractionResponse),
+    /// Made with [`error_response`]
+    Failed(InteractionResponse),
+}
+
+impl CommandResponse {
+    pub fn into_interaction_response(self) -> InteractionResponse {
+        match self {
+            Self::Succeeded(response) | Self::Failed(response) => response,
+        }
+    }
+}
+
+pub fn error_response(error: impl std::error::Error, config: &Config) -> InteractionResponse {
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .embeds([error_embed(error, config)])
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+
+    InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum PermissionError {
+    /// the command was run outside of a Discord server, so there are no permissions to check
+    NotUsedByMember,
+
+    /// you need {missing} to use this command
+    InsufficientPermissions { missing: String },
+}
+
+/// Makes sure the person who ran the command has every one of `permissions` (as Discord says they have in the channel)
+/// or has `role_id`, for commands that are only meant for some people in a server.
+/// Having no permissions to ask for doesn't let everyone through, so a command can be limited to just the role
+pub fn require_permissions(
+    interaction: &Interaction,
+    permissions: Permissions,
+    role_id: Option<Id<RoleMarker>>,
+) -> Result<(), PermissionError> {
+    let member_permissions = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .unwrap_or_else(Permissions::empty);
+
+    require_permissions_in(interaction, member_permissions, permissions, role_id)
+}
+
+/// Like [`require_permissions`], but for what the person who ran the command can do in another channel,
+/// since the permissions Discord sends with the interaction are only for the channel it was used in
+pub fn require_permissions_in(
+    interaction: &Interaction,
+    member_permissions: Permissions,
+    permissions: Permissions,
+    role_id: Option<Id<RoleMarker>>,
+) -> Result<(), PermissionError> {
+    let member = interaction.member.as_ref().context(NotUsedByMemberSnafu)?;
+
+    let has_role = role_id.is_some_and(|role_id| member.roles.contains(&role_id));
+    let has_permissions = !permissions.is_empty() && member_permissions.contains(permissions);
+
+    ensure!(
+        has_role || has_permissions,
+        InsufficientPermissionsSnafu {
+            missing: describe_missing(permissions - member_permissions, role_id),
+        }
+    );
+
+    Ok(())
+}
+
+/// When Discord's epoch starts, in milliseconds since the Unix epoch, which is what the timestamp in an ID counts from
+const DISCORD_EPOCH_MILLIS: u64 = 1_420_070_400_000;
+
+/// When anything the interaction waits on has to be done by for its response to still be sent in time,
+/// going by when Discord created the interaction rather than when it got here
+pub fn response_deadline(interaction: &Interaction) -> tokio::time::Instant {
+    let created_at =
+        UNIX_EPOCH + Duration::from_millis((interaction.id.get() >> 22) + DISCORD_EPOCH_MILLIS);
+    // a clock that's behind Discord's would make the interaction seem to be from the future
+    let age = SystemTime::now()
+        .duration_since(created_at)
+        .unwrap_or_default();
+
+    tokio::time::Instant::now()
+        + (crate::INTERACTION_TOKEN_LIFETIME - crate::INTERACTION_TOKEN_LEEWAY).saturating_sub(age)
+}
+
+fn describe_missing(permissions: Permissions, role_id: Option<Id<RoleMarker>>) -> String {
+    let permissions = (!permissions.is_empty()).then(|| {
+        let names = permissions.iter_names().map(|(name, _)| name).join(", ");
+        format!("the {names} permission")
+    });
+    let role = role_id.map(|role_id| format!("the <@&{role_id}> role"));
+
+    [permissions, role].into_iter().flatten().join(" or ")
+}
+
+/// What a component that this bot attached to one of its messages does, as identified by its `custom_id`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentAction {
+    /// Post the release formatted in an ephemeral `new-release` response where everyone can see it
+    PostPublicly,
+}
 
-type Return = InteractionResponse;
-type ArcedHandler = Arc<dyn Fn(State, Interaction) -> BoxFuture<'static, Return> + Send + Sync>;
+impl ComponentAction {
+    const POST_PUBLICLY_CUSTOM_ID: &str = "post-publicly";
+
+    pub const fn custom_id(self) -> &'static str {
+        match self {
+            Self::PostPublicly => Self::POST_PUBLICLY_CUSTOM_ID,
+        }
+    }
+
+    pub fn from_custom_id(custom_id: &str) -> Option<Self> {
+        match custom_id {
+            Self::POST_PUBLICLY_CUSTOM_ID => Some(Self::PostPublicly),
+            _ => None,
+        }
+    }
+}
+
+/// An action row with just one button in it, to attach to a response with [`InteractionResponseDataBuilder::components`]
+pub fn single_button_row(action: ComponentAction, label: impl Into<String>) -> Component {
+    Component::ActionRow(ActionRow {
+        components: vec![Component::Button(Button {
+            custom_id: Some(action.custom_id().to_owned()),
+            disabled: false,
+            emoji: None,
+            label: Some(label.into()),
+            style: ButtonStyle::Primary,
+            url: None,
+            sku_id: None,
+        })],
+    })
+}
+
+#[derive(Debug, Snafu)]
+enum HandleComponentError {
+    /// missing expected component data
+    MissingComponentData,
+
+    /// this bot doesn't know what to do with the component {custom_id:?} (it might be from an older version of the bot)
+    UnknownComponent { custom_id: String },
+
+    /// couldn't post the release publicly
+    PostPubliclyError {
+        source: new_release::PostPubliclyError,
+    },
+}
+
+fn handle_component_impl(
+    state: &State,
+    interaction: &Interaction,
+) -> Result<InteractionResponse, HandleComponentError> {
+    let Some(InteractionData::MessageComponent(component_data)) = &interaction.data else {
+        return Err(HandleComponentError::MissingComponentData);
+    };
+
+    let action = ComponentAction::from_custom_id(&component_data.custom_id).with_context(|| {
+        UnknownComponentSnafu {
+            custom_id: component_data.custom_id.clone(),
+        }
+    })?;
+
+    match action {
+        ComponentAction::PostPublicly => {
+            new_release::post_publicly(interaction, &state.config).context(PostPubliclySnafu)
+        }
+    }
+}
+
+/// Responds to someone using a component (like pressing a button) on one of this bot's messages
+#[tracing::instrument(skip(state))]
+pub fn handle_component(state: State, interaction: Interaction) -> InteractionResponse {
+    match handle_component_impl(&state, &interaction) {
+        Ok(interaction_response) => interaction_response,
+        Err(error) => error_response(error, &state.config),
+    }
+}
+
+type Return = CommandResponse;
+pub(crate) type ArcedHandler =
+    Arc<dyn Fn(State, Interaction) -> BoxFuture<'static, Return> + Send + Sync>;
 
 fn arc_handler<Handler, Fut>(handler: Handler) -> ArcedHandler
 where
@@ -26,7 +258,127 @@ where
 }
 
 pub fn all() -> Vec<(&'static Command, ArcedHandler)> {
-    vec![(&new_release::COMMAND, arc_handler(new_release::handle))]
+    vec![
+        (&new_release::COMMAND, arc_handler(new_release::handle)),
+        (&check_roles::COMMAND, arc_handler(check_roles::handle)),
+        (&batch::COMMAND, arc_handler(batch::handle)),
+        (&debug::COMMAND, arc_handler(debug::handle)),
+        (&refresh_roles::COMMAND, arc_handler(refresh_roles::handle)),
+        (&stats::COMMAND, arc_handler(stats::handle)),
+        (&version::COMMAND, arc_handler(version::handle)),
+    ]
+}
+
+/// there's no command named {name:?} to register
+#[derive(Debug, Snafu)]
+pub struct UnknownCommandError {
+    name: String,
+}
+
+/// The commands to register with Discord and route, which are only the ones named in `enabled_commands` when that's given.
+/// A disabled command is left out of both, so it can't be run even if Discord still has it registered from before
+pub fn enabled(
+    enabled_commands: Option<&AHashSet<String>>,
+) -> Result<Vec<(&'static Command, ArcedHandler)>, UnknownCommandError> {
+    let mut all_commands = all();
+
+    if let Some(enabled_commands) = enabled_commands {
+        if let Some(name) = enabled_commands.iter().find(|name| {
+            !all_commands
+                .iter()
+                .any(|(command, _)| command.name == **name)
+        }) {
+            return UnknownCommandSnafu { name }.fail();
+        }
+
+        all_commands.retain(|(command, _)| enabled_commands.contains(&command.name));
+    }
+
+    Ok(all_commands)
+}
+
+/// How the commands already registered with Discord differ from the ones this bot wants registered
+#[derive(Debug, Default)]
+pub struct CommandDrift<'a> {
+    pub added: Vec<&'a str>,
+    pub removed: Vec<&'a str>,
+    pub changed: Vec<&'a str>,
+}
+
+impl CommandDrift<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Only the parts of a command that this bot decides on; Discord fills in IDs, versions, and defaults on its side
+#[derive(Debug, PartialEq)]
+struct Signature<'a> {
+    kind: CommandType,
+    description: &'a str,
+    options: &'a [CommandOption],
+    default_member_permissions: Option<Permissions>,
+    contexts: Option<&'a [InteractionContextType]>,
+    dm_permission: bool,
+    nsfw: bool,
+}
+
+// `dm_permission` is superseded by `contexts`, but it's still what commands registered without contexts come back with
+#[allow(deprecated)]
+fn signature(command: &Command) -> Signature<'_> {
+    Signature {
+        kind: command.kind,
+        description: &command.description,
+        options: &command.options,
+        default_member_permissions: command.default_member_permissions,
+        contexts: command.contexts.as_deref(),
+        // the defaults Discord fills in when these aren't given
+        dm_permission: command.dm_permission.unwrap_or(true),
+        nsfw: command.nsfw.unwrap_or(false),
+    }
+}
+
+/// The type of an Activity's Entry Point command, which twilight doesn't know about yet
+const PRIMARY_ENTRY_POINT: CommandType = CommandType::Unknown(4);
+
+/// Whether the command is the app's Entry Point (for launching its Activity), which is registered outside of this bot.
+/// Discord refuses to overwrite the commands without it, so it's kept as it is instead of counting as removed
+pub fn is_entry_point(command: &Command) -> bool {
+    command.kind == PRIMARY_ENTRY_POINT
+}
+
+pub fn drift<'a>(desired: &'a [Command], registered: &'a [Command]) -> CommandDrift<'a> {
+    let desired = BTreeMap::from_iter(
+        desired
+            .iter()
+            .map(|command| (command.name.as_str(), signature(command))),
+    );
+    let registered = BTreeMap::from_iter(
+        registered
+            .iter()
+            .filter(|command| !is_entry_point(command))
+            .map(|command| (command.name.as_str(), signature(command))),
+    );
+
+    let mut drift = CommandDrift::default();
+
+    for (name, desired_signature) in &desired {
+        match registered.get(name) {
+            None => drift.added.push(*name),
+            Some(registered_signature) if registered_signature != desired_signature => {
+                drift.changed.push(*name)
+            }
+            Some(_) => {}
+        }
+    }
+
+    for name in registered.keys() {
+        if !desired.contains_key(name) {
+            drift.removed.push(*name);
+        }
+    }
+
+    drift
 }
 
 #[derive(Default, Clone)]
@@ -96,3 +448,287 @@ impl<'a> FromIterator<(&'a Command, ArcedHandler)> for CommandRouter {
         router
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use twilight_model::application::interaction::InteractionType;
+    use twilight_util::builder::command::CommandBuilder;
+
+    /// Enough of a [`State`] for handlers that don't get as far as talking to Discord or Spotify
+    pub(crate) fn state(config: Config) -> State {
+        State {
+            discord_client: Arc::new(twilight_http::Client::new(String::new())),
+            discord_application_id: Id::new(2),
+            discord_application_owner_ids: Arc::new([]),
+            spotify_client: None,
+            http_client: Arc::new(reqwest::Client::new()),
+            fetch_permits: Arc::new(tokio::sync::Semaphore::new(1)),
+            roles_cache: Arc::new(crate::roles_cache::RolesCache::new(None)),
+            usage_stats: None,
+            config: Arc::new(config),
+        }
+    }
+
+    fn footer_text(response: &InteractionResponse) -> Option<&str> {
+        let embeds = response.data.as_ref()?.embeds.as_ref()?;
+        let footer = embeds.first()?.footer.as_ref()?;
+        Some(&footer.text)
+    }
+
+    pub(crate) fn member_interaction(permissions: Permissions, roles: &[u64]) -> Interaction {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "application_id": "2",
+            // who ran it and where, without any command data
+            "type": 1,
+            "token": "token",
+            "authorizing_integration_owners": {},
+            "entitlements": [],
+            "guild_id": "3",
+            "member": {
+                "deaf": false,
+                "mute": false,
+                "flags": 0,
+                "joined_at": null,
+                "roles": roles.iter().map(u64::to_string).collect::<Vec<_>>(),
+                "permissions": permissions.bits().to_string(),
+            },
+        }))
+        .unwrap()
+    }
+
+    fn command(name: &str, description: &str) -> Command {
+        CommandBuilder::new(name, description, CommandType::ChatInput).build()
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn what_discord_fills_in_is_not_drift() {
+        let desired = [command("new-release", "Post a new music release")];
+        let mut registered = desired.clone();
+        registered[0].id = Some(Id::new(1));
+        registered[0].application_id = Some(Id::new(2));
+        registered[0].nsfw = Some(false);
+        registered[0].dm_permission = Some(true);
+
+        assert!(drift(&desired, &registered).is_empty());
+    }
+
+    #[test]
+    fn who_can_use_a_command_and_where_is_drift() {
+        let desired = [command("refresh-roles", "Refresh the roles")];
+
+        let mut registered = desired.clone();
+        registered[0].default_member_permissions = Some(Permissions::MANAGE_ROLES);
+        assert_eq!(drift(&desired, &registered).changed, ["refresh-roles"]);
+
+        let mut registered = desired.clone();
+        registered[0].contexts = Some(vec![InteractionContextType::Guild]);
+        assert_eq!(drift(&desired, &registered).changed, ["refresh-roles"]);
+
+        let mut registered = desired.clone();
+        registered[0].nsfw = Some(true);
+        assert_eq!(drift(&desired, &registered).changed, ["refresh-roles"]);
+    }
+
+    #[test]
+    fn drift_names_added_removed_and_changed_commands() {
+        let desired = [
+            command("new-release", "Post a new music release"),
+            command("check-roles", "Check the roles"),
+        ];
+        let registered = [
+            command("new-release", "Post a music release"),
+            command("old-command", "Not around anymore"),
+        ];
+
+        let drift = drift(&desired, &registered);
+        assert_eq!(drift.added, ["check-roles"]);
+        assert_eq!(drift.removed, ["old-command"]);
+        assert_eq!(drift.changed, ["new-release"]);
+    }
+
+    #[test]
+    fn an_entry_point_isnt_drift() {
+        let desired = [command("new-release", "Post a new music release")];
+        let mut entry_point = command("launch", "Launch the Activity");
+        entry_point.kind = PRIMARY_ENTRY_POINT;
+        let registered = [desired[0].clone(), entry_point];
+
+        assert!(drift(&desired, &registered).is_empty());
+        assert!(drift(&[], &registered[1..]).is_empty());
+    }
+
+    #[test]
+    fn error_embeds_have_the_configured_footer_and_link() {
+        let config = Config {
+            report_footer_text: "Tell the mods in #bot-help".to_owned(),
+            report_url: Some("https://example.com/report".to_owned()),
+            ..Config::default()
+        };
+
+        let embed = error_embed(HandlingError::MissingExpectedCommandData, &config);
+        assert_eq!(embed.color, Some(COLOR_ERROR));
+        assert_eq!(
+            embed.footer.map(|footer| footer.text).as_deref(),
+            Some("Tell the mods in #bot-help")
+        );
+        assert_eq!(embed.url.as_deref(), Some("https://example.com/report"));
+    }
+
+    #[test]
+    fn permissions_or_role_let_members_through() {
+        let role_id = Some(Id::new(20));
+
+        let interaction = member_interaction(Permissions::MANAGE_MESSAGES, &[]);
+        assert!(require_permissions(&interaction, Permissions::MANAGE_MESSAGES, role_id).is_ok());
+
+        let interaction = member_interaction(Permissions::empty(), &[20]);
+        assert!(require_permissions(&interaction, Permissions::MANAGE_MESSAGES, role_id).is_ok());
+    }
+
+    #[test]
+    fn missing_permissions_and_role_are_named() {
+        let interaction = member_interaction(Permissions::SEND_MESSAGES, &[21]);
+
+        let error = require_permissions(
+            &interaction,
+            Permissions::MANAGE_MESSAGES,
+            Some(Id::new(20)),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "you need the MANAGE_MESSAGES permission or the <@&20> role to use this command"
+        );
+    }
+
+    #[test]
+    fn no_permissions_means_only_the_role() {
+        let interaction = member_interaction(Permissions::all(), &[]);
+
+        assert!(require_permissions(&interaction, Permissions::empty(), None).is_err());
+        assert!(
+            require_permissions(&interaction, Permissions::empty(), Some(Id::new(20))).is_err()
+        );
+    }
+
+    #[test]
+    fn permissions_elsewhere_replace_the_members() {
+        let interaction = member_interaction(Permissions::MANAGE_MESSAGES, &[]);
+
+        assert!(
+            require_permissions_in(
+                &interaction,
+                Permissions::empty(),
+                Permissions::MANAGE_MESSAGES,
+                None
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn needs_a_member() {
+        let mut interaction = member_interaction(Permissions::all(), &[]);
+        interaction.member = None;
+
+        assert!(matches!(
+            require_permissions(&interaction, Permissions::SEND_MESSAGES, None),
+            Err(PermissionError::NotUsedByMember)
+        ));
+    }
+
+    #[tokio::test]
+    async fn error_footer_is_the_configured_one() {
+        let config = Config {
+            report_footer_text: "Tell the mods in #bot-help".to_owned(),
+            ..Config::default()
+        };
+
+        // a command used outside of a server
+        let mut interaction = member_interaction(Permissions::empty(), &[]);
+        interaction.guild_id = None;
+        let outside_server = new_release::handle(state(config.clone()), interaction).await;
+
+        // a command used by someone who isn't allowed to
+        let interaction = member_interaction(Permissions::empty(), &[]);
+        let not_allowed = check_roles::handle(state(config.clone()), interaction).await;
+
+        // a button this bot doesn't know about
+        let mut interaction = member_interaction(Permissions::empty(), &[]);
+        interaction.kind = InteractionType::MessageComponent;
+        interaction.data = Some(
+            serde_json::from_value(serde_json::json!({
+                "custom_id": "not-a-real-button",
+                "component_type": 2,
+            }))
+            .map(InteractionData::MessageComponent)
+            .unwrap(),
+        );
+        let unknown_component = handle_component(state(config), interaction);
+
+        let (CommandResponse::Failed(outside_server), CommandResponse::Failed(not_allowed)) =
+            (outside_server, not_allowed)
+        else {
+            panic!("expected both commands to fail");
+        };
+        for response in [outside_server, not_allowed, unknown_component] {
+            assert_eq!(footer_text(&response), Some("Tell the mods in #bot-help"));
+        }
+    }
+
+    /// An interaction for running the command named `name`, with no options
+    pub(crate) fn command_interaction(name: &str) -> Interaction {
+        let mut interaction = member_interaction(Permissions::all(), &[]);
+        interaction.kind = InteractionType::ApplicationCommand;
+        interaction.data = Some(
+            serde_json::from_value(serde_json::json!({
+                "id": "5",
+                "name": name,
+                "type": 1,
+            }))
+            .map(|command_data| InteractionData::ApplicationCommand(Box::new(command_data)))
+            .unwrap(),
+        );
+
+        interaction
+    }
+
+    #[test]
+    fn only_enabled_commands_are_registered() {
+        assert_eq!(enabled(None).unwrap().len(), all().len());
+
+        let enabled_commands = AHashSet::from_iter(["version".to_owned()]);
+        let commands = enabled(Some(&enabled_commands)).unwrap();
+        assert_eq!(
+            Vec::from_iter(commands.iter().map(|(command, _)| command.name.as_str())),
+            ["version"]
+        );
+
+        let enabled_commands = AHashSet::from_iter(["not-a-command".to_owned()]);
+        assert!(enabled(Some(&enabled_commands)).is_err());
+    }
+
+    #[test]
+    fn the_deadline_goes_by_when_discord_created_the_interaction() {
+        // the test interaction's ID is from 2015, so its token expired long ago
+        let mut interaction = member_interaction(Permissions::empty(), &[]);
+        assert!(response_deadline(&interaction) <= tokio::time::Instant::now());
+
+        let now_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        interaction.id = Id::new((u64::try_from(now_millis).unwrap() - DISCORD_EPOCH_MILLIS) << 22);
+
+        let remaining = response_deadline(&interaction) - tokio::time::Instant::now();
+        assert!(
+            remaining
+                > crate::INTERACTION_TOKEN_LIFETIME
+                    - crate::INTERACTION_TOKEN_LEEWAY
+                    - Duration::from_secs(5)
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use super::new_release::{
+    DigestEntryError, EmptyFormattedReleaseError, GetReleaseError, GetRolesMapError,
+    LabelPlacement, Release, assign_artist_roles, digest, digest_entry, format_release,
+    get_release, get_roles_map, strip_tracking_query_params,
+};
+use crate::{
+    Config,
+    command::{COLOR_SUCCESS, CommandResponse, State, error_response, response_deadline},
+};
+use futures::future;
+use iref::{IriRefBuf, iri::InvalidIriRef};
+use snafu::{OptionExt, Report, ResultExt, Snafu, ensure};
+use std::{collections::BTreeMap, sync::LazyLock};
+use tokio::sync::Semaphore;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::{
+            Interaction, InteractionData,
+            application_command::{CommandDataOption, CommandOptionValue},
+        },
+    },
+    channel::message::MessageFlags,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+};
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    command::{BooleanBuilder, CommandBuilder, StringBuilder},
+    embed::EmbedBuilder,
+};
+
+const NAME: &str = "batch";
+const DESCRIPTION: &str = "Format several music releases at once, like for a roundup";
+
+const URLS_NAME: &str = "urls";
+const URLS_DESCRIPTION: &str = "The URLs to the releases, separated by spaces or new lines";
+
+const DIGEST_NAME: &str = "digest";
+const DIGEST_DESCRIPTION: &str =
+    "Group the releases by type under headers, split into messages that can each be posted";
+
+const MAX_URLS: usize = 10;
+/// So that one batch doesn't hammer Spotify or anyone else's website
+const MAX_CONCURRENT_FETCHES: usize = 4;
+
+// https://discord.com/developers/docs/resources/message#create-message-jsonform-params
+const CONTENT_LIMIT: usize = 2000;
+// https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+const EMBEDS_TOTAL_LIMIT: usize = 6000;
+const EMBEDS_PER_MESSAGE_LIMIT: usize = 10;
+
+const ENTRY_SEPARATOR: &str = "\n\n";
+/// The code block fences around each page, plus its "Content (1/2)" title
+const PAGE_OVERHEAD: usize = "```\n\n```".len() + "Content (10/10)".len();
+/// The code block fences around each message of a digest, plus its "Digest (1/2)" title
+const DIGEST_PAGE_OVERHEAD: usize = "```\n\n```".len() + "Digest (10/10)".len();
+
+pub static COMMAND: LazyLock<Command> = LazyLock::new(|| {
+    CommandBuilder::new(NAME, DESCRIPTION, CommandType::ChatInput)
+        .option(StringBuilder::new(URLS_NAME, URLS_DESCRIPTION).required(true))
+        .option(BooleanBuilder::new(DIGEST_NAME, DIGEST_DESCRIPTION))
+        .validate()
+        .expect("command wasn't correct")
+        .build()
+});
+
+#[derive(Debug, Snafu)]
+enum HandleError {
+    /// the command was run outside of a Discord server
+    NotUsedInGuild,
+
+    /// the `urls` argument wasn't provided
+    UrlsMissing,
+
+    /// the `urls` argument wasn't a string like it's supposed to be, it was actually {actual:?}
+    UrlsNotString { actual: CommandOptionValue },
+
+    /// the `digest` argument wasn't a boolean like it's supposed to be, it was actually {actual:?}
+    DigestNotBoolean { actual: CommandOptionValue },
+
+    /// the `urls` argument didn't have any URLs in it
+    NoUrls,
+
+    /// only up to {MAX_URLS} URLs can be formatted at once, but {count} were given
+    TooManyUrls { count: usize },
+
+    /// couldn't get the roles in this server from Discord
+    RolesMapError { source: GetRolesMapError },
+}
+
+#[derive(Debug, Snafu)]
+enum FetchUrlError {
+    /// couldn't be parsed as a URL
+    UrlParseError { source: InvalidIriRef<String> },
+
+    /// couldn't get the release data
+    ReleaseError { source: GetReleaseError },
+
+    /// couldn't format the release into something worth posting
+    EmptyFormattedReleaseError { source: EmptyFormattedReleaseError },
+
+    /// couldn't add the release to the digest
+    DigestEntryError { source: DigestEntryError },
+}
+
+#[tracing::instrument(skip(spotify_client, http_client, fetch_permits), ret)]
+async fn fetch_url(
+    spotify_client: Option<&rspotify::ClientCredsSpotify>,
+    http_client: &reqwest::Client,
+    fetch_permits: &Semaphore,
+    config: &Config,
+    url: &str,
+    deadline: tokio::time::Instant,
+) -> Result<Release, FetchUrlError> {
+    let url = IriRefBuf::new(url.to_owned()).context(UrlParseSnafu)?;
+    let url = strip_tracking_query_params(url);
+
+    get_release(
+        spotify_client,
+        http_client,
+        fetch_permits,
+        config,
+        url,
+        deadline,
+    )
+    .await
+    .context(ReleaseSnafu)
+}
+
+fn failure_text(url: &str, error: FetchUrlError) -> String {
+    format!("⚠️ <{url}>: {}", Report::from_error(error))
+}
+
+/// Breaks up code fences in text that's shown in a code block, since one would otherwise end the block early
+/// (and error messages can quote whatever a page had in it)
+fn defuse_code_fences(text: &str) -> String {
+    text.replace("```", "`\u{200B}`\u{200B}`")
+}
+
+/// A formatted release, or why it couldn't be formatted, for one of the given URLs
+#[derive(Debug)]
+struct Entry {
+    url: String,
+    text: String,
+}
+
+/// Packs the entries, in order, into as few embed descriptions as Discord's limits allow,
+/// returning the pages and whichever entries didn't fit in the message at all
+fn paginate(entries: Vec<Entry>) -> (Vec<String>, Vec<Entry>) {
+    let mut pages: Vec<(String, usize)> = Vec::new();
+    let mut total_length = 0;
+    let mut left_out = Vec::new();
+
+    for entry in entries {
+        let entry_length = entry.text.chars().count();
+
+        let appended_length = ENTRY_SEPARATOR.len() + entry_length;
+        let fits_on_last_page = pages.last().is_some_and(|(_page, page_length)| {
+            page_length + appended_length + PAGE_OVERHEAD <= EMBED_DESCRIPTION_LIMIT
+        }) && total_length + appended_length <= EMBEDS_TOTAL_LIMIT;
+
+        let fits_on_new_page = pages.len() < EMBEDS_PER_MESSAGE_LIMIT
+            && entry_length + PAGE_OVERHEAD <= EMBED_DESCRIPTION_LIMIT
+            && total_length + entry_length + PAGE_OVERHEAD <= EMBEDS_TOTAL_LIMIT;
+
+        if fits_on_last_page {
+            let (page, page_length) = pages.last_mut().expect("it was just checked to exist");
+            page.push_str(ENTRY_SEPARATOR);
+            page.push_str(&entry.text);
+            *page_length += appended_length;
+            total_length += appended_length;
+        } else if fits_on_new_page {
+            pages.push((entry.text, entry_length));
+            total_length += entry_length + PAGE_OVERHEAD;
+        } else {
+            left_out.push(entry);
+        }
+    }
+
+    let pages = pages.into_iter().map(|(page, _length)| page).collect();
+
+    (pages, left_out)
+}
+
+#[tracing::instrument(
+    skip(
+        discord_client,
+        spotify_client,
+        http_client,
+        fetch_permits,
+        roles_cache
+    ),
+    ret
+)]
+async fn handle_impl(
+    State {
+        discord_client,
+        spotify_client,
+        http_client,
+        fetch_permits,
+        roles_cache,
+        config,
+        ..
+    }: State,
+    interaction: Interaction,
+) -> Result<InteractionResponse, HandleError> {
+    let guild_id = interaction.guild_id.context(NotUsedInGuildSnafu)?;
+    let deadline = response_deadline(&interaction);
+
+    let InteractionData::ApplicationCommand(command_data) = interaction.data.unwrap() else {
+        panic!(
+            "this is a command handler so it should be impossible for the interaction data not to be for an application command invocation"
+        );
+    };
+    let command_data = *command_data;
+
+    let mut options = BTreeMap::from_iter(
+        command_data
+            .options
+            .into_iter()
+            .map(|CommandDataOption { name, value }| (name, value)),
+    );
+
+    let urls = match options.remove(URLS_NAME).context(UrlsMissingSnafu)? {
+        CommandOptionValue::String(urls) => urls,
+        other => {
+            return Err(HandleError::UrlsNotString { actual: other });
+        }
+    };
+    let urls = Vec::from_iter(urls.split_whitespace());
+
+    let digest_requested = match options.remove(DIGEST_NAME) {
+        None => false,
+        Some(CommandOptionValue::Boolean(digest_requested)) => digest_requested,
+        Some(other) => return Err(HandleError::DigestNotBoolean { actual: other }),
+    };
+
+    ensure!(!urls.is_empty(), NoUrlsSnafu);
+    ensure!(
+        urls.len() <= MAX_URLS,
+        TooManyUrlsSnafu { count: urls.len() }
+    );
+
+    let roles_map = get_roles_map(&discord_client, &roles_cache, guild_id)
+        .await
+        .context(RolesMapSnafu)?;
+
+    let semaphore = &Semaphore::new(MAX_CONCURRENT_FETCHES);
+    let spotify_client = spotify_client.as_deref();
+    let (http_client, fetch_permits, config) = (&http_client, &fetch_permits, &config);
+
+    let results = future::join_all(urls.into_iter().map(|url| async move {
+        let _permit = semaphore
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+
+        let result = fetch_url(
+            spotify_client,
+            http_client,
+            fetch_permits,
+            config,
+            url,
+            deadline,
+        )
+        .await
+        .map(|mut release| {
+            assign_artist_roles(&mut release, &config.artist_roles, guild_id);
+            release
+        });
+
+        (url, result)
+    }))
+    .await;
+
+    let interaction_response_data = if digest_requested {
+        let mut entries = Vec::new();
+        let mut failures = Vec::new();
+        for (url, result) in results {
+            let result = result.and_then(|release| {
+                digest_entry(release, &roles_map, config).context(DigestEntrySnafu)
+            });
+
+            match result {
+                Ok(entry) => entries.push(entry),
+                Err(error) => failures.push(failure_text(url, error)),
+            }
+        }
+
+        digest_response(digest(entries), failures)
+    } else {
+        let entries = Vec::from_iter(results.into_iter().map(|(url, result)| {
+            let result = result.and_then(|release| {
+                let formatted_release =
+                    format_release(release, &roles_map, config, LabelPlacement::Inline);
+                formatted_release
+                    .validate(config)
+                    .context(EmptyFormattedReleaseSnafu)?;
+                Ok(formatted_release.message)
+            });
+            let text = match result {
+                Ok(message) => message,
+                Err(error) => failure_text(url, error),
+            };
+
+            Entry {
+                url: url.to_owned(),
+                text: defuse_code_fences(&text),
+            }
+        }));
+
+        entries_response(entries)
+    };
+
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
+}
+
+/// Each release formatted on its own, packed into as few embeds as fit
+fn entries_response(entries: Vec<Entry>) -> InteractionResponseData {
+    let (pages, left_out) = paginate(entries);
+
+    let n_pages = pages.len();
+    let embeds = pages.into_iter().enumerate().map(|(i, page)| {
+        EmbedBuilder::new()
+            .color(COLOR_SUCCESS)
+            .title(format!("Content ({}/{n_pages})", i + 1))
+            .description(format!("```\n{page}\n```"))
+            .build()
+    });
+
+    let mut interaction_response_data = InteractionResponseDataBuilder::new();
+    if !left_out.is_empty() {
+        let lines = Vec::from_iter(
+            std::iter::once(
+                "These didn't fit in this message, so run them again separately:".to_owned(),
+            )
+            .chain(left_out.iter().map(|entry| format!("- <{}>", entry.url))),
+        );
+        interaction_response_data = interaction_response_data.content(content_from_lines(lines));
+    }
+
+    interaction_response_data
+        .embeds(embeds)
+        .flags(MessageFlags::EPHEMERAL)
+        .build()
+}
+
+/// Joins the lines into a message's content, leaving off whichever don't fit and saying how many that was.
+/// A first line that doesn't fit on its own is cut short instead, so there's always something to read
+fn content_from_lines(lines: Vec<String>) -> String {
+    let n_lines = lines.len();
+    let more_note = |n_more: usize| format!("\n…and {n_more} more");
+    // the longest the note could be, so there's always room for it
+    let note_room = more_note(n_lines).chars().count();
+
+    let mut content = String::new();
+    let mut content_length = 0;
+    for (i, line) in lines.into_iter().enumerate() {
+        let separator = if content.is_empty() { "" } else { "\n" };
+        let line_length = separator.len() + line.chars().count();
+        let is_last = i + 1 == n_lines;
+        let room = if is_last { 0 } else { note_room };
+
+        if content_length + line_length + room <= CONTENT_LIMIT {
+            content.push_str(separator);
+            content.push_str(&line);
+            content_length += line_length;
+        } else if content.is_empty() {
+            let cut_line = line
+                .chars()
+                .take(CONTENT_LIMIT - note_room - "…".chars().count())
+                .collect::<String>();
+            content = format!("{cut_line}…");
+            content_length = content.chars().count();
+        } else {
+            content.push_str(&more_note(n_lines - i));
+            break;
+        }
+    }
+
+    content
+}
+
+/// Each message of the digest in an embed of its own, so they can be copied and posted one at a time
+fn digest_response(messages: Vec<String>, failures: Vec<String>) -> InteractionResponseData {
+    let messages = Vec::from_iter(messages.iter().map(|message| defuse_code_fences(message)));
+
+    let mut pages = Vec::new();
+    let mut total_length = 0;
+    for message in &messages {
+        let page_length = message.chars().count() + DIGEST_PAGE_OVERHEAD;
+        if pages.len() == EMBEDS_PER_MESSAGE_LIMIT
+            || total_length + page_length > EMBEDS_TOTAL_LIMIT
+        {
+            break;
+        }
+
+        pages.push(message);
+        total_length += page_length;
+    }
+
+    let n_messages = messages.len();
+    let all_fit = pages.len() == n_messages;
+    let embeds = pages.into_iter().enumerate().map(|(i, page)| {
+        EmbedBuilder::new()
+            .color(COLOR_SUCCESS)
+            .title(format!("Digest ({}/{n_messages})", i + 1))
+            .description(format!("```\n{page}\n```"))
+            .build()
+    });
+
+    // the note about the digest comes first, since it's the one that can't be left off when there are too many failures to list
+    let mut notes = Vec::new();
+    if !all_fit {
+        notes.push(
+            "The rest of the digest didn't fit in this message, so run it again with fewer URLs."
+                .to_owned(),
+        );
+    }
+    notes.extend(failures);
+
+    let mut interaction_response_data = InteractionResponseDataBuilder::new();
+    if !notes.is_empty() {
+        interaction_response_data = interaction_response_data.content(content_from_lines(notes));
+    }
+
+    interaction_response_data
+        .embeds(embeds)
+        .flags(MessageFlags::EPHEMERAL)
+        .build()
+}
+
+#[tracing::instrument]
+pub async fn handle(state: State, interaction: Interaction) -> CommandResponse {
+    let config = state.config.clone();
+
+    match handle_impl(state, interaction).await {
+        Ok(interaction_response) => CommandResponse::Succeeded(interaction_response),
+        Err(error) => CommandResponse::Failed(error_response(error, &config)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str, text: &str) -> Entry {
+        Entry {
+            url: url.to_owned(),
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn code_fences_are_broken_up() {
+        let defused = defuse_code_fences("⚠️ <https://example.com>: the page said ```nope```");
+
+        assert!(!defused.contains("```"));
+        assert_eq!(
+            defused.replace('\u{200B}', ""),
+            "⚠️ <https://example.com>: the page said ```nope```"
+        );
+    }
+
+    #[test]
+    fn entries_share_pages_until_a_page_is_full() {
+        let long = "a".repeat(EMBED_DESCRIPTION_LIMIT - PAGE_OVERHEAD - "first".len());
+        let (pages, left_out) = paginate(vec![
+            entry("https://example.com/1", "first"),
+            entry("https://example.com/2", "second"),
+            entry("https://example.com/3", &long),
+        ]);
+
+        assert_eq!(pages, ["first\n\nsecond".to_owned(), long]);
+        assert!(left_out.is_empty());
+    }
+
+    #[test]
+    fn entries_past_the_message_limit_are_left_out() {
+        let long = "a".repeat(EMBED_DESCRIPTION_LIMIT - PAGE_OVERHEAD);
+        let (pages, left_out) = paginate(vec![
+            entry("https://example.com/1", &long),
+            entry("https://example.com/2", &long),
+        ]);
+
+        assert_eq!(pages.len(), 1);
+        let left_out_urls = Vec::from_iter(left_out.iter().map(|entry| entry.url.as_str()));
+        assert_eq!(left_out_urls, ["https://example.com/2"]);
+    }
+
+    #[test]
+    fn content_lines_that_fit_are_all_kept() {
+        let content = content_from_lines(vec!["one".to_owned(), "two".to_owned()]);
+
+        assert_eq!(content, "one\ntwo");
+    }
+
+    #[test]
+    fn content_lines_past_the_limit_are_counted_instead() {
+        let lines = Vec::from_iter(
+            (0..10).map(|i| format!("⚠️ <https://example.com/{i}>: {}", "x".repeat(400))),
+        );
+        let content = content_from_lines(lines);
+
+        assert!(content.chars().count() <= CONTENT_LIMIT);
+        assert!(content.ends_with("\n…and 6 more"), "{content}");
+    }
+
+    #[test]
+    fn a_first_content_line_too_long_to_fit_is_cut_short() {
+        let content = content_from_lines(vec!["x".repeat(3000), "after".to_owned()]);
+
+        assert!(content.chars().count() <= CONTENT_LIMIT);
+        assert!(content.ends_with("x…\nafter"), "{content}");
+    }
+
+    #[test]
+    fn digest_response_content_fits_in_a_message() {
+        let failures = Vec::from_iter(
+            (0..10).map(|i| format!("⚠️ <https://example.com/{i}>: {}", "x".repeat(1000))),
+        );
+        let response = digest_response(vec!["## Singles\nsomething".to_owned()], failures);
+
+        let content = response.content.unwrap();
+        assert!(content.chars().count() <= CONTENT_LIMIT);
+        assert!(content.starts_with("⚠️ <https://example.com/0>"));
+        assert_eq!(response.embeds.map(|embeds| embeds.len()), Some(1));
+    }
+}
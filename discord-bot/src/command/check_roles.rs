@@ -0,0 +1,247 @@
+use super::new_release::{
+    GetReleaseError, GetRolesMapError, get_release, get_roles_map, strip_tracking_query_params,
+};
+use crate::command::{
+    COLOR_SUCCESS, CommandResponse, PermissionError, State, error_response, require_permissions,
+    response_deadline,
+};
+use iref::{IriRefBuf, iri::InvalidIriRef};
+use snafu::{OptionExt, ResultExt, Snafu, futures::TryFutureExt};
+use std::{collections::BTreeMap, sync::LazyLock};
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::{
+            Interaction, InteractionData,
+            application_command::{CommandDataOption, CommandOptionValue},
+        },
+    },
+    channel::message::MessageFlags,
+    guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    command::{CommandBuilder, StringBuilder},
+    embed::{EmbedBuilder, EmbedFieldBuilder},
+};
+use uncased::UncasedStr;
+
+const NAME: &str = "check-roles";
+const DESCRIPTION: &str =
+    "List which artists and labels in a release have a role in this server and which don't";
+
+const URL_NAME: &str = "url";
+const URL_DESCRIPTION: &str = "The URL to the release, like you'd give to /new-release";
+
+// https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const EMBED_FIELD_VALUE_LIMIT: usize = 1024;
+
+pub static COMMAND: LazyLock<Command> = LazyLock::new(|| {
+    CommandBuilder::new(NAME, DESCRIPTION, CommandType::ChatInput)
+        .option(StringBuilder::new(URL_NAME, URL_DESCRIPTION).required(true))
+        .validate()
+        .expect("command wasn't correct")
+        .build()
+});
+
+#[derive(Debug, Snafu)]
+enum HandleError {
+    /// the command was run outside of a Discord server
+    NotUsedInGuild,
+
+    /// you aren't allowed to check roles
+    NotAllowed { source: PermissionError },
+
+    /// the `url` argument wasn't provided
+    UrlMissing,
+
+    /// the `url` argument wasn't a string like it's supposed to be, it was actually {actual:?}
+    UrlNotString { actual: CommandOptionValue },
+
+    /// the `url` argument couldn't be parsed as a URL
+    UrlParseError { source: InvalidIriRef<String> },
+
+    /// couldn't get the roles in this server from Discord
+    RolesMapError { source: GetRolesMapError },
+
+    /// couldn't get the release data
+    ReleaseError { source: GetReleaseError },
+}
+
+/// The names as a list that fits in an embed field, ending with "+N more" for however many didn't fit
+fn list_or_none(names: &[&str]) -> String {
+    if names.is_empty() {
+        return "(none)".to_owned();
+    }
+
+    let more_note = |n_more: usize| format!("\n+{n_more} more");
+    // the longest the note could be, so there's always room for it
+    let note_room = more_note(names.len()).chars().count();
+
+    let mut list = String::new();
+    let mut list_length = 0;
+    for (i, name) in names.iter().enumerate() {
+        let item = if list.is_empty() {
+            format!("- {name}")
+        } else {
+            format!("\n- {name}")
+        };
+        let item_length = item.chars().count();
+        let is_last = i + 1 == names.len();
+        let room = if is_last { 0 } else { note_room };
+
+        if list_length + item_length + room <= EMBED_FIELD_VALUE_LIMIT {
+            list.push_str(&item);
+            list_length += item_length;
+        } else if list.is_empty() {
+            // a name too long to fit on its own is cut short, so the list doesn't start with how many more there are
+            let cut_item = item
+                .chars()
+                .take(EMBED_FIELD_VALUE_LIMIT - note_room - "…".chars().count())
+                .collect::<String>();
+            list = format!("{cut_item}…");
+            list_length = list.chars().count();
+        } else {
+            list.push_str(&more_note(names.len() - i));
+            break;
+        }
+    }
+
+    list
+}
+
+#[tracing::instrument(
+    skip(
+        discord_client,
+        spotify_client,
+        http_client,
+        fetch_permits,
+        roles_cache
+    ),
+    ret
+)]
+async fn handle_impl(
+    State {
+        discord_client,
+        spotify_client,
+        http_client,
+        fetch_permits,
+        roles_cache,
+        config,
+        ..
+    }: State,
+    interaction: Interaction,
+) -> Result<InteractionResponse, HandleError> {
+    let guild_id = interaction.guild_id.context(NotUsedInGuildSnafu)?;
+    let deadline = response_deadline(&interaction);
+
+    require_permissions(
+        &interaction,
+        Permissions::MANAGE_ROLES,
+        config.check_roles_role_id,
+    )
+    .context(NotAllowedSnafu)?;
+
+    let InteractionData::ApplicationCommand(command_data) = interaction.data.unwrap() else {
+        panic!(
+            "this is a command handler so it should be impossible for the interaction data not to be for an application command invocation"
+        );
+    };
+    let command_data = *command_data;
+
+    let mut options = BTreeMap::from_iter(
+        command_data
+            .options
+            .into_iter()
+            .map(|CommandDataOption { name, value }| (name, value)),
+    );
+
+    let url = match options.remove(URL_NAME).context(UrlMissingSnafu)? {
+        CommandOptionValue::String(url) => url,
+        other => {
+            return Err(HandleError::UrlNotString { actual: other });
+        }
+    };
+    let url = IriRefBuf::new(url).context(UrlParseSnafu)?;
+    let url = strip_tracking_query_params(url);
+
+    let (roles_map, release) = tokio::try_join!(
+        get_roles_map(&discord_client, &roles_cache, guild_id).context(RolesMapSnafu),
+        get_release(
+            spotify_client.as_deref(),
+            &http_client,
+            &fetch_permits,
+            &config,
+            url,
+            deadline,
+        )
+        .context(ReleaseSnafu)
+    )?;
+
+    let (matched, unmatched): (Vec<_>, Vec<_>) = release
+        .credited_names()
+        .into_iter()
+        .partition(|name| roles_map.contains_key(UncasedStr::new(name)));
+
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .embeds([EmbedBuilder::new()
+            .color(COLOR_SUCCESS)
+            .title("Roles")
+            .field(EmbedFieldBuilder::new("Has a role", list_or_none(&matched)))
+            .field(EmbedFieldBuilder::new("No role", list_or_none(&unmatched)))
+            .build()])
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
+}
+
+#[tracing::instrument]
+pub async fn handle(state: State, interaction: Interaction) -> CommandResponse {
+    let config = state.config.clone();
+
+    match handle_impl(state, interaction).await {
+        Ok(interaction_response) => CommandResponse::Succeeded(interaction_response),
+        Err(error) => CommandResponse::Failed(error_response(error, &config)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_every_name_that_fits() {
+        assert_eq!(list_or_none(&[]), "(none)");
+        assert_eq!(
+            list_or_none(&["Harbor", "Lighthouse Recordings"]),
+            "- Harbor\n- Lighthouse Recordings"
+        );
+    }
+
+    #[test]
+    fn counts_the_names_that_dont_fit() {
+        let names = Vec::from_iter((0..100).map(|i| format!("Artist Number {i:03}")));
+        let names = Vec::from_iter(names.iter().map(String::as_str));
+
+        let list = list_or_none(&names);
+
+        assert!(list.chars().count() <= EMBED_FIELD_VALUE_LIMIT);
+        // each name takes 20 characters with its "- " and new line, which leaves room for 50 of them and the note
+        assert!(list.ends_with("- Artist Number 049\n+50 more"), "{list}");
+    }
+
+    #[test]
+    fn cuts_short_a_first_name_too_long_to_fit() {
+        let long_name = "x".repeat(2000);
+
+        let list = list_or_none(&[&long_name, "Harbor"]);
+
+        assert!(list.chars().count() <= EMBED_FIELD_VALUE_LIMIT);
+        assert!(list.ends_with("x…\n+1 more"), "{list}");
+    }
+}
@@ -0,0 +1,154 @@
+use super::new_release::{get_roles_map, has_valid_spotify_token};
+use crate::command::{COLOR_SUCCESS, CommandResponse, State, error_response, require_permissions};
+use snafu::{OptionExt, Report, Snafu, ensure};
+use std::sync::LazyLock;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::Interaction,
+    },
+    channel::message::MessageFlags,
+    guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    command::CommandBuilder,
+    embed::{EmbedBuilder, EmbedFieldBuilder},
+};
+
+const NAME: &str = "debug";
+const DESCRIPTION: &str =
+    "Show diagnostic info about this bot in this server (for the bot's operators)";
+
+pub static COMMAND: LazyLock<Command> = LazyLock::new(|| {
+    CommandBuilder::new(NAME, DESCRIPTION, CommandType::ChatInput)
+        .validate()
+        .expect("command wasn't correct")
+        .build()
+});
+
+#[derive(Debug, Snafu)]
+enum HandleError {
+    /// the command was run outside of a Discord server
+    NotUsedInGuild,
+
+    /// only the bot's owners (or people with the role configured for it) can use this command
+    NotAllowed,
+}
+
+/// Whether the person who ran the command owns the bot or has the role configured for debugging
+fn is_allowed(state: &State, interaction: &Interaction) -> bool {
+    let is_owner = interaction
+        .author_id()
+        .is_some_and(|user_id| state.discord_application_owner_ids.contains(&user_id));
+
+    let has_debug_role = require_permissions(
+        interaction,
+        Permissions::empty(),
+        state.config.debug_role_id,
+    )
+    .is_ok();
+
+    is_owner || has_debug_role
+}
+
+#[tracing::instrument(skip(state), ret)]
+async fn handle_impl(
+    state: State,
+    interaction: Interaction,
+) -> Result<InteractionResponse, HandleError> {
+    let guild_id = interaction.guild_id.context(NotUsedInGuildSnafu)?;
+
+    ensure!(is_allowed(&state, &interaction), NotAllowedSnafu);
+
+    let roles = match get_roles_map(&state.discord_client, &state.roles_cache, guild_id).await {
+        Ok(roles_map) => roles_map.len().to_string(),
+        Err(error) => format!("Couldn't fetch them: {}", Report::from_error(error)),
+    };
+
+    let spotify = match &state.spotify_client {
+        None => "Disabled".to_owned(),
+        Some(spotify_client) => {
+            if has_valid_spotify_token(spotify_client).await {
+                "Has a valid token".to_owned()
+            } else {
+                "No valid token (one will be requested when it's next needed)".to_owned()
+            }
+        }
+    };
+
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .embeds([EmbedBuilder::new()
+            .color(COLOR_SUCCESS)
+            .title("Debug")
+            .field(EmbedFieldBuilder::new("Server ID", guild_id.to_string()))
+            .field(EmbedFieldBuilder::new("Roles fetched", roles))
+            .field(EmbedFieldBuilder::new("Spotify", spotify))
+            .build()])
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
+}
+
+#[tracing::instrument]
+pub async fn handle(state: State, interaction: Interaction) -> CommandResponse {
+    let config = state.config.clone();
+
+    match handle_impl(state, interaction).await {
+        Ok(interaction_response) => CommandResponse::Succeeded(interaction_response),
+        Err(error) => CommandResponse::Failed(error_response(error, &config)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Config,
+        command::tests::{member_interaction, state},
+    };
+    use std::sync::Arc;
+    use twilight_model::id::Id;
+
+    fn ran_by_user(user_id: u64, roles: &[u64]) -> Interaction {
+        let mut interaction = member_interaction(Permissions::empty(), roles);
+        interaction.member.as_mut().unwrap().user = Some(
+            serde_json::from_value(serde_json::json!({
+                "id": user_id.to_string(),
+                "username": "someone",
+                "discriminator": "0",
+                "avatar": null,
+            }))
+            .unwrap(),
+        );
+
+        interaction
+    }
+
+    #[test]
+    fn owners_can_debug() {
+        let mut state = state(Config::default());
+        state.discord_application_owner_ids = Arc::new([Id::new(4)]);
+
+        assert!(is_allowed(&state, &ran_by_user(4, &[])));
+        assert!(!is_allowed(&state, &ran_by_user(5, &[])));
+    }
+
+    #[test]
+    fn the_debug_role_can_debug_when_configured() {
+        let with_role = state(Config {
+            debug_role_id: Some(Id::new(20)),
+            ..Config::default()
+        });
+        assert!(is_allowed(&with_role, &ran_by_user(5, &[20])));
+        assert!(!is_allowed(&with_role, &ran_by_user(5, &[21])));
+
+        let without_role = state(Config::default());
+        assert!(!is_allowed(&without_role, &ran_by_user(5, &[20])));
+    }
+}
@@ -1,44 +1,62 @@
-use crate::command::State;
-use ahash::AHashSet;
+use crate::{
+    ArtistRole, Config, ImageSize, LabelDisplay, MissingArtist, OverlappingCredits,
+    SpotifyAlbumTypeMapping, SpotifyReleaseType, TrackCountThresholds,
+    command::{
+        COLOR_SUCCESS, CommandResponse, ComponentAction, PermissionError, State, error_response,
+        require_permissions, require_permissions_in, response_deadline, single_button_row,
+    },
+    public_address::{is_non_public_address_error, is_non_public_url},
+    roles_cache::{RolesCache, RolesMap},
+};
+use ahash::{AHashMap, AHashSet};
 use chrono::Datelike;
 use deranged::RangedU8;
-use futures::TryStreamExt;
-use iref::{
-    Iri, IriRef, IriRefBuf,
-    iri::{InvalidIriRef, SegmentBuf},
-};
+use futures::{StreamExt, TryStreamExt, future};
+use iref::{IriRef, IriRefBuf, iri::InvalidIriRef};
 use itertools::Itertools;
 use nonempty::NonEmpty as NonEmptyVec;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use readformat::readf;
 use rspotify::{
-    model::{AlbumId, AlbumType, Id, IdError, PlaylistId, SimplifiedArtist, TrackId},
+    model::{
+        AlbumId, AlbumType, ArtistId, Id, IdError, Market, PlaylistId, SearchResult, SearchType,
+        SimplifiedArtist, SimplifiedTrack, TrackId,
+    },
     prelude::BaseClient,
 };
 use snafu::{OptionExt, Report, ResultExt, Snafu, ensure, futures::TryFutureExt};
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Display},
     num::ParseIntError,
-    sync::LazyLock,
+    str::FromStr,
+    sync::{Arc, LazyLock},
 };
 use time::{Date, OffsetDateTime, Time};
+use tokio::sync::Semaphore;
 use twilight_model::{
     application::{
         command::{Command, CommandType},
         interaction::{
-            Interaction, InteractionData,
+            Interaction, InteractionData, InteractionDataResolved,
             application_command::{CommandDataOption, CommandOptionValue},
         },
     },
-    channel::message::MessageFlags,
-    guild::Role,
+    channel::{
+        ChannelType,
+        message::{AllowedMentions, MentionType, MessageFlags},
+    },
+    guild::{Permissions, Role},
     http::interaction::{InteractionResponse, InteractionResponseType},
-    id::marker::GuildMarker,
+    id::{
+        Id as DiscordId,
+        marker::{ChannelMarker, GuildMarker, RoleMarker},
+    },
 };
 use twilight_util::builder::{
     InteractionResponseDataBuilder,
-    command::{CommandBuilder, StringBuilder},
-    embed::{EmbedBuilder, EmbedFooterBuilder},
+    command::{BooleanBuilder, ChannelBuilder, CommandBuilder, StringBuilder},
+    embed::{EmbedBuilder, EmbedFooterBuilder, ImageSource},
 };
 use uncased::{Uncased, UncasedStr};
 
@@ -49,16 +67,42 @@ const URL_NAME: &str = "url";
 const URL_DESCRIPTION: &str =
     "The URL to the release on Spotify or Bandcamp (only (known) services supported so far)";
 
+const CHANNEL_NAME: &str = "channel";
+const CHANNEL_DESCRIPTION: &str =
+    "Post the release directly in this channel or thread instead of showing it to you first";
+
+const ARTIST_NAME: &str = "artist";
+const ARTIST_DESCRIPTION: &str =
+    "Use these artists (like \"A, B & C\") instead of the ones found, rather than in addition";
+
+const PREVIEW_ONLY_NAME: &str = "preview-only";
+const PREVIEW_ONLY_DESCRIPTION: &str =
+    "Only show how the release will look, without the copyable version or instructions";
+
 pub static COMMAND: LazyLock<Command> = LazyLock::new(|| {
     CommandBuilder::new(NAME, DESCRIPTION, CommandType::ChatInput)
         .option(StringBuilder::new(URL_NAME, URL_DESCRIPTION).required(true))
+        .option(StringBuilder::new(ARTIST_NAME, ARTIST_DESCRIPTION))
+        .option(BooleanBuilder::new(
+            PREVIEW_ONLY_NAME,
+            PREVIEW_ONLY_DESCRIPTION,
+        ))
+        .option(
+            ChannelBuilder::new(CHANNEL_NAME, CHANNEL_DESCRIPTION).channel_types([
+                ChannelType::GuildText,
+                ChannelType::GuildAnnouncement,
+                ChannelType::PublicThread,
+                ChannelType::PrivateThread,
+                ChannelType::AnnouncementThread,
+            ]),
+        )
         .validate()
         .expect("command wasn't correct")
         .build()
 });
 
 #[derive(Debug, Clone)]
-enum SpotifyResource<'a> {
+pub(super) enum SpotifyResource<'a> {
     Album { id: AlbumId<'a> },
     Track { id: TrackId<'a> },
     Playlist { id: PlaylistId<'a> },
@@ -76,39 +120,66 @@ enum SpotifyResourceFromUrlError {
     MissingResourceId,
 
     /// the resource type ({kind:?}) in the URL is not one that I recognize (e.g. album)
-    UnrecognizedResourceType { kind: SegmentBuf },
+    UnrecognizedResourceType { kind: String },
 
     /// the resource ID in the URL ({id:?}) is not valid by Spotify's rules
     InvalidResourceId { id: String, source: IdError },
 }
 
+/// Recognizes both open.spotify.com links and the `spotify:album:<id>` URIs that Spotify's apps copy
 fn parse_spotify_resource<'a>(
     url: &'a IriRef,
 ) -> Result<SpotifyResource<'static>, SpotifyResourceFromUrlError> {
-    let base = Iri::new("https://open.spotify.com").expect("this is a valid URL");
-
-    ensure!(url.authority() == base.authority(), NotSpotifySnafu);
+    let is_uri = url
+        .scheme()
+        .is_some_and(|scheme| scheme.as_str().eq_ignore_ascii_case("spotify"));
+
+    let (kind, id) = if is_uri {
+        let mut parts = url
+            .path()
+            .as_str()
+            .split(':')
+            .filter(|part| !part.is_empty());
+
+        let kind = parts.next().context(MissingResourceTypeSnafu)?;
+        let id = parts.next().context(MissingResourceIdSnafu)?;
+        (kind, id)
+    } else {
+        ensure!(
+            url.authority().is_some_and(|authority| authority
+                .as_str()
+                .eq_ignore_ascii_case("open.spotify.com")),
+            NotSpotifySnafu
+        );
 
-    let resource_path = url.relative_to(base);
+        // empty segments come from trailing (or doubled) slashes, and don't mean anything
+        let mut segments = url
+            .path()
+            .segments()
+            .filter(|segment| !segment.as_str().is_empty())
+            .peekable();
 
-    let mut segments = resource_path.path().segments();
+        // localized links start with a segment for the language, like `/intl-es/album/...`
+        segments.next_if(|segment| segment.as_str().starts_with("intl-"));
 
-    let kind = segments.next().context(MissingResourceTypeSnafu)?;
-    let id = segments.next().context(MissingResourceIdSnafu)?;
+        let kind = segments.next().context(MissingResourceTypeSnafu)?;
+        let id = segments.next().context(MissingResourceIdSnafu)?;
+        (kind.as_str(), id.as_str())
+    };
 
-    match kind.as_str() {
+    match kind {
         "album" => Ok(SpotifyResource::Album {
-            id: AlbumId::from_id(id.as_str())
+            id: AlbumId::from_id(id)
                 .with_context(|_e| InvalidResourceIdSnafu { id: id.to_owned() })?
                 .into_static(),
         }),
         "playlist" => Ok(SpotifyResource::Playlist {
-            id: PlaylistId::from_id(id.as_str())
+            id: PlaylistId::from_id(id)
                 .with_context(|_e| InvalidResourceIdSnafu { id: id.to_owned() })?
                 .into_static(),
         }),
         "track" => Ok(SpotifyResource::Track {
-            id: TrackId::from_id(id.as_str())
+            id: TrackId::from_id(id)
                 .with_context(|_e| InvalidResourceIdSnafu { id: id.to_owned() })?
                 .into_static(),
         }),
@@ -118,9 +189,180 @@ fn parse_spotify_resource<'a>(
     }
 }
 
+/// The services with handling specific to them, to tell people what links work when theirs doesn't.
+///
+/// Anything that handles links from a service in its own way should be listed here (and described in [`Service::description`]),
+/// so that the list stays accurate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Service {
+    /// See [`parse_spotify_resource`]
+    Spotify,
+    /// See [`parse_bandcamp_page`]
+    Bandcamp,
+    /// See [`apple_music_embed_url`]
+    AppleMusic,
+    /// See [`is_smart_link`]
+    SmartLink,
+}
+
+impl Service {
+    const ALL: [Self; 4] = [
+        Self::Spotify,
+        Self::Bandcamp,
+        Self::AppleMusic,
+        Self::SmartLink,
+    ];
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Spotify => "Spotify albums and tracks",
+            Self::Bandcamp => "Bandcamp albums and tracks",
+            Self::AppleMusic => "Apple Music albums",
+            Self::SmartLink => "Songwhip and Linkfire (lnk.to and ffm.to) links to any of those",
+        }
+    }
+
+    /// Which service a URL is for, if it's one that's handled in its own way
+    fn of(url: &IriRef) -> Option<Self> {
+        if parse_spotify_resource(url).is_ok() {
+            Some(Self::Spotify)
+        } else if parse_bandcamp_page(url).is_some() {
+            Some(Self::Bandcamp)
+        } else if apple_music_embed_url(url).is_some() {
+            Some(Self::AppleMusic)
+        } else if is_smart_link(url) {
+            Some(Self::SmartLink)
+        } else {
+            None
+        }
+    }
+
+    /// Every supported service, followed by the catch-all of pages with structured data
+    fn supported_list() -> String {
+        Self::ALL
+            .into_iter()
+            .map(Self::description)
+            .chain(["other pages that describe their release with JSON-LD or OpenGraph data"])
+            .join("; ")
+    }
+}
+
+/// Which kind of page on Bandcamp a URL is for, which its path gives away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BandcampPage {
+    Album,
+    Track,
+}
+
+fn parse_bandcamp_page(url: &IriRef) -> Option<BandcampPage> {
+    let host = url.authority()?.host().as_str().to_ascii_lowercase();
+    if host != "bandcamp.com" && !host.ends_with(".bandcamp.com") {
+        return None;
+    }
+
+    match url.path().segments().next()?.as_str() {
+        "album" => Some(BandcampPage::Album),
+        "track" => Some(BandcampPage::Track),
+        _ => None,
+    }
+}
+
+/// The `embed.music.apple.com` version of an Apple Music page, which has the same path and sometimes has JSON-LD when the page itself doesn't.
+///
+/// Apple's AMP versions of pages aren't tried, since they aren't served at a URL that can be worked out from the page's.
+fn apple_music_embed_url(url: &IriRef) -> Option<IriRefBuf> {
+    let host = url.authority()?.host().as_str();
+    if !host.eq_ignore_ascii_case("music.apple.com") {
+        return None;
+    }
+
+    let query = url
+        .query()
+        .map(|query| format!("?{}", query.as_str()))
+        .unwrap_or_default();
+
+    IriRefBuf::new(format!(
+        "https://embed.music.apple.com{}{query}",
+        url.path().as_str()
+    ))
+    .ok()
+}
+
+/// Hosts of "smart links", which are pages that link to a release on every service it's on
+const SMART_LINK_HOSTS: &[&str] = &["songwhip.com", "lnk.to", "ffm.to"];
+
+fn is_smart_link(url: &IriRef) -> bool {
+    url.authority().is_some_and(|authority| {
+        let host = authority.host().as_str().to_ascii_lowercase();
+        SMART_LINK_HOSTS.iter().any(|smart_link_host| {
+            host == *smart_link_host
+                || host
+                    .strip_suffix(smart_link_host)
+                    .is_some_and(|subdomains| subdomains.ends_with('.'))
+        })
+    })
+}
+
+/// Every link in a smart link's page to a release on a service this bot handles, in the order they appear.
+///
+/// Smart link pages keep their links in JSON blobs of different shapes,
+/// so this looks for anything URL-shaped rather than going through any one of them
+fn service_links_in_page(page: &str) -> Vec<(Service, IriRefBuf)> {
+    // JSON can escape the slashes in URLs
+    let page = page.replace("\\/", "/");
+    let ends_url = |c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | '\\' | '`');
+
+    page.match_indices("https://")
+        .filter_map(|(start, _)| {
+            let link = &page[start..];
+            let link = link.find(ends_url).map_or(link, |end| &link[..end]);
+            let link = IriRefBuf::new(link.to_owned()).ok()?;
+
+            match Service::of(link.as_iri_ref())? {
+                Service::SmartLink => None,
+                service => Some((service, strip_tracking_query_params(link))),
+            }
+        })
+        .unique_by(|(_service, link)| link.as_str().to_owned())
+        .collect()
+}
+
+fn is_tracking_query_param(key: &str) -> bool {
+    key == "si" || key == "from" || key.starts_with("utm_")
+}
+
+pub(super) fn strip_tracking_query_params(url: IriRefBuf) -> IriRefBuf {
+    let (without_fragment, fragment) = try_split_once(url.as_str(), "#");
+    let (before_query, query) = try_split_once(without_fragment, "?");
+
+    let Some(query) = query else {
+        return url;
+    };
+
+    let kept_params = query
+        .split('&')
+        .filter(|param| {
+            let (key, _value) = try_split_once(param, "=");
+            !param.is_empty() && !is_tracking_query_param(key)
+        })
+        .join("&");
+
+    let mut stripped = before_query.to_owned();
+    if !kept_params.is_empty() {
+        stripped = format!("{stripped}?{kept_params}");
+    }
+    if let Some(fragment) = fragment {
+        stripped = format!("{stripped}#{fragment}");
+    }
+
+    IriRefBuf::new(stripped)
+        .expect("removing query parameters from a valid URL still leaves a valid URL")
+}
+
 type Year = u16;
 type Month = RangedU8<1, 12>;
-type Day = RangedU8<1, 32>;
+// deranged's ranges are inclusive
+type Day = RangedU8<1, 31>;
 
 type DayResult = Result<Day, deranged::ParseIntError>;
 type MonthResult = Result<(Month, Option<DayResult>), deranged::ParseIntError>;
@@ -133,8 +375,13 @@ fn try_split_once<'a>(string: &'a str, delimiter: &'a str) -> (&'a str, Option<&
     }
 }
 
+/// Spotify leaves the date empty or zeroed out (e.g. `0000`) for albums that haven't been given one
+fn is_placeholder_date(date: &str) -> bool {
+    date.trim().is_empty() || matches!(parse_date(date), Ok((0, _)))
+}
+
 fn parse_date(date: &str) -> YearResult {
-    let (year, month_and_date) = try_split_once(date, "-");
+    let (year, month_and_date) = try_split_once(date.trim(), "-");
 
     year.parse().map(|year| {
         (
@@ -149,35 +396,8 @@ fn parse_date(date: &str) -> YearResult {
     })
 }
 
-const COLOR_RED_500: u32 = 0xef4444;
-const COLOR_PINK_500: u32 = 0xec4899;
-
-const COLOR_ERROR: u32 = COLOR_RED_500;
-const COLOR_SUCCESS: u32 = COLOR_PINK_500;
-
-impl From<HandleError> for InteractionResponse {
-    fn from(error: HandleError) -> Self {
-        let embed = EmbedBuilder::new()
-            .color(COLOR_ERROR)
-            .title("Error")
-            .description(Report::from_error(error).to_string())
-            .footer(EmbedFooterBuilder::new("Please report this to J / Navith!").build())
-            .build();
-
-        let interaction_response_data = InteractionResponseDataBuilder::new()
-            .embeds([embed])
-            .flags(MessageFlags::EPHEMERAL)
-            .build();
-
-        InteractionResponse {
-            kind: InteractionResponseType::ChannelMessageWithSource,
-            data: Some(interaction_response_data),
-        }
-    }
-}
-
 #[derive(Debug, Snafu)]
-enum GetRolesMapError {
+pub(super) enum GetRolesMapError {
     /// could not fetch the roles in this Discord server
     FetchRolesError { source: twilight_http::Error },
 
@@ -187,11 +407,26 @@ enum GetRolesMapError {
     },
 }
 
-#[tracing::instrument(skip(discord_client), ret)]
-async fn get_roles_map(
+/// The server's roles from `roles_cache` if they're there, and otherwise fetched (and cached for next time)
+#[tracing::instrument(skip(discord_client, roles_cache))]
+pub(super) async fn get_roles_map(
+    discord_client: &twilight_http::Client,
+    roles_cache: &RolesCache,
+    guild_id: twilight_model::id::Id<GuildMarker>,
+) -> Result<Arc<RolesMap>, GetRolesMapError> {
+    match roles_cache.get(guild_id) {
+        Some(roles_map) => Ok(roles_map),
+        None => refresh_roles_map(discord_client, roles_cache, guild_id).await,
+    }
+}
+
+/// Fetches the server's roles even if they're cached, replacing what was cached
+#[tracing::instrument(skip(discord_client, roles_cache), ret)]
+pub(super) async fn refresh_roles_map(
     discord_client: &twilight_http::Client,
+    roles_cache: &RolesCache,
     guild_id: twilight_model::id::Id<GuildMarker>,
-) -> Result<BTreeMap<Uncased<'static>, Role>, GetRolesMapError> {
+) -> Result<Arc<RolesMap>, GetRolesMapError> {
     let roles = discord_client
         .roles(guild_id)
         .await
@@ -200,55 +435,236 @@ async fn get_roles_map(
         .await
         .context(DeserializeRolesSnafu)?;
 
-    Ok(roles
-        .into_iter()
-        .map(|role| (Uncased::from(role.name.as_str()).into_owned(), role))
-        .collect())
+    let roles_map =
+        Arc::new(RolesMap::from_iter(roles.into_iter().map(|role| {
+            (Uncased::from(role.name.as_str()).into_owned(), role)
+        })));
+    roles_cache.insert(guild_id, roles_map.clone());
+
+    Ok(roles_map)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How a release is labelled in the posted message.
+///
+/// Every kind a data source can report maps onto one of these deliberately:
+/// Spotify's "appears on" albums are handled like its singles (a single, or an EP once there are more tracks than a single is configured to have),
+/// and broadcasts (radio shows, podcast episodes, and the like) aren't music releases at all, so they're rejected instead of being given a type.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum ReleaseType {
     Single,
     EP,
     LP,
     Compilation,
     Remixes,
+    /// Only recognized when `recognize_music_videos` is configured, since the commands are about releases otherwise
+    Video,
+    /// Whatever else a release is called (like in an override), shown just as it was written
     Other(String),
 }
 
+impl Display for ReleaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseType::Single => write!(f, "Single"),
+            ReleaseType::EP => write!(f, "EP"),
+            ReleaseType::LP => write!(f, "LP"),
+            ReleaseType::Compilation => write!(f, "Compilation"),
+            ReleaseType::Remixes => write!(f, "Remixes"),
+            ReleaseType::Video => write!(f, "Music Video"),
+            ReleaseType::Other(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(display("{s:?} is blank, so it can't be a release type"))]
+struct ParseReleaseTypeError {
+    s: String,
+}
+
+impl FromStr for ReleaseType {
+    type Err = ParseReleaseTypeError;
+
+    /// Accepts what [`ReleaseType`]'s `Display` shows, along with "album" for an LP and "video" for a music video, ignoring case.
+    /// Any other text is kept as [`ReleaseType::Other`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let release_type = match s.trim().to_lowercase().as_str() {
+            "" => return ParseReleaseTypeSnafu { s }.fail(),
+            "single" => ReleaseType::Single,
+            "ep" => ReleaseType::EP,
+            "lp" | "album" => ReleaseType::LP,
+            "compilation" => ReleaseType::Compilation,
+            "remixes" => ReleaseType::Remixes,
+            "music video" | "video" => ReleaseType::Video,
+            _ => ReleaseType::Other(s.trim().to_owned()),
+        };
+
+        Ok(release_type)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Artist {
+    /// Only set when the data source has real IDs for artists, since names alone can't tell apart different artists
     id: Option<String>, // TODO: I just didn't want to deal with generics
     name: String,
+    /// The role configured for this artist's ID in the server, which is pinged instead of the role named after them
+    role_id: Option<DiscordId<RoleMarker>>,
+}
+
+/// What makes two credited artists the same artist, for deduplicating them:
+/// real IDs when there are any, and otherwise exactly matching names
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ArtistKey {
+    Id(String),
+    Name(String),
+}
+
+impl Artist {
+    fn key(&self) -> ArtistKey {
+        match &self.id {
+            Some(id) => ArtistKey::Id(id.clone()),
+            None => ArtistKey::Name(self.name.clone()),
+        }
+    }
+
+    /// The artist's ID as it appears in open.spotify.com links, if they're from Spotify
+    fn spotify_id(&self) -> Option<&str> {
+        let id = self.id.as_deref()?;
+        Some(id.strip_prefix("spotify:artist:").unwrap_or(id))
+    }
 }
 
 #[derive(Debug, Clone)]
 struct Track {
+    /// Where the track is in the release (or on its disc, when there's one), counting from 1
+    position: Option<u32>,
+    /// Which disc the track is on, counting from 1, when the data source groups tracks by disc
+    disc_number: Option<u32>,
+    title: Option<String>,
     artists: Vec<Artist>,
+    /// Whether `artists` was copied from the release's main artists because the track isn't credited on its own
+    artists_inherited: bool,
+}
+
+fn release_type_from_track_count(n_tracks: usize, thresholds: TrackCountThresholds) -> ReleaseType {
+    if n_tracks <= thresholds.single_max_tracks {
+        ReleaseType::Single
+    } else if n_tracks <= thresholds.ep_max_tracks {
+        ReleaseType::EP
+    } else {
+        ReleaseType::LP // TODO: distinguish compilations
+    }
+}
+
+/// A part of a release that a data source wasn't able to provide, so it had to be left blank or guessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ReleaseField {
+    Type,
+    Date,
+    Artists,
+    Tracks,
+}
+
+impl Display for ReleaseField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseField::Type => write!(f, "release type"),
+            ReleaseField::Date => write!(f, "release date"),
+            ReleaseField::Artists => write!(f, "artists"),
+            ReleaseField::Tracks => write!(f, "tracks"),
+        }
+    }
+}
+
+/// Cover art for a release, along with its dimensions when the data source gives them
+#[derive(Debug, Clone)]
+struct ReleaseImage {
+    url: IriRefBuf,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+/// Picks by the longer side of each image, and falls back to the first image when none of them have dimensions
+fn choose_image(images: &[ReleaseImage], preference: ImageSize) -> Option<&ReleaseImage> {
+    let sized = images
+        .iter()
+        .filter_map(|image| Some((image, image.width?.max(image.height?))));
+
+    let chosen = match preference {
+        ImageSize::Largest => sized.max_by_key(|(_image, size)| *size),
+        ImageSize::Closest { pixels } => sized.min_by_key(|(_image, size)| size.abs_diff(pixels)),
+    };
+
+    chosen
+        .map(|(image, _size)| image)
+        .or_else(|| images.first())
+}
+
+/// How much of a release date is actually known; anything finer is filled in with a default and shouldn't be shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DatePrecision {
+    Year,
+    Month,
+    Day,
 }
 
 #[derive(Debug, Clone)]
-struct Release {
+pub(super) struct Release {
     url: IriRefBuf,
     kind: ReleaseType,
     title: String,
-    date: time::Date,
+    date: Option<time::Date>,
+    date_precision: DatePrecision,
     main_artists: Vec<Artist>,
     tracks: Vec<Track>,
-    record_label: Option<String>,
+    /// The first is the main one when there are several, like when a release is put out by more than one label together
+    record_labels: Vec<String>,
+    images: Vec<ReleaseImage>,
+    undetermined: BTreeSet<ReleaseField>,
+    /// Where to find the same release on Spotify, when it was originally found elsewhere
+    spotify_url: Option<IriRefBuf>,
+    /// As the data source gives them, so either language codes or names
+    languages: Vec<String>,
+    /// Whether any of the tracks are marked as explicit, which only some data sources say
+    explicit: bool,
+    genres: Vec<String>,
+    catalog_number: Option<String>,
+    /// The barcode (a UPC or EAN), which is printed on physical copies and used by databases of releases
+    upc: Option<String>,
+}
+
+impl Release {
+    /// Every artist and label this release credits by name, without repeats, in the order they're credited
+    pub(super) fn credited_names(&self) -> Vec<&str> {
+        let artists = self.main_artists.iter().chain(
+            self.tracks
+                .iter()
+                .filter(|track| !track.artists_inherited)
+                .flat_map(|track| &track.artists),
+        );
+
+        artists
+            .map(|artist| artist.name.as_str())
+            .chain(self.record_labels.iter().map(String::as_str))
+            .unique()
+            .collect()
+    }
 }
 
 #[derive(Debug, Snafu)]
-enum GetReleaseFromLdJsonError {
+pub(super) enum GetReleaseFromLdJsonError {
     /// there is no semantic (JSON-LD) release data in the web page (this is likely to mean the service is unsupported)
     NoSemanticDataInPage,
 
     #[snafu(display(
         "any semantic (JSON-LD) data in the page wasn't able to be parsed (this is likely to mean the service is unsupported): ```{:#?}```",
-        Vec::from_iter(errors)
+        Vec::from_iter(std::iter::once(source).chain(other_errors))
     ))]
     UnsupportedSemanticDataInPage {
-        errors: NonEmptyVec<serde_json::Error>, // TODO: it'd be nice if `source` could be a Vec
+        /// The first of the errors, since there can only be one `source`
+        source: serde_json::Error,
+        other_errors: Vec<serde_json::Error>,
     },
 
     /// the semantic data doesn't include a canonical URL to the music release
@@ -257,35 +673,189 @@ enum GetReleaseFromLdJsonError {
     /// the semantic data doesn't include the type (e.g. EP or LP) of the music release; I don't feel like assuming or guessing (maybe in the future though)
     NoReleaseType,
 
+    /// this is a broadcast (like a radio show or podcast episode) rather than a music release; link the release itself instead
+    BroadcastRelease,
+
     /// the semantic data doesn't include the title of the music release
     NoTitle,
 
     /// the semantic data doesn't include the date of the release
     NoDate,
 
-    /// the semantic data doesn't include the artists of the release
+    /// the semantic data doesn't include the artists of the release, neither for the whole release nor for any of its tracks
     NoArtists,
 
     /// the semantic data doesn't include the tracks of the release
     NoTracks,
 }
 
-fn get_release_from_ld_json(document: scraper::Html) -> Result<Release, GetReleaseFromLdJsonError> {
+fn date_from_schema_org(date: schema_org::DateOrDateTime) -> time::Date {
+    match date {
+        schema_org::DateOrDateTime::Date(date) => {
+            let jiff_date = date.0;
+
+            let year = jiff_date.year().into();
+            let month = u8::try_from(jiff_date.month()).unwrap().try_into().unwrap();
+            let day = jiff_date.day().try_into().unwrap();
+
+            time::Date::from_calendar_date(year, month, day)
+                .expect("there is simply no way this is an invalid date, I don't buy it")
+        }
+        schema_org::DateOrDateTime::DateTime(datetime) => {
+            let chrono_datetime = datetime.0;
+
+            let year = chrono_datetime.year();
+            let month = u8::try_from(chrono_datetime.month())
+                .unwrap()
+                .try_into()
+                .unwrap();
+            let day = chrono_datetime.day().try_into().unwrap();
+
+            time::Date::from_calendar_date(year, month, day)
+                .expect("there is simply no way this is an invalid date, I don't buy it")
+        }
+    }
+}
+
+fn music_release_date(music_release: &schema_org::MusicRelease) -> Option<time::Date> {
+    let creative_work = &music_release.music_playlist.creative_work;
+
+    creative_work
+        .date_published
+        .clone()
+        .or_else(|| creative_work.date_created.clone())
+        .map(date_from_schema_org)
+}
+
+/// Which of an album's releases (its different formats and regions) best describes it:
+/// a digital one if there are any, since that's what links lead to, and otherwise (or among those) the earliest
+fn choose_music_release(
+    music_releases: &[schema_org::MusicRelease],
+) -> Option<&schema_org::MusicRelease> {
+    music_releases.iter().min_by_key(|music_release| {
+        let is_digital = matches!(
+            music_release.music_release_format,
+            Some(schema_org::MusicReleaseFormatType::DigitalFormat)
+        );
+        let date = music_release_date(music_release);
+
+        (!is_digital, date.is_none(), date)
+    })
+}
+
+/// Fills in whatever `into` is missing with what `from` has, for when they're the same album.
+/// Of their track lists, the longer one is kept, since a minimal blob might only list some of the tracks
+fn fill_in_music_album(into: &mut schema_org::MusicAlbum, from: schema_org::MusicAlbum) {
+    fn fill_in<T>(into: &mut Option<T>, from: Option<T>) {
+        if into.is_none() {
+            *into = from;
+        }
+    }
+
+    let schema_org::MusicAlbum {
+        album_production_type,
+        album_release,
+        album_release_type,
+        by_artist,
+        music_playlist:
+            schema_org::MusicPlaylist {
+                num_tracks,
+                track,
+                creative_work:
+                    schema_org::CreativeWork {
+                        creator,
+                        date_created,
+                        date_modified,
+                        date_published,
+                        genre,
+                        in_language,
+                        publisher,
+                        thing: schema_org::Thing { id: _, image, name },
+                    },
+            },
+    } = from;
+
+    fill_in(&mut into.album_production_type, album_production_type);
+    if into.album_release.is_empty() {
+        into.album_release = album_release;
+    }
+    fill_in(&mut into.album_release_type, album_release_type);
+    fill_in(&mut into.by_artist, by_artist);
+
+    let music_playlist = &mut into.music_playlist;
+    fill_in(&mut music_playlist.num_tracks, num_tracks);
+    let n_tracks = |track: &Option<schema_org::ItemList<schema_org::MusicRecording>>| {
+        track.as_ref().map_or(0, schema_org::ItemList::n_items)
+    };
+    if n_tracks(&track) > n_tracks(&music_playlist.track) {
+        music_playlist.track = track;
+    }
+
+    let creative_work = &mut music_playlist.creative_work;
+    fill_in(&mut creative_work.creator, creator);
+    fill_in(&mut creative_work.date_created, date_created);
+    fill_in(&mut creative_work.date_modified, date_modified);
+    fill_in(&mut creative_work.date_published, date_published);
+    fill_in(&mut creative_work.genre, genre);
+    fill_in(&mut creative_work.in_language, in_language);
+    fill_in(&mut creative_work.publisher, publisher);
+    fill_in(&mut creative_work.thing.image, image);
+    fill_in(&mut creative_work.thing.name, name);
+}
+
+/// Some pages split one album across several ld+json blobs (like a minimal one and a full one),
+/// so the blobs with the same `@id` are combined into one, where the first of them was.
+/// Blobs without an `@id` can't be told apart from different albums, so they're left alone
+fn merge_same_music_albums(
+    music_albums: Vec<schema_org::MusicAlbum>,
+) -> Vec<schema_org::MusicAlbum> {
+    let mut merged = Vec::<schema_org::MusicAlbum>::with_capacity(music_albums.len());
+
+    for music_album in music_albums {
+        let id = music_album.music_playlist.creative_work.thing.id.as_ref();
+        let same_album = id.and_then(|id| {
+            merged.iter().position(|merged_album| {
+                merged_album.music_playlist.creative_work.thing.id.as_ref() == Some(id)
+            })
+        });
+
+        match same_album {
+            Some(i) => fill_in_music_album(&mut merged[i], music_album),
+            None => merged.push(music_album),
+        }
+    }
+
+    merged
+}
+
+fn ld_json_strings(document: &scraper::Html) -> Vec<String> {
     let ld_json_selector = scraper::Selector::parse("script[type='application/ld+json']")
         .expect("ld+json selector should be valid");
     let ld_json_elements = document.select(&ld_json_selector);
     let ld_json_texts = ld_json_elements.map(|e| e.text());
-    let ld_json_strings = ld_json_texts.map(String::from_iter);
-    let ld_json_strings = Vec::from_iter(ld_json_strings).into_par_iter();
+
+    Vec::from_iter(ld_json_texts.map(String::from_iter))
+}
+
+fn get_release_from_ld_json(
+    document: &scraper::Html,
+    thresholds: TrackCountThresholds,
+    comma_artist_names: &[String],
+) -> Result<Release, GetReleaseFromLdJsonError> {
+    let ld_json_strings = ld_json_strings(document).into_par_iter();
     let music_album_results =
         ld_json_strings.map(|s| serde_json::from_str::<schema_org::MusicAlbum>(&s));
 
     let (errors, music_albums): (Vec<_>, Vec<_>) = music_album_results.partition_map(Into::into);
+    let music_albums = merge_same_music_albums(music_albums);
 
     let music_albums_option = NonEmptyVec::from_vec(music_albums);
     let music_albums = match NonEmptyVec::from_vec(errors) {
         Some(errors) => {
-            music_albums_option.context(UnsupportedSemanticDataInPageSnafu { errors })?
+            music_albums_option.ok_or(GetReleaseFromLdJsonError::UnsupportedSemanticDataInPage {
+                source: errors.head,
+                other_errors: errors.tail,
+            })?
         }
         None => music_albums_option.context(NoSemanticDataInPageSnafu)?,
     };
@@ -301,6 +871,7 @@ fn get_release_from_ld_json(document: scraper::Html) -> Result<Release, GetRelea
     );
 
     let schema_org::MusicAlbum {
+        album_release,
         album_release_type,
         by_artist,
         music_playlist,
@@ -312,20 +883,45 @@ fn get_release_from_ld_json(document: scraper::Html) -> Result<Release, GetRelea
         ..
     } = music_playlist;
     let schema_org::CreativeWork {
+        creator,
         date_created,
         date_published,
+        genre,
+        in_language,
         publisher,
         thing,
         ..
     } = creative_work;
-    let schema_org::Thing { id, name } = thing;
+    let schema_org::Thing { id, image, name } = thing;
+
+    let images = Vec::from_iter(image.into_iter().flatten().filter_map(|image| match image {
+        schema_org::ImageObjectOrURL::URL(url) => Some(ReleaseImage {
+            url,
+            width: None,
+            height: None,
+        }),
+        schema_org::ImageObjectOrURL::ImageObject(image_object) => {
+            let schema_org::MediaObject {
+                content_url,
+                width,
+                height,
+                creative_work,
+            } = image_object.media_object;
+
+            Some(ReleaseImage {
+                url: content_url.or(creative_work.thing.id)?,
+                width: width.and_then(|width| width.try_into().ok()),
+                height: height.and_then(|height| height.try_into().ok()),
+            })
+        }
+    }));
 
     let url = id.context(NoUrlSnafu)?;
 
     let release_type = match album_release_type.context(NoReleaseTypeSnafu)? {
         schema_org::MusicAlbumReleaseType::AlbumRelease => ReleaseType::LP,
         schema_org::MusicAlbumReleaseType::BroadcastRelease => {
-            ReleaseType::Other("Broadcast (I don't know what this means)".into())
+            return Err(GetReleaseFromLdJsonError::BroadcastRelease);
         }
         schema_org::MusicAlbumReleaseType::EPRelease => ReleaseType::EP,
         schema_org::MusicAlbumReleaseType::SingleRelease => ReleaseType::Single,
@@ -333,131 +929,659 @@ fn get_release_from_ld_json(document: scraper::Html) -> Result<Release, GetRelea
 
     let title = name.context(NoTitleSnafu)?;
 
-    let date = date_published.or(date_created).context(NoDateSnafu)?;
-    let date = match date {
-        schema_org::DateOrDateTime::Date(date) => {
-            let jiff_date = date.0;
+    let chosen_music_release = choose_music_release(&album_release);
+
+    // the chosen release's date is specific to its format or region, so it's more relevant than the album's,
+    // and when neither has one, the earliest of the album's other releases' dates is the next best thing
+    let date = match chosen_music_release.and_then(music_release_date) {
+        Some(date) => date,
+        None => date_published
+            .or(date_created)
+            .map(date_from_schema_org)
+            .or_else(|| album_release.iter().filter_map(music_release_date).min())
+            .context(NoDateSnafu)?,
+    };
 
-            let year = jiff_date.year().into();
-            let month = u8::try_from(jiff_date.month()).unwrap().try_into().unwrap();
-            let day = jiff_date.day().try_into().unwrap();
+    let catalog_number =
+        chosen_music_release.and_then(|music_release| music_release.catalog_number.clone());
+    let upc = chosen_music_release.and_then(|music_release| {
+        music_release
+            .gtin13
+            .clone()
+            .or_else(|| music_release.gtin.clone())
+    });
 
-            time::Date::from_calendar_date(year, month, day)
-                .expect("there is simply no way this is an invalid date, I don't buy it")
-        }
-        schema_org::DateOrDateTime::DateTime(datetime) => {
-            let chrono_datetime = datetime.0;
+    let (main_artists_genre, main_artists_joined) = match by_artist {
+        Some(main_artists_group) => (
+            main_artists_group.genre,
+            main_artists_group.performing_group.organization.thing.name,
+        ),
+        None => (None, None),
+    };
 
-            let year = chrono_datetime.year();
-            let month = u8::try_from(chrono_datetime.month())
-                .unwrap()
-                .try_into()
-                .unwrap();
-            let day = chrono_datetime.day().try_into().unwrap();
+    // genres given as URLs (into some taxonomy) aren't readable, so only the textual ones are kept
+    let genres = Vec::from_iter(
+        genre
+            .into_iter()
+            .chain(main_artists_genre)
+            .flatten()
+            .filter_map(|genre| match genre {
+                // every URL is valid text too, so that's what they're deserialized as
+                schema_org::TextOrURL::Text(genre) if iref::Iri::new(&genre).is_err() => {
+                    Some(genre)
+                }
+                schema_org::TextOrURL::Text(_) | schema_org::TextOrURL::URL(_) => None,
+            })
+            .unique(),
+    );
 
-            time::Date::from_calendar_date(year, month, day)
-                .expect("there is simply no way this is an invalid date, I don't buy it")
-        }
+    let to_artists = |artists_joined| {
+        parse_list_of_artists(artists_joined, comma_artist_names).map(|artist_name| Artist {
+            id: None,
+            name: artist_name,
+            role_id: None,
+        })
     };
 
-    let main_artists_joined = by_artist.context(NoArtistsSnafu)?;
-    let main_artists_joined = main_artists_joined
-        .performing_group
-        .organization
-        .thing
-        .name
-        .context(NoArtistsSnafu)?;
+    let ld_json_tracks = Vec::from_iter(
+        tracks
+            .context(NoTracksSnafu)?
+            .into_flattened()
+            .into_iter()
+            .map(|list_item| {
+                let to_u32 = |number| u32::try_from(number).ok();
+                let position = list_item.position.and_then(to_u32);
+                let disc_number = list_item.list_position.and_then(to_u32);
+
+                let music_recording = list_item.item;
+                let title = music_recording.creative_work.thing.name;
+                let explicit_artists = music_recording
+                    .by_artist
+                    .map(schema_org::Thing::from)
+                    .and_then(|thing| thing.name)
+                    .map(to_artists);
+
+                (position, disc_number, title, explicit_artists)
+            }),
+    );
 
-    let main_artists = parse_list_of_artists(main_artists_joined);
-    let main_artists = main_artists.map(|artist_name| Artist {
-        id: Some(artist_name.clone()), // sure, why not
-        name: artist_name,
-    });
+    let creators = NonEmptyVec::collect(
+        creator
+            .into_iter()
+            .flatten()
+            .filter_map(|creator| schema_org::Thing::from(creator).name)
+            .flat_map(to_artists)
+            .unique_by(|artist| artist.key()),
+    );
 
-    let tracks = tracks
-        .context(NoTracksSnafu)?
-        .item_list_element
-        .into_iter()
-        .map(|list_item| list_item.item)
-        .map(|music_recording| {
-            music_recording
-                .by_artist
-                .map(schema_org::Thing::from)
-                .and_then(|thing| thing.name)
-                .map(parse_list_of_artists)
-                .map_or_else(
-                    || main_artists.clone(),
-                    |artists| {
-                        artists.map(|artist_name| Artist {
-                            id: Some(artist_name.clone()), // sure, why not
-                            name: artist_name,
-                        })
-                    },
-                )
-        })
-        .map(Into::into)
-        .map(|artists| Track { artists });
-    let tracks = Vec::from_iter(tracks);
+    // some feeds credit the album's artists as its creators instead,
+    // and compilations can leave out an artist for the whole album while still crediting every track,
+    // so the tracks' artists stand in for the main ones then
+    let main_artists = match (main_artists_joined, creators) {
+        (Some(main_artists_joined), _) => to_artists(main_artists_joined),
+        (None, Some(creators)) => creators,
+        (None, None) => NonEmptyVec::collect(
+            ld_json_tracks
+                .iter()
+                .filter_map(|(_position, _disc_number, _title, explicit_artists)| {
+                    explicit_artists.as_ref()
+                })
+                .flat_map(|artists| artists.iter())
+                .unique_by(|artist| artist.key())
+                .cloned(),
+        )
+        .context(NoArtistsSnafu)?,
+    };
 
-    // TODO: do this in a bandcamp-specific way instead
-    let release_type = if release_type == ReleaseType::LP {
-        if tracks.len() < 3 {
-            ReleaseType::Single
-        } else if tracks.len() < 7 {
-            ReleaseType::EP
-        } else {
-            ReleaseType::LP // TODO: distinguish compilations
+    let mut tracks = Vec::from_iter(ld_json_tracks.into_iter().map(
+        |(position, disc_number, title, explicit_artists)| match explicit_artists {
+            Some(artists) => Track {
+                position,
+                disc_number,
+                title,
+                artists: artists.into(),
+                artists_inherited: false,
+            },
+            None => Track {
+                position,
+                disc_number,
+                title,
+                artists: main_artists.clone().into(),
+                artists_inherited: true,
+            },
+        },
+    ));
+    // the list isn't necessarily in order, but the positions say what the order is
+    // (and the sort is stable, so tracks without positions stay where they were relative to each other, after the ones with them).
+    // positions start over on each disc, so the discs go in order first
+    tracks.sort_by_key(|track| (track.position.is_none(), track.disc_number, track.position));
+
+    // Bandcamp calls everything an album, so only anything else it says is worth respecting
+    let release_type = match (release_type, parse_bandcamp_page(&url)) {
+        (ReleaseType::LP, Some(BandcampPage::Track)) => ReleaseType::Single,
+        // Bandcamp pages on custom domains can't be recognized, so unrecognized pages get the same heuristic as albums
+        (ReleaseType::LP, Some(BandcampPage::Album) | None) => {
+            release_type_from_track_count(tracks.len(), thresholds)
         }
-    } else {
-        release_type
+        (release_type, _) => release_type,
     };
 
     let main_artists = main_artists.into();
 
-    let record_label = publisher
+    // some feeds only give the label on the album's releases, and it's more specific than the publisher anyway
+    let release_record_label = chosen_music_release
+        .into_iter()
+        .chain(&album_release)
+        .filter_map(|music_release| music_release.record_label.clone())
         .map(schema_org::Thing::from)
-        .and_then(|thing| thing.name);
+        .find_map(|thing| thing.name);
+    let record_labels = match release_record_label {
+        Some(release_record_label) => vec![release_record_label],
+        None => Vec::from_iter(
+            publisher
+                .into_iter()
+                .flatten()
+                .map(schema_org::Thing::from)
+                .filter_map(|thing| thing.name)
+                .unique(),
+        ),
+    };
 
     Ok(Release {
         url,
         kind: release_type,
         title,
-        date,
+        date: Some(date),
+        date_precision: DatePrecision::Day,
         main_artists,
         tracks,
-        record_label,
+        record_labels,
+        images,
+        undetermined: BTreeSet::new(),
+        spotify_url: None,
+        languages: in_language.unwrap_or_default(),
+        explicit: false,
+        genres,
+        catalog_number,
+        upc,
     })
 }
 
 #[derive(Debug, Snafu)]
-enum GetSemanticDataError {
-    /// couldn't fetch {url}
-    FetchError {
-        source: reqwest::Error,
-        url: IriRefBuf,
-    },
-    /// couldn't get the content of the webpage
-    ResponseTextError { source: reqwest::Error },
+enum GetMusicVideoFromLdJsonError {
+    /// there is no video in the semantic (JSON-LD) data in the web page
+    NoVideoInPage,
 
-    /// could not surface a release from JSON-LD in the page (this is likely to mean the service is unsupported)
-    ReleaseFromLdJsonError { source: GetReleaseFromLdJsonError },
+    /// the semantic data doesn't include the title of the video
+    NoVideoTitle,
 }
 
-#[tracing::instrument(ret)]
-async fn get_semantic_data(url: &IriRef) -> Result<Release, GetSemanticDataError> {
-    let response = reqwest::get(url.as_str())
-        .await
-        .with_context(|_| FetchSnafu {
-            url: url.to_owned(),
-        })?;
-    let document = response.text().await.context(ResponseTextSnafu)?;
-    let document = scraper::Html::parse_document(&document);
+/// A degraded alternative to [`get_release_from_ld_json`] for pages about a music video (like a premiere) rather than a release,
+/// which only has what a video can say about itself: its title, and maybe its artists and date
+fn get_music_video_from_ld_json(
+    document: &scraper::Html,
+    fetched_url: &IriRef,
+    comma_artist_names: &[String],
+) -> Result<Release, GetMusicVideoFromLdJsonError> {
+    let video_object = ld_json_strings(document)
+        .into_iter()
+        .find_map(|s| serde_json::from_str::<schema_org::VideoObject>(&s).ok())
+        .context(NoVideoInPageSnafu)?;
 
-    get_release_from_ld_json(document).context(ReleaseFromLdJsonSnafu)
-}
+    let schema_org::VideoObject {
+        by_artist,
+        thumbnail_url,
+        upload_date,
+        media_object,
+        ..
+    } = video_object;
+    let schema_org::CreativeWork {
+        date_published,
+        thing,
+        ..
+    } = media_object.creative_work;
+
+    let title = thing.name.context(NoVideoTitleSnafu)?;
+
+    let images = Vec::from_iter(
+        thumbnail_url
+            .into_iter()
+            .flatten()
+            .chain(
+                thing
+                    .image
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|image| match image {
+                        schema_org::ImageObjectOrURL::URL(url) => Some(url),
+                        schema_org::ImageObjectOrURL::ImageObject(_) => None,
+                    }),
+            )
+            .map(|url| ReleaseImage {
+                url,
+                width: None,
+                height: None,
+            }),
+    );
+
+    let date = date_published.or(upload_date).map(date_from_schema_org);
+
+    let main_artists = Vec::from_iter(
+        by_artist
+            .and_then(|main_artists_group| {
+                main_artists_group.performing_group.organization.thing.name
+            })
+            .into_iter()
+            .flat_map(|artists_joined| parse_list_of_artists(artists_joined, comma_artist_names))
+            .map(|artist_name| Artist {
+                id: None,
+                name: artist_name,
+                role_id: None,
+            }),
+    );
+
+    let mut undetermined = BTreeSet::new();
+    if date.is_none() {
+        undetermined.insert(ReleaseField::Date);
+    }
+    if main_artists.is_empty() {
+        undetermined.insert(ReleaseField::Artists);
+    }
+
+    Ok(Release {
+        // the page is what's worth linking, since videos' IDs often aren't URLs to them
+        url: fetched_url.to_owned(),
+        kind: ReleaseType::Video,
+        title: title.clone(),
+        date,
+        date_precision: DatePrecision::Day,
+        main_artists,
+        tracks: vec![Track {
+            position: None,
+            disc_number: None,
+            title: Some(title),
+            artists: Vec::new(),
+            artists_inherited: true,
+        }],
+        record_labels: Vec::new(),
+        images,
+        undetermined,
+        spotify_url: None,
+        languages: Vec::new(),
+        explicit: false,
+        genres: Vec::new(),
+        catalog_number: None,
+        upc: None,
+    })
+}
+
+#[derive(Debug, Snafu)]
+enum GetReleaseFromOpenGraphError {
+    /// the OpenGraph data in the web page isn't about music (its type is {og_type:?}), or is about a video when music videos aren't recognized
+    NotMusic { og_type: Option<String> },
+
+    /// the OpenGraph data doesn't include the title of the music release
+    NoOpenGraphTitle,
+}
+
+/// A degraded alternative to [`get_release_from_ld_json`] for pages that only describe themselves with OpenGraph `og:` and `music:` meta tags
+fn get_release_from_open_graph(
+    document: &scraper::Html,
+    fetched_url: &IriRef,
+    thresholds: TrackCountThresholds,
+    recognize_music_videos: bool,
+) -> Result<Release, GetReleaseFromOpenGraphError> {
+    let meta_selector = scraper::Selector::parse("meta[property][content], meta[name][content]")
+        .expect("meta selector should be valid");
+
+    let mut properties: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for element in document.select(&meta_selector) {
+        let element = element.value();
+
+        let Some(property) = element.attr("property").or_else(|| element.attr("name")) else {
+            continue;
+        };
+        let content = element
+            .attr("content")
+            .expect("the selector only matches meta tags with content");
+
+        properties.entry(property).or_default().push(content);
+    }
+
+    let first = |property: &str| {
+        properties
+            .get(property)
+            .and_then(|contents| contents.first())
+            .copied()
+    };
+
+    let og_type = first("og:type");
+    let is_video =
+        recognize_music_videos && og_type.is_some_and(|og_type| og_type.starts_with("video."));
+    ensure!(
+        is_video || og_type.is_some_and(|og_type| og_type.starts_with("music.")),
+        NotMusicSnafu {
+            og_type: og_type.map(ToOwned::to_owned),
+        }
+    );
+
+    let title = first("og:title").context(NoOpenGraphTitleSnafu)?.to_owned();
+
+    let url = first("og:url")
+        .and_then(|url| IriRefBuf::new(url.to_owned()).ok())
+        .unwrap_or_else(|| fetched_url.to_owned());
+
+    // the dimensions of each image come after it, so they line up by position
+    let dimension = |property: &str, index: usize| {
+        properties
+            .get(property)
+            .and_then(|contents| contents.get(index))
+            .and_then(|dimension| dimension.parse().ok())
+    };
+    let images = Vec::from_iter(
+        properties
+            .get("og:image")
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(index, url)| {
+                Some(ReleaseImage {
+                    url: IriRefBuf::new(url.to_string()).ok()?,
+                    width: dimension("og:image:width", index),
+                    height: dimension("og:image:height", index),
+                })
+            }),
+    );
+
+    let date = first("music:release_date").and_then(|date| {
+        let (date, _time) = try_split_once(date, "T");
+        assemble_parsed_date(parse_date(date), DatePrecision::Day).ok()
+    });
+
+    let mut undetermined = BTreeSet::from([ReleaseField::Artists]);
+
+    if date.is_none() {
+        undetermined.insert(ReleaseField::Date);
+    }
+
+    let n_tracks = if is_video || og_type == Some("music.song") {
+        1
+    } else {
+        properties.get("music:song").map_or(0, Vec::len)
+    };
+
+    let kind = if is_video {
+        ReleaseType::Video
+    } else if n_tracks == 0 {
+        undetermined.insert(ReleaseField::Type);
+        undetermined.insert(ReleaseField::Tracks);
+        ReleaseType::LP
+    } else {
+        release_type_from_track_count(n_tracks, thresholds)
+    };
+
+    let tracks = Vec::from_iter((0..n_tracks).map(|_| Track {
+        position: None,
+        disc_number: None,
+        title: None,
+        artists: Vec::new(),
+        artists_inherited: true,
+    }));
+
+    Ok(Release {
+        url,
+        kind,
+        title,
+        date,
+        date_precision: DatePrecision::Day,
+        main_artists: Vec::new(),
+        tracks,
+        record_labels: Vec::new(),
+        images,
+        undetermined,
+        spotify_url: None,
+        languages: Vec::new(),
+        explicit: false,
+        genres: Vec::new(),
+        catalog_number: None,
+        upc: None,
+    })
+}
+
+#[derive(Debug, Snafu)]
+pub(super) enum GetSemanticDataError {
+    /// couldn't fetch {url}
+    FetchError {
+        source: reqwest::Error,
+        url: IriRefBuf,
+    },
+    /// couldn't get the content of the webpage
+    ResponseTextError { source: reqwest::Error },
+
+    /// {url} isn't somewhere this bot is allowed to fetch pages from
+    HostNotAllowed { url: IriRefBuf },
+
+    /// {url} points to a private, loopback, or link-local address, which this bot won't fetch from
+    NonPublicAddress { url: IriRefBuf },
+
+    /// {url} isn't publicly accessible (it needs logging in or a subscription to see)
+    LoginRequired { url: IriRefBuf },
+
+    /// {url} didn't respond with a web page that could be looked through for a release ({reason})
+    EmptyOrUnparseableBody { url: IriRefBuf, reason: String },
+
+    /// could not surface a release from JSON-LD in the page (this is likely to mean the service is unsupported)
+    ReleaseFromLdJsonError { source: GetReleaseFromLdJsonError },
+}
+
+/// Path segments that sites redirect to when a page needs logging in (or paying) to see
+const LOGIN_PATH_SEGMENTS: &[&str] = &[
+    "login",
+    "log-in",
+    "signin",
+    "sign-in",
+    "sign_in",
+    "auth",
+    "paywall",
+    "subscribe",
+];
+
+/// Whether a page that didn't have any release in it was really a login wall or paywall in the way.
+///
+/// The signals are a redirect to a login-looking path,
+/// a login form (for sites that return one with a 200 instead of a 401 or 403),
+/// or a redirect that landed on a generic `og:type` of `website` page instead of the release.
+fn is_login_wall(page: &str, requested_url: &IriRef, landed_url: &reqwest::Url) -> bool {
+    let redirected = landed_url.as_str() != requested_url.as_str();
+
+    let landed_on_login_path = redirected
+        && landed_url.path_segments().is_some_and(|mut segments| {
+            segments.any(|segment| {
+                LOGIN_PATH_SEGMENTS
+                    .iter()
+                    .any(|login_segment| segment.eq_ignore_ascii_case(login_segment))
+            })
+        });
+    if landed_on_login_path {
+        return true;
+    }
+
+    let document = scraper::Html::parse_document(page);
+
+    let password_input_selector = scraper::Selector::parse("form input[type='password']")
+        .expect("password input selector should be valid");
+    if document.select(&password_input_selector).next().is_some() {
+        return true;
+    }
+
+    let website_og_type_selector =
+        scraper::Selector::parse("meta[property='og:type'][content='website']")
+            .expect("og:type selector should be valid");
+    redirected && document.select(&website_og_type_selector).next().is_some()
+}
+
+/// The page at the URL (as long as it's somewhere this bot is allowed to fetch from and doesn't need logging in to see),
+/// along with where it ended up after any redirects
+#[tracing::instrument(skip(http_client))]
+async fn fetch_page(
+    http_client: &reqwest::Client,
+    url: &IriRef,
+    config: &Config,
+) -> Result<(String, reqwest::Url), GetSemanticDataError> {
+    let allowed = url
+        .scheme()
+        .zip(url.authority())
+        .is_some_and(|(scheme, authority)| {
+            config
+                .host_policy
+                .allows(scheme.as_str(), authority.host().as_str())
+        });
+    ensure!(
+        allowed,
+        HostNotAllowedSnafu {
+            url: url.to_owned()
+        }
+    );
+    ensure!(
+        !is_non_public_url(url.as_str()),
+        NonPublicAddressSnafu {
+            url: url.to_owned()
+        }
+    );
+
+    let response = match http_client.get(url.as_str()).send().await {
+        Ok(response) => response,
+        Err(error) if is_non_public_address_error(&error) => {
+            return NonPublicAddressSnafu {
+                url: url.to_owned(),
+            }
+            .fail();
+        }
+        Err(error) => {
+            return Err(error).context(FetchSnafu {
+                url: url.to_owned(),
+            });
+        }
+    };
+
+    ensure!(
+        !matches!(
+            response.status(),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+        ),
+        LoginRequiredSnafu {
+            url: url.to_owned()
+        }
+    );
+
+    // without this, an image or a download would be parsed as a page with nothing in it, blaming the service for being unsupported
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| {
+            let (media_type, _parameters) = try_split_once(content_type, ";");
+            media_type.trim().to_ascii_lowercase()
+        });
+    if let Some(content_type) = content_type {
+        ensure!(
+            content_type.starts_with("text/") || content_type.ends_with("+xml"),
+            EmptyOrUnparseableBodySnafu {
+                url: url.to_owned(),
+                reason: format!("it's {content_type}, not a web page"),
+            }
+        );
+    }
+
+    let landed_url = response.url().clone();
+    let page = response.text().await.context(ResponseTextSnafu)?;
+
+    ensure!(
+        !page.trim().is_empty(),
+        EmptyOrUnparseableBodySnafu {
+            url: url.to_owned(),
+            reason: "the response was empty",
+        }
+    );
+    ensure!(
+        !page.contains('\0'),
+        EmptyOrUnparseableBodySnafu {
+            url: url.to_owned(),
+            reason: "the response is binary data, not text",
+        }
+    );
+
+    Ok((page, landed_url))
+}
+
+#[tracing::instrument(skip(http_client), ret)]
+async fn get_semantic_data(
+    http_client: &reqwest::Client,
+    url: &IriRef,
+    config: &Config,
+) -> Result<Release, GetSemanticDataError> {
+    let (page, landed_url) = fetch_page(http_client, url, config).await?;
+
+    match get_release_from_page(
+        &page,
+        url,
+        config.track_count_thresholds,
+        config.recognize_music_videos,
+        &config.comma_artist_names,
+    ) {
+        Err(
+            GetReleaseFromLdJsonError::NoSemanticDataInPage
+            | GetReleaseFromLdJsonError::UnsupportedSemanticDataInPage { .. },
+        ) if is_login_wall(&page, url, &landed_url) => LoginRequiredSnafu {
+            url: url.to_owned(),
+        }
+        .fail(),
+        other => other.context(ReleaseFromLdJsonSnafu),
+    }
+}
+
+/// Everything [`get_semantic_data`] does once it has the page in hand,
+/// kept apart from the fetching so that recorded pages can be replayed through it
+fn get_release_from_page(
+    page: &str,
+    url: &IriRef,
+    thresholds: TrackCountThresholds,
+    recognize_music_videos: bool,
+    comma_artist_names: &[String],
+) -> Result<Release, GetReleaseFromLdJsonError> {
+    let document = scraper::Html::parse_document(page);
+
+    match get_release_from_ld_json(&document, thresholds, comma_artist_names) {
+        Err(
+            ld_json_error @ (GetReleaseFromLdJsonError::NoSemanticDataInPage
+            | GetReleaseFromLdJsonError::UnsupportedSemanticDataInPage { .. }),
+        ) => {
+            if recognize_music_videos {
+                match get_music_video_from_ld_json(&document, url, comma_artist_names) {
+                    Ok(release) => return Ok(release),
+                    Err(music_video_error) => tracing::debug!(
+                        ?music_video_error,
+                        "couldn't find a music video in the JSON-LD data in this page either"
+                    ),
+                }
+            }
+
+            get_release_from_open_graph(&document, url, thresholds, recognize_music_videos).map_err(
+                |open_graph_error| {
+                    tracing::debug!(
+                        ?open_graph_error,
+                        "couldn't fall back to OpenGraph data in this page either"
+                    );
+                    ld_json_error
+                },
+            )
+        }
+        other => other,
+    }
+}
 
 #[derive(Debug, Snafu)]
-enum AssembleDateError {
+pub(super) enum AssembleDateError {
     /// couldn't parse the year
     ParseYearError { source: ParseIntError },
 
@@ -470,45 +1594,89 @@ enum AssembleDateError {
     DayMissing,
     /// couldn't parse the day
     ParseDayError { source: deranged::ParseIntError },
+    /// {month} only has {days_in_month} days in {year}, so there's no day {day}
+    DayOutOfRangeForMonth {
+        year: i32,
+        month: time::Month,
+        day: u8,
+        days_in_month: u8,
+    },
 
     /// some date component was out of valid range (somehow)
     OutOfRange { source: time::error::ComponentRange },
 }
 
-fn assemble_parsed_date(year_result: YearResult) -> Result<time::Date, AssembleDateError> {
+/// Components finer than `precision` are allowed to be missing, and default to January and the 1st
+fn assemble_parsed_date(
+    year_result: YearResult,
+    precision: DatePrecision,
+) -> Result<time::Date, AssembleDateError> {
     let (year, month_and_day) = year_result.context(ParseYearSnafu)?;
-    let (month, day) = month_and_day
-        .context(MonthMissingSnafu)?
-        .context(ParseMonthSnafu)?;
-    let day = day.context(DayMissingSnafu)?.context(ParseDaySnafu)?;
-
-    let date = Date::from_calendar_date(
-        year as i32,
-        month
-            .get()
-            .try_into()
-            .expect("month is in the range of 1 to 12"),
-        day.get(),
-    )
-    .context(OutOfRangeSnafu)?;
+    let (month, day) = match (month_and_day, precision) {
+        (None, DatePrecision::Year) => (1, None),
+        (month_and_day, _) => {
+            let (month, day) = month_and_day
+                .context(MonthMissingSnafu)?
+                .context(ParseMonthSnafu)?;
+            (month.get(), day)
+        }
+    };
+    let day = match (day, precision) {
+        (None, DatePrecision::Year | DatePrecision::Month) => 1,
+        (day, _) => day.context(DayMissingSnafu)?.context(ParseDaySnafu)?.get(),
+    };
+
+    let year = i32::from(year);
+    let month = time::Month::try_from(month).expect("month is in the range of 1 to 12");
+
+    let days_in_month = month.length(year);
+    ensure!(
+        day <= days_in_month,
+        DayOutOfRangeForMonthSnafu {
+            year,
+            month,
+            day,
+            days_in_month,
+        }
+    );
+
+    let date = Date::from_calendar_date(year, month, day).context(OutOfRangeSnafu)?;
 
     Ok(date)
 }
 
 #[derive(Debug, Snafu)]
-enum GetSpotifyReleaseError {
-    /// the `url` is for Spotify, but not a resource type valid for this command (currently just album)
+pub(super) enum GetSpotifyReleaseError {
+    /// the `url` is for Spotify, but not a resource type valid for this command (currently album or track)
     UrlForUnsupportedResource { got: SpotifyResource<'static> },
 
+    /// <{url}> is a Spotify playlist, which isn't a release, so link an album instead
+    PlaylistNotRelease { url: String },
+
     /// couldn't authenticate with Spotify
     TokenError { source: rspotify::ClientError },
 
+    /// couldn't retrieve the track's data from Spotify to find the album it's on
+    FetchTrackError { source: rspotify::ClientError },
+
+    /// the track isn't on an album on Spotify (like a local file), so there's no release to show
+    TrackNotOnAlbum,
+
     /// couldn't retrieve album data from Spotify
     FetchAlbumError { source: rspotify::ClientError },
 
+    /// the album isn't available on Spotify in the {market} market, and couldn't be found in any other market either
+    NotAvailableInMarket {
+        market: String,
+        source: rspotify::ClientError,
+    },
+
     /// couldn't retrieve data for tracks in this album from Spotify
     FetchTracksError { source: rspotify::ClientError },
 
+    /// Spotify doesn't have a release date for this album yet, which usually means it's an unreleased placeholder
+    NoReleaseDate,
+
     /// the date of the Spotify release is invalid
     DateInvalid { source: AssembleDateError },
 
@@ -516,121 +1684,742 @@ enum GetSpotifyReleaseError {
     ReturnedUrlInvalid { source: InvalidIriRef<String> },
 }
 
+/// Words that mark a bracketed or dashed suffix of a Spotify album name as saying which edition it is, rather than being part of the title
+const EDITION_QUALIFIER_WORDS: &[&str] = &[
+    "deluxe",
+    "edition",
+    "version",
+    "remaster",
+    "remastered",
+    "expanded",
+    "anniversary",
+    "bonus",
+    "explicit",
+    "clean",
+];
+
+/// Where the last bracketed or dashed suffix of `title` starts, if it's an edition qualifier like "(Deluxe Edition)" or "- Remastered 2011"
+fn edition_qualifier_start(title: &str) -> Option<usize> {
+    let (start, qualifier) = if let Some(inner) = title.strip_suffix(')') {
+        let open = inner.rfind('(')?;
+        (open, &inner[open + 1..])
+    } else if let Some(inner) = title.strip_suffix(']') {
+        let open = inner.rfind('[')?;
+        (open, &inner[open + 1..])
+    } else {
+        let dash = title.rfind(" - ")?;
+        (dash, &title[dash + " - ".len()..])
+    };
+
+    let is_edition_qualifier = qualifier.split(|c: char| !c.is_alphanumeric()).any(|word| {
+        EDITION_QUALIFIER_WORDS
+            .iter()
+            .any(|qualifier_word| word.eq_ignore_ascii_case(qualifier_word))
+    });
+
+    // never strip the whole title away
+    (is_edition_qualifier && start > 0).then_some(start)
+}
+
+/// The canonical title of a release, without any edition qualifiers Spotify tacks onto the end of album names
+fn strip_edition_qualifiers(title: &str) -> &str {
+    let mut title = title.trim_end();
+
+    while let Some(qualifier_start) = edition_qualifier_start(title) {
+        title = title[..qualifier_start].trim_end();
+    }
+
+    title
+}
+
+/// The values Spotify uses for `album_group`, which rspotify leaves as a string
+fn parse_spotify_album_group(album_group: &str) -> Option<AlbumType> {
+    match album_group {
+        "album" => Some(AlbumType::Album),
+        "single" => Some(AlbumType::Single),
+        "compilation" => Some(AlbumType::Compilation),
+        "appears_on" => Some(AlbumType::AppearsOn),
+        _ => None,
+    }
+}
+
+/// How many of an artist's albums are looked through for the one being formatted (4 of Spotify's pages of 50),
+/// so that an artist with a huge catalog doesn't take a request per page of it.
+/// Spotify lists the newest releases first within each group, and new releases are what get formatted
+const SPOTIFY_ARTIST_ALBUMS_SEARCHED: usize = 200;
+
+/// How the album is filed among its main artist's releases, which Spotify only says when listing an artist's albums (not when getting the album itself).
+/// This is `None` when the album isn't among the artist's [`SPOTIFY_ARTIST_ALBUMS_SEARCHED`] newest
 #[tracing::instrument(skip(client), ret)]
-async fn get_spotify_release(
+async fn get_spotify_album_group(
     client: &rspotify::ClientCredsSpotify,
-    resource: SpotifyResource<'static>,
-) -> Result<Release, GetSpotifyReleaseError> {
-    let album_id = match resource {
-        SpotifyResource::Album { id } => id,
-        other => return Err(GetSpotifyReleaseError::UrlForUnsupportedResource { got: other }),
+    album_id: AlbumId<'_>,
+    main_artist_id: ArtistId<'_>,
+) -> Result<Option<AlbumType>, rspotify::ClientError> {
+    let album = client
+        .artist_albums(main_artist_id, None, None)
+        .take(SPOTIFY_ARTIST_ALBUMS_SEARCHED)
+        .try_filter(|album| {
+            future::ready(album.id.as_ref().is_some_and(|id| id.id() == album_id.id()))
+        })
+        .try_next()
+        .await?;
+
+    Ok(album
+        .and_then(|album| album.album_group)
+        .and_then(|album_group| parse_spotify_album_group(&album_group)))
+}
+
+/// `album_group` takes precedence over `album_type` when there is one, since it's how the release is filed on the artist's page.
+/// The exception is "appears on", which only says the artist isn't the main one and nothing about the release itself, so `album_type` decides then.
+/// Either way, `mapping` decides what that type is labelled as.
+fn release_type_from_spotify(
+    album_type: AlbumType,
+    album_group: Option<AlbumType>,
+    n_tracks: usize,
+    thresholds: TrackCountThresholds,
+    mapping: SpotifyAlbumTypeMapping,
+) -> ReleaseType {
+    let deciding_type = match album_group {
+        Some(AlbumType::AppearsOn) | None => album_type,
+        Some(album_group) => album_group,
+    };
+
+    let mapped_type = match deciding_type {
+        AlbumType::Album => mapping.album,
+        AlbumType::Single => mapping.single,
+        AlbumType::Compilation => mapping.compilation,
+        AlbumType::AppearsOn => mapping.appears_on,
     };
 
-    let needs_refresh = client
+    match mapped_type {
+        SpotifyReleaseType::Single => ReleaseType::Single,
+        SpotifyReleaseType::EP => ReleaseType::EP,
+        SpotifyReleaseType::LP => ReleaseType::LP,
+        SpotifyReleaseType::Compilation => ReleaseType::Compilation,
+        SpotifyReleaseType::SingleOrEP => {
+            if n_tracks > thresholds.single_max_tracks {
+                ReleaseType::EP
+            } else {
+                ReleaseType::Single
+            }
+        }
+    }
+}
+
+/// The album's barcode, which Spotify calls a UPC or an EAN depending on where the album was released
+fn spotify_upc(external_ids: &HashMap<String, String>) -> Option<String> {
+    ["upc", "ean"]
+        .into_iter()
+        .find_map(|key| external_ids.get(key))
+        .filter(|upc| !upc.is_empty())
+        .cloned()
+}
+
+/// Words in an album's title that give it away as a compilation, since Spotify sometimes calls those plain albums
+const COMPILATION_TITLE_WORDS: &[&str] = &["compilation", "compilations", "sampler"];
+/// How labels start the titles of compilations to say they're by various artists (like "VA - Summer Sounds")
+const VARIOUS_ARTISTS_TITLE_PREFIXES: &[&str] = &["va - ", "v.a. - ", "va: ", "v.a.: "];
+
+/// Whether an album Spotify calls an album is really a compilation: one by "Various Artists",
+/// or with a title like "Label Sampler 2024" or "VA - Summer Sounds"
+fn looks_like_compilation<'a>(
+    title: &str,
+    mut artist_names: impl Iterator<Item = &'a str>,
+) -> bool {
+    let by_various_artists =
+        artist_names.any(|artist_name| artist_name.eq_ignore_ascii_case("Various Artists"));
+
+    let has_compilation_title_word = title.split(|c: char| !c.is_alphanumeric()).any(|word| {
+        COMPILATION_TITLE_WORDS
+            .iter()
+            .any(|compilation_word| word.eq_ignore_ascii_case(compilation_word))
+    });
+
+    let has_various_artists_title_prefix = VARIOUS_ARTISTS_TITLE_PREFIXES.iter().any(|prefix| {
+        title
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    });
+
+    by_various_artists || has_compilation_title_word || has_various_artists_title_prefix
+}
+
+#[tracing::instrument(skip(client), ret)]
+pub(super) async fn has_valid_spotify_token(client: &rspotify::ClientCredsSpotify) -> bool {
+    client
         .token
         .lock()
         .await
         .expect("mutex was poisoned")
         .as_ref()
-        .map_or(true, |token| {
+        .is_some_and(|token| {
             token
                 .expires_at
-                .map_or(true, |expires_at| expires_at <= chrono::Utc::now())
-        });
+                .is_some_and(|expires_at| expires_at > chrono::Utc::now())
+        })
+}
 
-    if needs_refresh {
-        client.request_token().await.context(TokenSnafu)?;
+#[tracing::instrument(skip(client))]
+async fn ensure_spotify_token(
+    client: &rspotify::ClientCredsSpotify,
+) -> Result<(), rspotify::ClientError> {
+    if !has_valid_spotify_token(client).await {
+        client.request_token().await?;
     }
 
-    let market = None;
-
-    let (album_data, all_tracks) = tokio::try_join!(
-        client
-            .album(album_id.as_ref(), market)
-            .context(FetchAlbumSnafu),
-        client
-            .album_track(album_id.as_ref(), market)
-            .try_collect::<Vec<_>>()
-            .context(FetchTracksSnafu)
-    )?;
+    Ok(())
+}
 
-    let release_type = match album_data.album_type {
-        AlbumType::Album => ReleaseType::LP,
-        AlbumType::Compilation => ReleaseType::Compilation,
-        AlbumType::AppearsOn => {
-            ReleaseType::Other("Appears On (I don't know what this means)".into())
-        }
-        AlbumType::Single => {
-            if all_tracks.len() >= 3 {
-                ReleaseType::EP
-            } else {
-                ReleaseType::Single
-            }
-        }
+/// How many tracks to ask Spotify for at once, which is the most it allows
+const SPOTIFY_TRACKS_PAGE_LIMIT: u32 = 50;
+/// How many times a page is retried after Spotify rate limits it, before giving up on it
+const SPOTIFY_MAX_RETRIES: u32 = 5;
+/// How long to wait before the first retry when Spotify doesn't say how long to wait, doubling with each retry after that
+const SPOTIFY_INITIAL_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Whether Spotify turned the request down for being rate limited, and if so, how long it said to wait (if it did)
+fn spotify_rate_limit(error: &rspotify::ClientError) -> Option<Option<std::time::Duration>> {
+    let rspotify::ClientError::Http(http_error) = error else {
+        return None;
     };
-
-    fn spotify_artist_to_my_artist_type(spotify_artist: SimplifiedArtist) -> Artist {
-        Artist {
-            id: spotify_artist.id.as_ref().map(ToString::to_string),
-            name: spotify_artist.name,
-        }
+    let rspotify::http::HttpError::StatusCode(response) = http_error.as_ref() else {
+        return None;
+    };
+    if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return None;
     }
 
-    let date =
-        assemble_parsed_date(parse_date(&album_data.release_date)).context(DateInvalidSnafu)?;
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|retry_after| retry_after.to_str().ok())
+        .and_then(|retry_after| retry_after.trim().parse().ok())
+        .map(std::time::Duration::from_secs);
 
-    Ok(Release {
-        url: album_id.url().parse().context(ReturnedUrlInvalidSnafu)?,
-        kind: release_type,
-        title: album_data.name,
-        date,
-        main_artists: album_data
-            .artists
-            .into_iter()
-            .map(spotify_artist_to_my_artist_type)
-            .collect(),
-        tracks: all_tracks
-            .into_iter()
-            .map(|spotify_track| Track {
-                artists: spotify_track
-                    .artists
-                    .into_iter()
-                    .map(spotify_artist_to_my_artist_type)
-                    .collect(),
-            })
-            .collect(),
-        record_label: album_data.label,
-    })
+    Some(retry_after)
 }
 
-#[derive(Debug, Snafu)]
-enum GetReleaseError {
-    /// could not get release data from Spotify
-    SpotifyError { source: GetSpotifyReleaseError },
+/// Whether Spotify said it doesn't have what was asked for, which it also says when an album isn't available in the market asked about
+fn is_spotify_not_found(error: &rspotify::ClientError) -> bool {
+    let rspotify::ClientError::Http(http_error) = error else {
+        return false;
+    };
+    let rspotify::http::HttpError::StatusCode(response) = http_error.as_ref() else {
+        return false;
+    };
 
-    /// could not get release data from the web page
-    SemanticDataError { source: GetSemanticDataError },
+    response.status() == reqwest::StatusCode::NOT_FOUND
 }
 
-#[tracing::instrument(skip(spotify_client), ret)]
-async fn get_release(
-    spotify_client: &rspotify::ClientCredsSpotify,
-    url: IriRefBuf,
-) -> Result<Release, GetReleaseError> {
-    if let Ok(spotify_resource) = parse_spotify_resource(&url) {
-        get_spotify_release(spotify_client, spotify_resource)
-            .await
-            .context(SpotifySnafu)
-    } else {
-        get_semantic_data(url.as_iri_ref())
-            .await
-            .context(SemanticDataSnafu)
+/// Makes the request again for as long as Spotify rate limits it, waiting as long as it says to (or backing off exponentially if it doesn't),
+/// unless that would take more than `SPOTIFY_MAX_RETRIES` retries or go past the `deadline`
+async fn with_spotify_retries<T, Fut>(
+    deadline: tokio::time::Instant,
+    mut request: impl FnMut() -> Fut,
+) -> Result<T, rspotify::ClientError>
+where
+    Fut: Future<Output = Result<T, rspotify::ClientError>>,
+{
+    let mut backoff = SPOTIFY_INITIAL_BACKOFF;
+    let mut retries = 0;
+
+    loop {
+        let error = match request().await {
+            Ok(response) => return Ok(response),
+            Err(error) => error,
+        };
+
+        let Some(retry_after) = spotify_rate_limit(&error) else {
+            return Err(error);
+        };
+        let wait = retry_after.unwrap_or(backoff);
+        if retries >= SPOTIFY_MAX_RETRIES || tokio::time::Instant::now() + wait > deadline {
+            return Err(error);
+        }
+
+        tracing::info!(
+            ?wait,
+            retries,
+            "rate limited by Spotify, so waiting to retry"
+        );
+        tokio::time::sleep(wait).await;
+
+        retries += 1;
+        backoff *= 2;
     }
 }
 
-fn parse_list_of_artists(artists_joined: String) -> NonEmptyVec<String> {
-    let artists = NonEmptyVec::collect(artists_joined.rsplit(", ").map(ToOwned::to_owned))
-        .expect("rsplit returns at least one thing");
+/// Every track on the album as it is in `market`, a page at a time, so that a page Spotify rate limits is retried on its own
+/// instead of the tracks already gotten being thrown away (unless waiting for it would go past the `deadline`)
+#[tracing::instrument(skip(client))]
+async fn get_spotify_album_tracks(
+    client: &rspotify::ClientCredsSpotify,
+    album_id: AlbumId<'_>,
+    market: Option<Market>,
+    deadline: tokio::time::Instant,
+) -> Result<Vec<SimplifiedTrack>, rspotify::ClientError> {
+    let mut tracks = Vec::new();
+
+    loop {
+        let offset = u32::try_from(tracks.len()).unwrap_or(u32::MAX);
+        let page = with_spotify_retries(deadline, || {
+            client.album_track_manual(
+                album_id.as_ref(),
+                market,
+                Some(SPOTIFY_TRACKS_PAGE_LIMIT),
+                Some(offset),
+            )
+        })
+        .await?;
+
+        let is_last_page = page.next.is_none() || page.items.is_empty();
+        tracks.extend(page.items);
+        if is_last_page {
+            return Ok(tracks);
+        }
+    }
+}
+
+/// The link to a Spotify artist, album, or so on, always as `https://open.spotify.com/<type>/<id>`,
+/// rather than as a `spotify:` URI or with a locale, which depend on the version of rspotify
+fn open_spotify_url(id: &impl Id) -> String {
+    format!("https://open.spotify.com/{}/{}", id._type(), id.id())
+}
+
+#[tracing::instrument(skip(client), ret)]
+async fn get_spotify_release(
+    client: &rspotify::ClientCredsSpotify,
+    resource: SpotifyResource<'static>,
+    config: &Config,
+    deadline: tokio::time::Instant,
+) -> Result<Release, GetSpotifyReleaseError> {
+    ensure_spotify_token(client).await.context(TokenSnafu)?;
+
+    let album_id = match resource {
+        SpotifyResource::Album { id } => id,
+        SpotifyResource::Playlist { id } => {
+            return Err(GetSpotifyReleaseError::PlaylistNotRelease {
+                url: open_spotify_url(&id),
+            });
+        }
+        // a track's release is the album it's on, which for a standalone single is that single,
+        // so it goes through everything after this as the album it is (keeping the album's type and URL)
+        SpotifyResource::Track { id } => {
+            // looked up without a market, since Spotify can relink a track to a different album that's available there
+            let track = client.track(id, None).await.context(FetchTrackSnafu)?;
+            track.album.id.context(TrackNotOnAlbumSnafu)?
+        }
+        other => return Err(GetSpotifyReleaseError::UrlForUnsupportedResource { got: other }),
+    };
+
+    // already checked to be valid in `init`
+    let market = config.spotify_country().ok().flatten().map(Market::Country);
+
+    let fetch_album = async {
+        match client.album(album_id.as_ref(), market).await {
+            Err(error) if config.spotify_market.is_some() && is_spotify_not_found(&error) => {
+                tracing::info!(
+                    market = ?config.spotify_market,
+                    "the album isn't available in the configured market, so looking it up in any market instead"
+                );
+
+                client
+                    .album(album_id.as_ref(), None)
+                    .await
+                    .context(NotAvailableInMarketSnafu {
+                        market: config.spotify_market.clone().unwrap_or_default(),
+                    })
+            }
+            result => result.context(FetchAlbumSnafu),
+        }
+    };
+
+    // the same as the album, so the tracks match the version of it that's available there
+    let fetch_tracks = async {
+        match get_spotify_album_tracks(client, album_id.as_ref(), market, deadline).await {
+            Err(error) if market.is_some() && is_spotify_not_found(&error) => {
+                get_spotify_album_tracks(client, album_id.as_ref(), None, deadline).await
+            }
+            result => result,
+        }
+        .context(FetchTracksSnafu)
+    };
+
+    let (album_data, all_tracks) = tokio::try_join!(fetch_album, fetch_tracks)?;
+
+    let main_artist_id = album_data
+        .artists
+        .first()
+        .and_then(|main_artist| main_artist.id.clone());
+
+    let album_group = match &main_artist_id {
+        Some(main_artist_id) => get_spotify_album_group(
+            client,
+            album_id.as_ref(),
+            main_artist_id.as_ref(),
+        )
+        .await
+        .unwrap_or_else(|error| {
+            tracing::warn!(
+                error = %Report::from_error(error),
+                "couldn't get the album group from Spotify, so going by the album type alone"
+            );
+            None
+        }),
+        None => None,
+    };
+
+    let release_type = release_type_from_spotify(
+        album_data.album_type,
+        album_group,
+        all_tracks.len(),
+        config.track_count_thresholds,
+        config.spotify_album_type_mapping,
+    );
+    let release_type = if release_type == ReleaseType::LP
+        && config.detect_spotify_compilations
+        && looks_like_compilation(
+            &album_data.name,
+            album_data.artists.iter().map(|artist| artist.name.as_str()),
+        ) {
+        ReleaseType::Compilation
+    } else {
+        release_type
+    };
+
+    fn spotify_artist_to_my_artist_type(spotify_artist: SimplifiedArtist) -> Artist {
+        Artist {
+            id: spotify_artist.id.as_ref().map(ToString::to_string),
+            name: spotify_artist.name,
+            role_id: None,
+        }
+    }
+
+    ensure!(
+        !is_placeholder_date(&album_data.release_date),
+        NoReleaseDateSnafu
+    );
+    let date_precision = match album_data.release_date_precision {
+        rspotify::model::DatePrecision::Year => DatePrecision::Year,
+        rspotify::model::DatePrecision::Month => DatePrecision::Month,
+        rspotify::model::DatePrecision::Day => DatePrecision::Day,
+    };
+    let date = assemble_parsed_date(parse_date(&album_data.release_date), date_precision)
+        .context(DateInvalidSnafu)?;
+
+    let images = Vec::from_iter(album_data.images.into_iter().filter_map(|image| {
+        Some(ReleaseImage {
+            url: image.url.parse().ok()?,
+            width: image.width,
+            height: image.height,
+        })
+    }));
+
+    let explicit = all_tracks.iter().any(|track| track.explicit);
+    let upc = spotify_upc(&album_data.external_ids);
+    // every track is on disc 1 for the usual album, which isn't worth saying
+    let multiple_discs = all_tracks.iter().any(|track| track.disc_number > 1);
+
+    // Spotify rarely gives albums genres of their own, but its artists usually have some
+    let genres = match main_artist_id {
+        Some(main_artist_id) if album_data.genres.is_empty() && config.show_genres => client
+            .artist(main_artist_id)
+            .await
+            .map(|artist| artist.genres)
+            .unwrap_or_else(|error| {
+                tracing::warn!(
+                    error = %Report::from_error(error),
+                    "couldn't get the main artist's genres from Spotify"
+                );
+                Vec::new()
+            }),
+        _ => album_data.genres,
+    };
+
+    Ok(Release {
+        url: open_spotify_url(&album_id)
+            .parse()
+            .context(ReturnedUrlInvalidSnafu)?,
+        kind: release_type,
+        title: strip_edition_qualifiers(&album_data.name).to_owned(),
+        date: Some(date),
+        date_precision,
+        main_artists: album_data
+            .artists
+            .into_iter()
+            .map(spotify_artist_to_my_artist_type)
+            .collect(),
+        tracks: all_tracks
+            .into_iter()
+            .map(|spotify_track| Track {
+                position: Some(spotify_track.track_number),
+                disc_number: multiple_discs
+                    .then(|| u32::try_from(spotify_track.disc_number).ok())
+                    .flatten(),
+                title: Some(spotify_track.name),
+                artists: spotify_track
+                    .artists
+                    .into_iter()
+                    .map(spotify_artist_to_my_artist_type)
+                    .collect(),
+                artists_inherited: false,
+            })
+            .collect(),
+        record_labels: Vec::from_iter(album_data.label),
+        images,
+        undetermined: BTreeSet::new(),
+        spotify_url: None,
+        languages: Vec::new(),
+        explicit,
+        genres,
+        catalog_number: None,
+        upc,
+    })
+}
+
+#[derive(Debug, Snafu)]
+enum FindOnSpotifyError {
+    /// couldn't authenticate with Spotify
+    CrossSearchTokenError { source: rspotify::ClientError },
+
+    /// couldn't search Spotify
+    SearchError { source: rspotify::ClientError },
+}
+
+fn similarity(a: &str, b: &str) -> f64 {
+    strsim::normalized_levenshtein(&a.to_lowercase(), &b.to_lowercase())
+}
+
+/// Searches Spotify for a release that was found elsewhere, only trusting a result whose title and artist are both at least `threshold` similar
+#[tracing::instrument(skip(client), ret)]
+async fn find_on_spotify(
+    client: &rspotify::ClientCredsSpotify,
+    release: &Release,
+    threshold: f64,
+) -> Result<Option<IriRefBuf>, FindOnSpotifyError> {
+    let Some(main_artist) = release.main_artists.first() else {
+        return Ok(None);
+    };
+
+    ensure_spotify_token(client)
+        .await
+        .context(CrossSearchTokenSnafu)?;
+
+    let query = format!("album:{} artist:{}", release.title, main_artist.name);
+    let search_result = client
+        .search(&query, SearchType::Album, None, None, Some(10), None)
+        .await
+        .context(SearchSnafu)?;
+
+    let SearchResult::Albums(albums) = search_result else {
+        return Ok(None);
+    };
+
+    let best_match = albums
+        .items
+        .into_iter()
+        .filter_map(|album| {
+            let title_similarity = similarity(&album.name, &release.title);
+            let artist_similarity = album
+                .artists
+                .iter()
+                .map(|artist| similarity(&artist.name, &main_artist.name))
+                .fold(0.0, f64::max);
+
+            let score = title_similarity.min(artist_similarity);
+            (score >= threshold).then_some((score, album.id?))
+        })
+        .max_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    Ok(best_match.and_then(|(_score, id)| open_spotify_url(&id).parse().ok()))
+}
+
+#[derive(Debug, Snafu)]
+pub(super) enum GetReleaseError {
+    /// Spotify integration is unavailable on this instance right now, so try a link to the release from somewhere else
+    SpotifyUnavailable,
+
+    /// could not get release data from Spotify
+    SpotifyError { source: GetSpotifyReleaseError },
+
+    /// could not get release data from the web page
+    SemanticDataError { source: GetSemanticDataError },
+
+    /// too many releases are being fetched right now, so try again in a bit
+    TooBusy,
+
+    /// couldn't get the smart link's page to find out where the release is
+    SmartLinkError { source: GetSemanticDataError },
+
+    #[snafu(display(
+        "{url} doesn't link to the release on any service this bot supports; what's supported is: {}",
+        Service::supported_list()
+    ))]
+    NoUsableSmartLinkService { url: IriRefBuf },
+
+    /// this release has no tracks, so it may be unreleased (or its tracks couldn't all be fetched)
+    EmptyTracklist,
+
+    #[snafu(display(
+        "there's no music release data in that page, so it's probably from a service this bot doesn't support yet; what's supported is: {}",
+        Service::supported_list()
+    ))]
+    UnsupportedService { source: GetSemanticDataError },
+}
+
+/// Says what's supported when nothing could be found in the page at all, since that's what someone can do something about
+fn semantic_data_error(error: GetSemanticDataError) -> GetReleaseError {
+    match error {
+        GetSemanticDataError::ReleaseFromLdJsonError {
+            source:
+                GetReleaseFromLdJsonError::NoSemanticDataInPage
+                | GetReleaseFromLdJsonError::UnsupportedSemanticDataInPage { .. },
+        } => GetReleaseError::UnsupportedService { source: error },
+        error => GetReleaseError::SemanticDataError { source: error },
+    }
+}
+
+/// The link to the release on the service the smart link points to, going by the order of [`Service::ALL`]
+/// (so Spotify when it's there, as long as Spotify integration is available)
+#[tracing::instrument(skip(http_client), ret)]
+async fn resolve_smart_link(
+    http_client: &reqwest::Client,
+    config: &Config,
+    spotify_available: bool,
+    url: IriRefBuf,
+) -> Result<IriRefBuf, GetReleaseError> {
+    let (page, _landed_url) = fetch_page(http_client, url.as_iri_ref(), config)
+        .await
+        .context(SmartLinkSnafu)?;
+
+    let service_links = service_links_in_page(&page);
+
+    Service::ALL
+        .into_iter()
+        .filter(|service| spotify_available || *service != Service::Spotify)
+        .find_map(|preferred_service| {
+            service_links
+                .iter()
+                .find(|(service, _link)| *service == preferred_service)
+                .map(|(_service, link)| link.clone())
+        })
+        .context(NoUsableSmartLinkServiceSnafu { url })
+}
+
+#[tracing::instrument(skip(spotify_client, http_client, fetch_permits), ret)]
+pub(super) async fn get_release(
+    spotify_client: Option<&rspotify::ClientCredsSpotify>,
+    http_client: &reqwest::Client,
+    fetch_permits: &Semaphore,
+    config: &Config,
+    url: IriRefBuf,
+    deadline: tokio::time::Instant,
+) -> Result<Release, GetReleaseError> {
+    // waiting for other releases to finish being fetched can't go past when this one has to be done by
+    let _permit = tokio::time::timeout_at(deadline, fetch_permits.acquire())
+        .await
+        .ok()
+        .context(TooBusySnafu)?
+        .expect("the semaphore is never closed");
+
+    let url = if is_smart_link(url.as_iri_ref()) {
+        resolve_smart_link(http_client, config, spotify_client.is_some(), url).await?
+    } else {
+        url
+    };
+
+    let mut release = if let Ok(spotify_resource) = parse_spotify_resource(&url) {
+        let spotify_client = spotify_client.context(SpotifyUnavailableSnafu)?;
+
+        get_spotify_release(spotify_client, spotify_resource, config, deadline)
+            .await
+            .context(SpotifySnafu)?
+    } else {
+        let mut release = match get_semantic_data(http_client, url.as_iri_ref(), config).await {
+            Err(error @ GetSemanticDataError::ReleaseFromLdJsonError { .. }) => {
+                let Some(embed_url) = apple_music_embed_url(&url) else {
+                    return Err(semantic_data_error(error));
+                };
+
+                match get_semantic_data(http_client, embed_url.as_iri_ref(), config).await {
+                    Ok(release) => Release {
+                        // the embed page is only for getting the data, not for linking to
+                        url: url.clone(),
+                        ..release
+                    },
+                    Err(embed_error) => {
+                        tracing::debug!(
+                            embed_error = %Report::from_error(embed_error),
+                            "couldn't fall back to the embed version of this Apple Music page either"
+                        );
+                        return Err(semantic_data_error(error));
+                    }
+                }
+            }
+            other => other.map_err(semantic_data_error)?,
+        };
+
+        if let Some(spotify_client) = spotify_client.filter(|_| config.spotify_cross_search) {
+            match find_on_spotify(
+                spotify_client,
+                &release,
+                config.spotify_cross_search_threshold,
+            )
+            .await
+            {
+                Ok(spotify_url) => release.spotify_url = spotify_url,
+                Err(error) => tracing::warn!(
+                    error = %Report::from_error(error),
+                    "couldn't look for this release on Spotify"
+                ),
+            }
+        }
+
+        release
+    };
+
+    // OpenGraph data can leave the tracks out entirely, which is already called out as undetermined,
+    // but a list of them that's empty would otherwise be posted as a release with 0 tracks
+    ensure!(
+        !release.tracks.is_empty() || release.undetermined.contains(&ReleaseField::Tracks),
+        EmptyTracklistSnafu
+    );
+
+    // the canonical URLs data sources give back can have tracking parameters of their own
+    release.url = strip_tracking_query_params(release.url);
+
+    Ok(release)
+}
+
+fn parse_list_of_artists(
+    artists_joined: String,
+    comma_artist_names: &[String],
+) -> NonEmptyVec<String> {
+    // names that would be torn apart by the splitting are swapped out for placeholders until it's done
+    let placeholder =
+        |index: usize| format!("{ARTIST_NAME_PLACEHOLDER}{index}{ARTIST_NAME_PLACEHOLDER}");
+    let mut artists_joined = artists_joined;
+    let mut protected_names = Vec::new();
+    for name in KNOWN_COMMA_ARTIST_NAMES
+        .iter()
+        .copied()
+        .chain(comma_artist_names.iter().map(String::as_str))
+    {
+        if !name.is_empty() && artists_joined.contains(name) {
+            artists_joined = artists_joined.replace(name, &placeholder(protected_names.len()));
+            protected_names.push(name);
+        }
+    }
+    let restore = |mut artist: String| {
+        for (index, name) in protected_names.iter().enumerate() {
+            artist = artist.replace(&placeholder(index), name);
+        }
+        artist
+    };
+
+    let artists = NonEmptyVec::collect(artists_joined.rsplit(", ").map(ToOwned::to_owned))
+        .expect("rsplit returns at least one thing");
 
     let last = artists.head;
     let last_ampersand = last.split(" & ").map(ToOwned::to_owned);
@@ -638,8 +2427,87 @@ fn parse_list_of_artists(artists_joined: String) -> NonEmptyVec<String> {
     let mut rest = artists.tail;
     rest.reverse();
 
-    NonEmptyVec::collect(rest.into_iter().chain(last_ampersand)).expect(
-        "rsplit returned at least one thing earlier, so there is still at least one thing now",
+    NonEmptyVec::collect(rest.into_iter().chain(last_ampersand))
+        .expect(
+            "rsplit returned at least one thing earlier, so there is still at least one thing now",
+        )
+        .map(restore)
+}
+
+/// Co-releases can have all of their labels in one string (like "Label A / Label B" or "Label A & Label B"),
+/// which is split apart so each label can match its own role.
+/// Unlike artists, labels aren't split on commas, since so many of them end in ", Inc." or ", LLC".
+/// A string that's a role's name as a whole is kept together, since some labels have an "&" in their name
+fn split_record_label(record_label: &str, roles_map: &BTreeMap<Uncased<'_>, Role>) -> Vec<String> {
+    if roles_map.contains_key(UncasedStr::new(record_label)) {
+        return vec![record_label.to_owned()];
+    }
+
+    record_label
+        .split(" / ")
+        .flat_map(|part| part.split(" & "))
+        .map(|label| label.trim().to_owned())
+        .filter(|label| !label.is_empty())
+        .collect()
+}
+
+/// Artists whose names have ", " (or " & ") in them, which would otherwise be split into several made-up artists.
+/// More can be configured with `comma_artist_names`
+const KNOWN_COMMA_ARTIST_NAMES: &[&str] = &[
+    "Tyler, The Creator",
+    "Earth, Wind & Fire",
+    "Crosby, Stills, Nash & Young",
+    "Crosby, Stills & Nash",
+    "Emerson, Lake & Palmer",
+    "Blood, Sweat & Tears",
+    "Peter, Paul and Mary",
+];
+/// Stands in for a protected artist name while a list of artists is split, since it never shows up in real names
+const ARTIST_NAME_PLACEHOLDER: char = '\u{1F}';
+
+const LANGUAGES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("de", "German"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("hi", "Hindi"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("th", "Thai"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+];
+
+/// Recognizes a language from either its BCP 47 code (ignoring any region, like the `US` in `en-US`) or its English name
+fn known_language(language: &str) -> Option<(&'static str, &'static str)> {
+    let (primary_subtag, _rest) = try_split_once(language.trim(), "-");
+
+    LANGUAGES.iter().copied().find(|(code, name)| {
+        primary_subtag.eq_ignore_ascii_case(code) || language.trim().eq_ignore_ascii_case(name)
+    })
+}
+
+fn is_same_language(a: &str, b: &str) -> bool {
+    match (known_language(a), known_language(b)) {
+        (Some((a_code, _)), Some((b_code, _))) => a_code == b_code,
+        _ => a.trim().eq_ignore_ascii_case(b.trim()),
+    }
+}
+
+fn language_name(language: &str) -> String {
+    known_language(language).map_or_else(
+        || language.trim().to_owned(),
+        |(_code, name)| name.to_owned(),
     )
 }
 
@@ -650,44 +2518,94 @@ fn format_or_role(name: &str, roles_map: &BTreeMap<Uncased, Role>) -> String {
     }
 }
 
-fn format_release(
-    Release {
-        url,
-        mut kind,
-        mut title,
-        date,
-        main_artists,
-        tracks,
-        record_label,
-    }: Release,
-    roles_map: BTreeMap<Uncased<'_>, Role>,
-) -> String {
-    let mut unique_artist_ids = AHashSet::new();
+/// Replaces (rather than adds to) the release's main artists with the ones someone gave by hand.
+///
+/// Artists that were only corrected in casing keep the IDs they had, so they're still recognized as the same artists when they're credited on tracks too.
+fn override_main_artists(
+    release: &mut Release,
+    artists_joined: String,
+    comma_artist_names: &[String],
+) {
+    let found_main_artists = std::mem::take(&mut release.main_artists);
+
+    release.main_artists = parse_list_of_artists(artists_joined, comma_artist_names)
+        .map(|name| Artist {
+            id: found_main_artists
+                .iter()
+                .find(|found| found.name.eq_ignore_ascii_case(&name))
+                .and_then(|found| found.id.clone()),
+            name,
+            role_id: None,
+        })
+        .into();
+}
 
-    let mut main_artist_names = Vec::new();
-    for main_artist in main_artists {
-        if let Some(artist_id) = main_artist.id {
-            if unique_artist_ids.insert(artist_id) {
-                main_artist_names.push(main_artist.name);
-            }
-        }
+/// Pings the role configured for the artist's ID if there is one, and otherwise goes by their name like [`format_or_role`]
+fn format_artist_or_role(artist: &Artist, roles_map: &BTreeMap<Uncased, Role>) -> String {
+    match artist.role_id {
+        Some(role_id) => format!("<@&{role_id}>"),
+        None => format_or_role(&artist.name, roles_map),
     }
+}
 
-    let n_tracks = tracks.len();
+/// Gives every artist in the release whose Spotify ID has a role configured for it in this server that role
+pub(super) fn assign_artist_roles(
+    release: &mut Release,
+    artist_roles: &[ArtistRole],
+    guild_id: DiscordId<GuildMarker>,
+) {
+    let role_ids = BTreeMap::from_iter(
+        artist_roles
+            .iter()
+            .filter(|artist_role| artist_role.guild_id == guild_id)
+            .map(|artist_role| (artist_role.spotify_artist_id.as_str(), artist_role.role_id)),
+    );
+    if role_ids.is_empty() {
+        return;
+    }
 
-    let mut additional_artist_names = Vec::new();
-    for track in tracks {
-        for track_artist in track.artists {
-            if let Some(artist_id) = track_artist.id {
-                if unique_artist_ids.insert(artist_id) {
-                    additional_artist_names.push(track_artist.name);
-                }
-            }
+    let artists = release.main_artists.iter_mut().chain(
+        release
+            .tracks
+            .iter_mut()
+            .flat_map(|track| &mut track.artists),
+    );
+    for artist in artists {
+        if let Some(role_id) = artist.spotify_id().and_then(|id| role_ids.get(id)) {
+            artist.role_id = Some(*role_id);
         }
     }
+}
+
+/// A line crediting a track's own artists, like "3. Title — Artist"
+fn format_track_credit(track: &Track, roles_map: &BTreeMap<Uncased<'_>, Role>) -> String {
+    let artists_joined = track
+        .artists
+        .iter()
+        .map(|artist| format_artist_or_role(artist, roles_map))
+        .join(" & ");
+
+    let position = track.position.map(|position| match track.disc_number {
+        Some(disc_number) => format!("{disc_number}-{position}"),
+        None => position.to_string(),
+    });
+
+    match (position, &track.title) {
+        (Some(position), Some(title)) => format!("{position}. {title} — {artists_joined}"),
+        (Some(position), None) => format!("{position}. {artists_joined}"),
+        (None, Some(title)) => format!("{title} — {artists_joined}"),
+        (None, None) => artists_joined,
+    }
+}
+
+/// Takes a release type that's tacked onto the end of the title (like " - EP" or " (Remixes)") out of it,
+/// since it's said separately
+fn kind_from_title_suffix(mut title: String, mut kind: ReleaseType) -> (String, ReleaseType) {
+    // a video of a remix (or of a song called "Something EP") is still a video
+    if kind == ReleaseType::Video {
+        return (title, kind);
+    }
 
-    // TODO: move this kind of logic out of here because "mutating" release data doesn't fit in with the theme of formatting,
-    // and some data providers might already be well-behaved on this front so this should only apply to ones that aren't
     if let Some(new_title) = title.strip_suffix(" - EP") {
         title = new_title.into();
         kind = ReleaseType::EP;
@@ -717,97 +2635,474 @@ fn format_release(
         kind = ReleaseType::Remixes;
     }
 
-    let release_type = match kind {
-        ReleaseType::Single => None,
-        ReleaseType::EP => Some("EP".to_owned()),
-        ReleaseType::LP => Some("LP".to_owned()),
-        ReleaseType::Compilation => Some("Compilation".to_owned()),
-        ReleaseType::Remixes => Some("Remixes".to_owned()),
-        ReleaseType::Other(s) => Some(s),
-    };
+    (title, kind)
+}
 
-    // TODO: move this kind of logic out of here because "mutating" release data doesn't fit in with the theme of formatting,
-    // and some data providers might already be well-behaved on this front so this should only apply to ones that aren't
-    let (title, features) = match readf("{} (feat. {})", &title) {
-        Some(args) => {
-            let [title, features] = args.try_into().expect(
-                "there should be two things returned because I wrote two {}s in the format string",
-            );
+/// Where a release's record label goes when it's formatted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LabelPlacement {
+    /// In the brackets after the title, like "[March 3 on Label]"
+    Inline,
+    /// Left out of the message, to be shown on its own (like in an embed's footer)
+    Separate,
+}
 
-            (title, Some(parse_list_of_artists(features)))
-        }
-        None => (title, None),
-    };
-    let features_set = features
-        .as_ref()
-        .map(BTreeSet::from_iter)
-        .unwrap_or_default();
-    let (title, remixers) = match readf("{} ({} Remix)", &title) {
-        Some(args) => {
-            let [title, remixers] = args.try_into().expect(
-                "there should be two things returned because I wrote two {}s in the format string",
-            );
+/// A formatted release, along with what was asked to be kept out of the message
+#[derive(Debug)]
+pub(super) struct FormattedRelease {
+    pub message: String,
+    /// The release's record labels as plain text, since wherever they're shown instead can't ping roles.
+    /// Only ever filled in for [`LabelPlacement::Separate`]
+    pub label: Option<String>,
+    /// Only ever filled in when `show_upc` is configured
+    pub upc: Option<String>,
+    /// Whether no artist at all (not even "Various Artists") made it into the message
+    pub artist_missing: bool,
+    /// Whether the release had no title (or only whitespace), which leaves the link to it with no text
+    pub title_missing: bool,
+}
 
-            (title, Some(parse_list_of_artists(remixers)))
-        }
-        None => (title, None),
-    };
-    let remixers_set = remixers
-        .as_ref()
-        .map(BTreeSet::from_iter)
-        .unwrap_or_default();
+#[derive(Debug, Snafu)]
+pub(super) enum EmptyFormattedReleaseError {
+    /// the release came out blank once it was formatted
+    NothingFormatted,
 
-    main_artist_names
-        .retain(|artist| !(features_set.contains(artist) || remixers_set.contains(artist)));
-    additional_artist_names
-        .retain(|artist| !(features_set.contains(artist) || remixers_set.contains(artist)));
+    /// the release has no title to show once it's formatted
+    NoTitleFormatted,
 
-    let now = OffsetDateTime::now_utc();
-    let almost_midnight_today = now.replace_time(Time::MAX);
+    /// couldn't determine who the release is by
+    NoArtistFormatted,
+}
 
-    let release_datetime = OffsetDateTime::new_utc(date, Time::MIDNIGHT);
+impl FormattedRelease {
+    /// Catches releases that format to next to nothing (like when every artist was taken out as a feature or remixer and the title is empty),
+    /// so they're reported instead of being shown blank.
+    /// Releases with no artist are only reported when `missing_artist` says to
+    pub(super) fn validate(&self, config: &Config) -> Result<(), EmptyFormattedReleaseError> {
+        let first_line = self.message.lines().next().unwrap_or_default();
+
+        ensure!(!first_line.trim().is_empty(), NothingFormattedSnafu);
+        ensure!(!self.title_missing, NoTitleFormattedSnafu);
+        ensure!(
+            !(self.artist_missing && matches!(config.missing_artist, MissingArtist::Error)),
+            NoArtistFormattedSnafu
+        );
 
-    // or time to release if it's negative
-    let time_since_release = almost_midnight_today - release_datetime;
+        Ok(())
+    }
+}
 
-    let year = date.year();
-    let month = date.month() as u8;
-    let day = date.day();
+pub(super) fn format_release(
+    release: Release,
+    roles_map: &BTreeMap<Uncased<'_>, Role>,
+    config: &Config,
+    label_placement: LabelPlacement,
+) -> FormattedRelease {
+    format_release_at(
+        release,
+        roles_map,
+        config,
+        label_placement,
+        OffsetDateTime::now_utc(),
+    )
+}
 
-    let release_date = if time_since_release < time::Duration::weeks(52) {
-        format!("{month}/{day}")
-    } else {
-        format!("{year}/{month}/{day}")
+// https://discord.com/developers/docs/resources/message#create-message-jsonform-params
+const MESSAGE_CONTENT_LIMIT: usize = 2000;
+
+const DIGEST_SEPARATOR: &str = "\n\n";
+
+fn digest_section_header(kind: &ReleaseType) -> String {
+    let header = match kind {
+        ReleaseType::Single => "Singles",
+        ReleaseType::EP => "EPs",
+        ReleaseType::LP => "LPs",
+        ReleaseType::Compilation => "Compilations",
+        ReleaseType::Remixes => "Remixes",
+        ReleaseType::Video => "Music Videos",
+        // there's no telling how to pluralize whatever a release was called, so it's used as it is
+        ReleaseType::Other(other) => other,
     };
 
-    let mut first_line = format!("[{title}](<{url}>)");
+    format!("## {header}")
+}
 
-    if let Some(remixers) = remixers {
-        let remixers_joined = remixers
-            .into_iter()
-            .map(|name| format_or_role(&name, &roles_map))
-            .join(" & ");
+/// A release formatted for a [`digest`], with the type it's grouped under
+#[derive(Debug)]
+pub(super) struct DigestEntry {
+    kind: ReleaseType,
+    message: String,
+}
 
-        first_line = format!("{first_line} ({remixers_joined} Remix)");
-    }
+#[derive(Debug, Snafu)]
+pub(super) enum DigestEntryError {
+    /// couldn't format the release into something worth posting
+    NotWorthPosting { source: EmptyFormattedReleaseError },
 
-    if let Some(release_type) = release_type {
-        let release_type_and_tracks = format!("{release_type}, {n_tracks} tracks");
+    /// the release is {length} characters long under its header, which is more than a message can be, so post it on its own in parts instead
+    TooLongForMessage { length: usize },
+}
 
-        first_line = format!("{first_line} ({release_type_and_tracks})");
-    }
+/// Formats a release for a [`digest`], checking that it's worth posting like any other formatted release,
+/// and that it fits in a message even when it has to start one under its header
+pub(super) fn digest_entry(
+    release: Release,
+    roles_map: &BTreeMap<Uncased<'_>, Role>,
+    config: &Config,
+) -> Result<DigestEntry, DigestEntryError> {
+    let (_title, kind) = kind_from_title_suffix(release.title.clone(), release.kind.clone());
+    let formatted_release = format_release(release, roles_map, config, LabelPlacement::Inline);
+    formatted_release
+        .validate(config)
+        .context(NotWorthPostingSnafu)?;
+
+    let length = digest_section_header(&kind).chars().count()
+        + 1
+        + formatted_release.message.chars().count();
+    ensure!(
+        length <= MESSAGE_CONTENT_LIMIT,
+        TooLongForMessageSnafu { length }
+    );
+
+    Ok(DigestEntry {
+        kind,
+        message: formatted_release.message,
+    })
+}
+
+/// Puts the releases together for a roundup, grouped under a header for each type of release (in the order they were given within each type),
+/// and split into as many messages as it takes to fit them all.
+///
+/// A type's header is repeated at the top of the next message when its releases continue there.
+/// Every entry fits in a message under its header (which [`digest_entry`] makes sure of), so no message ends up too long to post.
+pub(super) fn digest(entries: Vec<DigestEntry>) -> Vec<String> {
+    let mut sections = BTreeMap::<ReleaseType, Vec<String>>::new();
+    for DigestEntry { kind, message } in entries {
+        sections.entry(kind).or_default().push(message);
+    }
+
+    let mut messages = Vec::new();
+    let mut message = String::new();
+    // which section's header the message being built most recently had, so it isn't repeated for every release
+    let mut message_section = None;
+
+    for (kind, formatted_releases) in sections {
+        let header = digest_section_header(&kind);
+
+        for formatted_release in formatted_releases {
+            let with_header = format!("{header}\n{formatted_release}");
+            let mut block = if message_section.as_ref() == Some(&kind) {
+                formatted_release
+            } else {
+                with_header.clone()
+            };
+
+            let fits = if message.is_empty() {
+                block.chars().count() <= MESSAGE_CONTENT_LIMIT
+            } else {
+                message.chars().count() + DIGEST_SEPARATOR.len() + block.chars().count()
+                    <= MESSAGE_CONTENT_LIMIT
+            };
+            if !fits {
+                if !message.is_empty() {
+                    messages.push(std::mem::take(&mut message));
+                }
+                block = with_header;
+            }
+            debug_assert!(
+                block.chars().count() <= MESSAGE_CONTENT_LIMIT,
+                "digest entries are made to fit in a message under their header"
+            );
+
+            if !message.is_empty() {
+                message.push_str(DIGEST_SEPARATOR);
+            }
+            message.push_str(&block);
+            message_section = Some(kind.clone());
+        }
+    }
+
+    if !message.is_empty() {
+        messages.push(message);
+    }
+
+    messages
+}
+
+/// How long ago a release came out (or how soon it will, when `days_since_release` is negative), in the largest unit that fits.
+/// Upcoming releases' dates already say "out", so this doesn't
+fn format_relative_date(days_since_release: i64) -> String {
+    let plural = |n: i64, unit: &str| {
+        if n == 1 {
+            format!("{n} {unit}")
+        } else {
+            format!("{n} {unit}s")
+        }
+    };
+
+    match days_since_release {
+        0 => "out today".to_owned(),
+        1 => "yesterday".to_owned(),
+        -1 => "tomorrow".to_owned(),
+        2..=13 => format!("{} ago", plural(days_since_release, "day")),
+        14..=59 => format!("{} ago", plural(days_since_release / 7, "week")),
+        60..=364 => format!("{} ago", plural(days_since_release / 30, "month")),
+        365.. => format!("{} ago", plural(days_since_release / 365, "year")),
+        -13..=-2 => format!("in {}", plural(-days_since_release, "day")),
+        -59..=-14 => format!("in {}", plural(-days_since_release / 7, "week")),
+        -364..=-60 => format!("in {}", plural(-days_since_release / 30, "month")),
+        ..=-365 => format!("in {}", plural(-days_since_release / 365, "year")),
+    }
+}
+
+/// [`format_release`] as of `now`, which decides whether the release date is recent enough to leave the year out
+fn format_release_at(
+    Release {
+        url,
+        kind,
+        title,
+        date,
+        date_precision,
+        main_artists,
+        tracks,
+        record_labels,
+        spotify_url,
+        languages,
+        explicit,
+        genres,
+        catalog_number,
+        upc,
+        ..
+    }: Release,
+    roles_map: &BTreeMap<Uncased<'_>, Role>,
+    config: &Config,
+    label_placement: LabelPlacement,
+    now: OffsetDateTime,
+) -> FormattedRelease {
+    let title_missing = title.trim().is_empty();
+
+    // artists that are only credited by name (like in the title) still get their configured role when they're credited properly elsewhere
+    let artist_role_ids = AHashMap::<String, DiscordId<RoleMarker>>::from_iter(
+        main_artists
+            .iter()
+            .chain(tracks.iter().flat_map(|track| &track.artists))
+            .filter_map(|artist| Some((artist.name.clone(), artist.role_id?))),
+    );
+    let format_artist_name_or_role = |name: &str| match artist_role_ids.get(name) {
+        Some(role_id) => format!("<@&{role_id}>"),
+        None => format_or_role(name, roles_map),
+    };
+
+    let mut unique_artist_keys = AHashSet::new();
+
+    let mut main_artist_names = Vec::new();
+    for main_artist in main_artists {
+        if unique_artist_keys.insert(main_artist.key()) {
+            main_artist_names.push(main_artist.name);
+        }
+    }
+
+    let n_tracks = tracks.len();
+
+    let track_credits = Vec::from_iter(
+        tracks
+            .iter()
+            .filter(|track| config.per_track_credits && !track.artists_inherited)
+            .map(|track| format_track_credit(track, roles_map)),
+    );
+
+    // a single's features are often only in its one track's title (like Spotify's), but they're still the whole release's features
+    let single_track_features = match tracks.as_slice() {
+        [track] => track
+            .title
+            .as_deref()
+            .and_then(|track_title| readf("{} (feat. {})", track_title))
+            .and_then(|args| args.into_iter().nth(1))
+            .map(|features| parse_list_of_artists(features, &config.comma_artist_names)),
+        _ => None,
+    };
+
+    let mut additional_artist_names = Vec::new();
+    // only tracks with their own credits can introduce artists beyond the main ones
+    for track in tracks.into_iter().filter(|track| !track.artists_inherited) {
+        for track_artist in track.artists {
+            if unique_artist_keys.insert(track_artist.key()) {
+                additional_artist_names.push(track_artist.name);
+            }
+        }
+    }
+
+    // TODO: move this kind of logic out of here because "mutating" release data doesn't fit in with the theme of formatting,
+    // and some data providers might already be well-behaved on this front so this should only apply to ones that aren't
+    let (title, kind) = kind_from_title_suffix(title, kind);
+
+    // a video isn't made up of tracks, even though it's given one to stand in for the song in it
+    let show_track_count = n_tracks > 0 && kind != ReleaseType::Video;
+
+    // singles are the usual case, so they go without saying
+    let release_type = (kind != ReleaseType::Single).then(|| kind.to_string());
+
+    // TODO: move this kind of logic out of here because "mutating" release data doesn't fit in with the theme of formatting,
+    // and some data providers might already be well-behaved on this front so this should only apply to ones that aren't
+    let (title, features) = match readf("{} (feat. {})", &title) {
+        Some(args) => {
+            let [title, features] = args.try_into().expect(
+                "there should be two things returned because I wrote two {}s in the format string",
+            );
+
+            (
+                title,
+                Some(parse_list_of_artists(features, &config.comma_artist_names)),
+            )
+        }
+        None => (title, None),
+    };
+    let mut features = match (features, single_track_features) {
+        (Some(features), Some(single_track_features)) => {
+            NonEmptyVec::collect(features.into_iter().chain(single_track_features).unique())
+        }
+        (features, single_track_features) => features.or(single_track_features),
+    };
+    // owned, since the features themselves can still be changed below
+    let features_set = features
+        .iter()
+        .flat_map(|features| features.iter())
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let (title, remixers) = match readf("{} ({} Remix)", &title) {
+        Some(args) => {
+            let [title, remixers] = args.try_into().expect(
+                "there should be two things returned because I wrote two {}s in the format string",
+            );
+
+            (
+                title,
+                Some(parse_list_of_artists(remixers, &config.comma_artist_names)),
+            )
+        }
+        None => (title, None),
+    };
+    let remixers_set = remixers
+        .as_ref()
+        .map(BTreeSet::from_iter)
+        .unwrap_or_default();
+
+    let is_credited_separately =
+        |artist: &String| features_set.contains(artist) || remixers_set.contains(artist);
+
+    // a release where every main artist is also featured would otherwise show no main artist at all,
+    // so they stay main artists, and aren't also shown as featured on their own release
+    let overlapping_credits = match config.overlapping_credits {
+        OverlappingCredits::Feature if main_artist_names.iter().all(is_credited_separately) => {
+            OverlappingCredits::Main
+        }
+        overlapping_credits => overlapping_credits,
+    };
+    match overlapping_credits {
+        OverlappingCredits::Feature => {
+            main_artist_names.retain(|artist| !is_credited_separately(artist));
+        }
+        OverlappingCredits::Main => {
+            let main_artists_set = BTreeSet::from_iter(main_artist_names.clone());
+            features = features.and_then(|features| {
+                NonEmptyVec::collect(
+                    features
+                        .into_iter()
+                        .filter(|artist| !main_artists_set.contains(artist)),
+                )
+            });
+
+            if !main_artist_names
+                .iter()
+                .all(|artist| remixers_set.contains(artist))
+            {
+                main_artist_names.retain(|artist| !remixers_set.contains(artist));
+            }
+        }
+        OverlappingCredits::Both => {}
+    }
+    additional_artist_names.retain(|artist| !is_credited_separately(artist));
+
+    let release_date = date.map(|date| {
+        let almost_midnight_today = now.replace_time(Time::MAX);
+
+        let release_datetime = OffsetDateTime::new_utc(date, Time::MIDNIGHT);
+
+        // or time to release if it's negative
+        let time_since_release = almost_midnight_today - release_datetime;
+        let is_upcoming = date > now.date();
+
+        let year = date.year();
+        let month = date.month() as u8;
+        let day = date.day();
+
+        let formatted_date = match date_precision {
+            DatePrecision::Year => format!("{year}"),
+            DatePrecision::Month => format!("{year}/{month}"),
+            // releases coming up within a year are just as clear without it as ones that came out within a year
+            DatePrecision::Day if time_since_release.abs() < time::Duration::weeks(52) => {
+                format!("{month}/{day}")
+            }
+            DatePrecision::Day => format!("{year}/{month}/{day}"),
+        };
+
+        // so that announcing an upcoming release doesn't read like it already came out
+        let formatted_date = if is_upcoming {
+            format!("out {formatted_date}")
+        } else {
+            formatted_date
+        };
+
+        match date_precision {
+            DatePrecision::Day if config.show_relative_date => {
+                let relative_date = format_relative_date((now.date() - date).whole_days());
+                format!("{formatted_date} ({relative_date})")
+            }
+            _ => formatted_date,
+        }
+    });
+
+    let mut first_line = format!("[{title}](<{url}>)");
+
+    if explicit && config.show_explicit_marker {
+        first_line = format!("{first_line} 🅴");
+    }
+
+    if let Some(spotify_url) = spotify_url {
+        first_line = format!("{first_line} ([Spotify](<{spotify_url}>))");
+    }
+
+    if let Some(remixers) = remixers {
+        let remixers_joined = remixers
+            .into_iter()
+            .map(|name| format_artist_name_or_role(&name))
+            .join(" & ");
+
+        first_line = format!("{first_line} ({remixers_joined} Remix)");
+    }
+
+    if let Some(release_type) = release_type {
+        let release_type_and_tracks = if show_track_count {
+            format!("{release_type}, {n_tracks} tracks")
+        } else {
+            release_type
+        };
+
+        first_line = format!("{first_line} ({release_type_and_tracks})");
+    }
 
     let featured_artists_joined = features.map(|features| {
         features
             .into_iter()
-            .map(|name| format_or_role(&name, &roles_map))
+            .map(|name| format_artist_name_or_role(&name))
             .join(" & ")
     });
 
+    let artist_missing = main_artist_names.is_empty() && featured_artists_joined.is_none();
     if !main_artist_names.is_empty() && main_artist_names != vec!["Various Artists".to_string()] {
         let main_artists_joined = main_artist_names
             .into_iter()
-            .map(|name| format_or_role(&name, &roles_map))
+            .map(|name| format_artist_name_or_role(&name))
             .join(" & ");
         let mut main_artists_section = main_artists_joined;
 
@@ -821,29 +3116,213 @@ fn format_release(
         first_line = format!("{featured_artists_joined} - {first_line}");
     }
 
-    let mut in_brackets = format!("{release_date}");
-    if let Some(record_label) = record_label {
-        if roles_map.contains_key(UncasedStr::new(&record_label)) {
-            let formatted_label = format_or_role(&record_label, &roles_map);
-            in_brackets = format!("{in_brackets} on {formatted_label}");
+    let mut shown_labels = record_labels
+        .iter()
+        .flat_map(|record_label| split_record_label(record_label, roles_map))
+        .unique()
+        .filter(|record_label| match config.label_display {
+            LabelDisplay::Never => false,
+            LabelDisplay::WhenRole => {
+                roles_map.contains_key(UncasedStr::new(record_label.as_str()))
+            }
+            LabelDisplay::Always => true,
+        });
+
+    let (formatted_label, separate_label) = match label_placement {
+        LabelPlacement::Inline => {
+            let formatted_labels = shown_labels
+                .map(|record_label| format_or_role(&record_label, roles_map))
+                .join(" & ");
+
+            (
+                (!formatted_labels.is_empty()).then_some(formatted_labels),
+                None,
+            )
+        }
+        LabelPlacement::Separate => {
+            let plain_labels = shown_labels.join(" & ");
+
+            (None, (!plain_labels.is_empty()).then_some(plain_labels))
+        }
+    };
+
+    let in_brackets = match (release_date, formatted_label) {
+        (Some(release_date), Some(formatted_label)) => {
+            Some(format!("{release_date} on {formatted_label}"))
+        }
+        (Some(release_date), None) => Some(release_date),
+        (None, Some(formatted_label)) => Some(format!("on {formatted_label}")),
+        (None, None) => None,
+    };
+
+    let in_brackets = match (in_brackets, catalog_number) {
+        (Some(in_brackets), Some(catalog_number)) => {
+            Some(format!("{in_brackets}, {catalog_number}"))
         }
+        (None, Some(catalog_number)) => Some(catalog_number),
+        (in_brackets, None) => in_brackets,
+    };
+
+    let shown_languages = languages
+        .iter()
+        .filter(|_| config.show_language)
+        .filter(|language| !is_same_language(language, &config.primary_language))
+        .map(|language| language_name(language))
+        .unique()
+        .join(", ");
+
+    let in_brackets = match (in_brackets, shown_languages.is_empty()) {
+        (in_brackets, true) => in_brackets,
+        (Some(in_brackets), false) => Some(format!("{in_brackets}, in {shown_languages}")),
+        (None, false) => Some(format!("in {shown_languages}")),
+    };
+
+    if let Some(in_brackets) = in_brackets {
+        first_line = format!("{first_line} [{in_brackets}]");
     }
 
-    first_line = format!("{first_line} [{in_brackets}]");
+    let indicators = &config.release_type_indicators;
+    let indicator = match kind {
+        ReleaseType::Single => indicators.single.as_deref(),
+        ReleaseType::EP => indicators.ep.as_deref(),
+        ReleaseType::LP => indicators.lp.as_deref(),
+        ReleaseType::Compilation => indicators.compilation.as_deref(),
+        ReleaseType::Remixes => indicators.remixes.as_deref(),
+        ReleaseType::Video => indicators.video.as_deref(),
+        ReleaseType::Other(_) => indicators.other_or_default(),
+    };
+    if let Some(indicator) = indicator {
+        first_line = format!("{indicator} {first_line}");
+    }
 
     let additional_artist_names = NonEmptyVec::from_vec(additional_artist_names);
     let additional_artists_and_pings = additional_artist_names.map(|names| {
-        names
+        let n_names = names.len();
+        let n_shown = config
+            .max_additional_artists
+            .unwrap_or(n_names)
+            .min(n_names);
+        let n_hidden = n_names - n_shown;
+
+        let shown = names
             .into_iter()
-            .map(|name| format_or_role(&name, &roles_map))
-            .join(", ")
+            .take(n_shown)
+            .map(|name| format_artist_name_or_role(&name))
+            .join(", ");
+
+        match (shown.is_empty(), n_hidden) {
+            (_, 0) => shown,
+            (true, n_hidden) => format!("{n_hidden} more"),
+            (false, n_hidden) => format!("{shown} and {n_hidden} more"),
+        }
+    });
+    let second_line = if track_credits.is_empty() {
+        additional_artists_and_pings.map(|s| format!("with {s}"))
+    } else {
+        Some(track_credits.join("\n"))
+    };
+
+    let genres_line = (config.show_genres && !genres.is_empty()).then(|| {
+        let genres_joined = genres
+            .iter()
+            .map(|genre| {
+                if config.ping_genre_roles {
+                    format_or_role(genre, roles_map)
+                } else {
+                    genre.to_owned()
+                }
+            })
+            .join(", ");
+
+        format!("genres: {genres_joined}")
     });
-    let second_line = additional_artists_and_pings.map(|s| format!("with {s}"));
 
-    [Some(first_line), second_line]
+    let message = [Some(first_line), second_line, genres_line]
         .into_iter()
         .flatten()
-        .join("\n")
+        .join("\n");
+
+    FormattedRelease {
+        message,
+        label: separate_label,
+        upc: upc.filter(|_| config.show_upc),
+        artist_missing,
+        title_missing,
+    }
+}
+
+/// The title of the embed in `new-release`'s response that shows the formatted release as it will look,
+/// which is there whether or not the copyable version is
+const PREVIEW_EMBED_TITLE: &str = "Preview";
+/// Told to whoever ran the command when the release came out with no artist, which otherwise looks like a mistake in the formatting
+const MISSING_ARTIST_WARNING: &str =
+    "Couldn't determine who this release is by, so fill in the artist yourself.";
+
+#[derive(Debug, Snafu)]
+pub(super) enum PostPubliclyError {
+    /// the button wasn't on a message
+    MissingMessage,
+
+    /// the message with the button doesn't have the formatted release in it
+    MissingContent,
+
+    /// you aren't allowed to post in this channel
+    NotAllowed { source: PermissionError },
+
+    /// the release is {length} characters long, which is more than a message can be, so copy it and post it in parts instead
+    TooLong { length: usize },
+}
+
+/// Posts the release from an ephemeral `new-release` response where everyone can see it,
+/// for when its "Post publicly" button is pressed
+#[tracing::instrument(skip(config), ret)]
+pub(super) fn post_publicly(
+    interaction: &Interaction,
+    config: &Config,
+) -> Result<InteractionResponse, PostPubliclyError> {
+    // the ephemeral response can be seen by anyone who ran the command, but posting it is like sending a message in the channel
+    require_permissions(
+        interaction,
+        Permissions::SEND_MESSAGES,
+        config.direct_post_role_id,
+    )
+    .context(NotAllowedSnafu)?;
+
+    let message = interaction.message.as_ref().context(MissingMessageSnafu)?;
+
+    let preview = message
+        .embeds
+        .iter()
+        .find(|embed| embed.title.as_deref() == Some(PREVIEW_EMBED_TITLE))
+        .context(MissingContentSnafu)?;
+    let description = preview
+        .description
+        .as_deref()
+        .context(MissingContentSnafu)?;
+
+    // the footer is shown as subtext, since that's the closest a message gets to an embed's footer
+    let content = match &preview.footer {
+        Some(footer) => format!("{description}\n-# {}", footer.text),
+        None => description.to_owned(),
+    };
+    // the preview is an embed description, which can be much longer than a message's content
+    let length = content.chars().count();
+    ensure!(length <= MESSAGE_CONTENT_LIMIT, TooLongSnafu { length });
+
+    // only the artists' and labels' roles are meant to be pinged, not anyone or anything else named in the release
+    let allowed_mentions = AllowedMentions {
+        parse: vec![MentionType::Roles],
+        ..AllowedMentions::default()
+    };
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .content(content)
+        .allowed_mentions(allowed_mentions)
+        .build();
+
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
 }
 
 #[derive(Debug, Snafu)]
@@ -865,20 +3344,172 @@ enum HandleError {
 
     /// couldn't get the release data
     ReleaseError { source: GetReleaseError },
+
+    /// the `channel` argument wasn't a channel like it's supposed to be, it was actually {actual:?}
+    ChannelNotChannel { actual: CommandOptionValue },
+
+    /// you aren't allowed to post in <#{channel_id}>
+    DirectPostNotAllowed {
+        channel_id: DiscordId<ChannelMarker>,
+        source: PermissionError,
+    },
+
+    /// the `artist` argument wasn't a string like it's supposed to be, it was actually {actual:?}
+    ArtistNotString { actual: CommandOptionValue },
+
+    /// the `preview-only` argument wasn't a boolean like it's supposed to be, it was actually {actual:?}
+    PreviewOnlyNotBoolean { actual: CommandOptionValue },
+
+    /// couldn't format the release into something worth posting
+    EmptyFormattedReleaseError { source: EmptyFormattedReleaseError },
+
+    /// couldn't post the release directly
+    DirectPostError { source: DirectPostError },
+}
+
+#[derive(Debug, Snafu)]
+enum DirectPostError {
+    /// couldn't look up the channel to post in
+    FetchChannelError { source: twilight_http::Error },
+
+    /// couldn't deserialize the channel to post in after looking it up
+    DeserializeChannelError {
+        source: twilight_http::response::DeserializeBodyError,
+    },
+
+    /// <#{channel_id}> isn't in this server, so I won't post there
+    ChannelInOtherGuild {
+        channel_id: DiscordId<ChannelMarker>,
+    },
+
+    /// I don't have permission to post in <#{channel_id}>
+    MissingPostPermission {
+        channel_id: DiscordId<ChannelMarker>,
+        source: twilight_http::Error,
+    },
+
+    /// couldn't post in <#{channel_id}>
+    PostError {
+        channel_id: DiscordId<ChannelMarker>,
+        source: twilight_http::Error,
+    },
+
+    /// the release (with the direct post prefix and suffix) is {length} characters long, which is more than a message can be, so preview it and post it in parts instead
+    #[snafu(context(name(DirectPostTooLongSnafu)))]
+    TooLong { length: usize },
+}
+
+fn is_forbidden(error: &twilight_http::Error) -> bool {
+    matches!(
+        error.kind(),
+        twilight_http::error::ErrorType::Response { status, .. } if status.get() == 403
+    )
+}
+
+/// Makes sure the person who ran the command could send a message in the channel they want the release posted in
+/// (or has the role configured for posting directly), so the bot isn't used to post where they can't.
+/// The permissions Discord sends with the interaction are only for the channel it was used in, but a resolved channel comes with theirs in it
+fn require_direct_post_permission(
+    interaction: &Interaction,
+    resolved: Option<&InteractionDataResolved>,
+    channel_id: DiscordId<ChannelMarker>,
+    config: &Config,
+) -> Result<(), PermissionError> {
+    let channel = resolved.and_then(|resolved| resolved.channels.get(&channel_id));
+    let send_permission = if channel.is_some_and(|channel| channel.kind.is_thread()) {
+        Permissions::SEND_MESSAGES_IN_THREADS
+    } else {
+        Permissions::SEND_MESSAGES
+    };
+    let channel_permissions =
+        channel.map_or_else(Permissions::empty, |channel| channel.permissions);
+
+    require_permissions_in(
+        interaction,
+        channel_permissions,
+        send_permission,
+        config.direct_post_role_id,
+    )
+}
+
+#[tracing::instrument(skip(discord_client, message))]
+async fn post_directly(
+    discord_client: &twilight_http::Client,
+    guild_id: DiscordId<GuildMarker>,
+    channel_id: DiscordId<ChannelMarker>,
+    message: &str,
+) -> Result<(), DirectPostError> {
+    // checked before anything is sent, since Discord would only turn it down with a generic error
+    let length = message.chars().count();
+    ensure!(
+        length <= MESSAGE_CONTENT_LIMIT,
+        DirectPostTooLongSnafu { length }
+    );
+
+    let channel = discord_client
+        .channel(channel_id)
+        .await
+        .context(FetchChannelSnafu)?
+        .model()
+        .await
+        .context(DeserializeChannelSnafu)?;
+
+    ensure!(
+        channel.guild_id == Some(guild_id),
+        ChannelInOtherGuildSnafu { channel_id }
+    );
+
+    // release data comes from pages anyone can edit, so it mustn't be able to ping everyone or random people
+    let allowed_mentions = AllowedMentions {
+        parse: vec![MentionType::Roles],
+        ..AllowedMentions::default()
+    };
+
+    match discord_client
+        .create_message(channel_id)
+        .content(message)
+        .allowed_mentions(Some(&allowed_mentions))
+        .await
+    {
+        Ok(_response) => Ok(()),
+        Err(error) if is_forbidden(&error) => Err(DirectPostError::MissingPostPermission {
+            channel_id,
+            source: error,
+        }),
+        Err(error) => Err(DirectPostError::PostError {
+            channel_id,
+            source: error,
+        }),
+    }
 }
 
-#[tracing::instrument(skip(discord_client, spotify_client), ret)]
+#[tracing::instrument(
+    skip(
+        discord_client,
+        spotify_client,
+        http_client,
+        fetch_permits,
+        roles_cache
+    ),
+    ret
+)]
 async fn handle_impl(
     State {
         discord_client,
         spotify_client,
+        http_client,
+        fetch_permits,
+        roles_cache,
+        config,
         ..
     }: State,
-    interaction: Interaction,
+    mut interaction: Interaction,
 ) -> Result<InteractionResponse, HandleError> {
     let guild_id = interaction.guild_id.context(NotUsedInGuildSnafu)?;
+    let deadline = response_deadline(&interaction);
 
-    let InteractionData::ApplicationCommand(command_data) = interaction.data.unwrap() else {
+    // the rest of the interaction is still needed for checking permissions
+    let InteractionData::ApplicationCommand(command_data) = interaction.data.take().unwrap() else {
         panic!(
             "this is a command handler so it should be impossible for the interaction data not to be for an application command invocation"
         );
@@ -900,41 +3531,3046 @@ async fn handle_impl(
         }
     };
     let url = IriRefBuf::new(url).context(UrlParseSnafu)?;
+    let url = strip_tracking_query_params(url);
 
-    let (roles_map, release) = tokio::try_join!(
-        get_roles_map(&discord_client, guild_id).context(RolesMapSnafu),
-        get_release(&spotify_client, url).context(ReleaseSnafu)
-    )?;
+    let target_channel_id = match options.remove(CHANNEL_NAME) {
+        None => None,
+        Some(CommandOptionValue::Channel(channel_id)) => Some(channel_id),
+        Some(other) => return Err(HandleError::ChannelNotChannel { actual: other }),
+    };
+    if let Some(target_channel_id) = target_channel_id {
+        require_direct_post_permission(
+            &interaction,
+            command_data.resolved.as_ref(),
+            target_channel_id,
+            &config,
+        )
+        .context(DirectPostNotAllowedSnafu {
+            channel_id: target_channel_id,
+        })?;
+    }
 
-    let message = format_release(release, roles_map);
-    let copyable = format!("```\n{message}\n```");
+    let artist_override = match options.remove(ARTIST_NAME) {
+        None => None,
+        Some(CommandOptionValue::String(artists_joined)) => Some(artists_joined),
+        Some(other) => return Err(HandleError::ArtistNotString { actual: other }),
+    };
 
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .content("Copy the `Content`, edit it to fix any mistakes, then post it.")
-        .embeds([
-            EmbedBuilder::new()
-                .color(COLOR_SUCCESS)
-                .title("Content")
-                .description(copyable)
-                .build(),
-            EmbedBuilder::new()
-                .title("Preview")
-                .description(message)
-                .build(),
-        ])
-        .flags(MessageFlags::EPHEMERAL)
-        .build();
+    let preview_only = match options.remove(PREVIEW_ONLY_NAME) {
+        None => false,
+        Some(CommandOptionValue::Boolean(preview_only)) => preview_only,
+        Some(other) => return Err(HandleError::PreviewOnlyNotBoolean { actual: other }),
+    };
 
-    Ok(InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    })
-}
+    let (roles_map, mut release) = tokio::try_join!(
+        get_roles_map(&discord_client, &roles_cache, guild_id).context(RolesMapSnafu),
+        get_release(
+            spotify_client.as_deref(),
+            &http_client,
+            &fetch_permits,
+            &config,
+            url,
+            deadline,
+        )
+        .context(ReleaseSnafu)
+    )?;
 
-#[tracing::instrument]
-pub async fn handle(state: State, interaction: Interaction) -> InteractionResponse {
-    match handle_impl(state, interaction).await {
-        Ok(interaction_response) => interaction_response,
-        Err(error) => error.into(),
+    if let Some(artist_override) = artist_override {
+        override_main_artists(&mut release, artist_override, &config.comma_artist_names);
+    }
+    assign_artist_roles(&mut release, &config.artist_roles, guild_id);
+
+    let mut helper_lines = Vec::new();
+    if config.show_helper_text && !preview_only {
+        helper_lines
+            .push("Copy the `Content`, edit it to fix any mistakes, then post it.".to_owned());
+    }
+    // the missing artist is only warned about separately when this doesn't already say so
+    let artists_undetermined = release.undetermined.contains(&ReleaseField::Artists);
+    // even people who know the drill need to be told which parts are missing
+    if !release.undetermined.is_empty() {
+        let undetermined = release.undetermined.iter().join(", ");
+        helper_lines.push(format!(
+            "Couldn't determine the {undetermined} of this release, so fill those in yourself."
+        ));
+    }
+
+    let thumbnail = choose_image(&release.images, config.preferred_image_size)
+        .and_then(|image| ImageSource::url(image.url.as_str()).ok());
+
+    if let Some(target_channel_id) = target_channel_id {
+        // a message posted directly has no footer to put the label in
+        let formatted_release =
+            format_release(release, &roles_map, &config, LabelPlacement::Inline);
+        formatted_release
+            .validate(&config)
+            .context(EmptyFormattedReleaseSnafu)?;
+        let FormattedRelease {
+            message,
+            artist_missing,
+            ..
+        } = formatted_release;
+        let message = [
+            config.direct_post_prefix.as_str(),
+            &message,
+            config.direct_post_suffix.as_str(),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .join("\n");
+
+        post_directly(&discord_client, guild_id, target_channel_id, &message)
+            .await
+            .context(DirectPostSnafu)?;
+
+        let mut content = format!("Posted the release in <#{target_channel_id}>.");
+        if artist_missing && !artists_undetermined {
+            content = format!("{content}\n{MISSING_ARTIST_WARNING}");
+        }
+
+        let interaction_response_data = InteractionResponseDataBuilder::new()
+            .content(content)
+            .flags(MessageFlags::EPHEMERAL)
+            .build();
+
+        return Ok(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(interaction_response_data),
+        });
+    }
+
+    let label_placement = if config.label_in_footer {
+        LabelPlacement::Separate
+    } else {
+        LabelPlacement::Inline
+    };
+    let formatted_release = format_release(release, &roles_map, &config, label_placement);
+    formatted_release
+        .validate(&config)
+        .context(EmptyFormattedReleaseSnafu)?;
+    let FormattedRelease {
+        message,
+        label,
+        upc,
+        artist_missing,
+        ..
+    } = formatted_release;
+    if artist_missing && !artists_undetermined {
+        helper_lines.push(MISSING_ARTIST_WARNING.to_owned());
+    }
+
+    let copyable = format!("```\n{message}\n```");
+
+    let mut preview = EmbedBuilder::new()
+        .title(PREVIEW_EMBED_TITLE)
+        .description(message);
+    if let Some(thumbnail) = thumbnail {
+        preview = preview.thumbnail(thumbnail);
+    }
+    let footer = [label, upc.map(|upc| format!("UPC {upc}"))]
+        .into_iter()
+        .flatten()
+        .join(" · ");
+    if !footer.is_empty() {
+        preview = preview.footer(EmbedFooterBuilder::new(footer).build());
+    }
+
+    let mut embeds = Vec::new();
+    if !preview_only {
+        embeds.push(
+            EmbedBuilder::new()
+                .color(COLOR_SUCCESS)
+                .title("Content")
+                .description(copyable)
+                .build(),
+        );
+    }
+    embeds.push(preview.build());
+
+    let mut interaction_response_data = InteractionResponseDataBuilder::new();
+    if !helper_lines.is_empty() {
+        interaction_response_data = interaction_response_data.content(helper_lines.join("\n"));
+    }
+    let interaction_response_data = interaction_response_data
+        .embeds(embeds)
+        .components([single_button_row(
+            ComponentAction::PostPublicly,
+            "Post publicly",
+        )])
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
+}
+
+#[tracing::instrument]
+pub async fn handle(state: State, interaction: Interaction) -> CommandResponse {
+    let config = state.config.clone();
+    let usage_stats = state.usage_stats.clone();
+    let guild_id = interaction.guild_id;
+
+    match handle_impl(state, interaction).await {
+        Ok(interaction_response) => {
+            // only releases that were actually formatted are counted, not attempts that errored
+            let recorded = match (usage_stats, guild_id) {
+                (Some(usage_stats), Some(guild_id)) => usage_stats.record_release(guild_id).await,
+                _ => Ok(()),
+            };
+            if let Err(error) = recorded {
+                tracing::warn!(error = %Report::from_error(error), "couldn't count the release in the usage stats");
+            }
+
+            CommandResponse::Succeeded(interaction_response)
+        }
+        Err(error) => CommandResponse::Failed(error_response(error, &config)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ReleaseTypeIndicators;
+    use crate::command::tests::{member_interaction, state};
+    use proptest::{
+        prelude::{Just, Strategy},
+        prop_assert, prop_assert_eq, prop_oneof, proptest,
+    };
+
+    /// The contents of a file saved in `tests/fixtures`
+    fn fixture(name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    /// Serves a page saved in `tests/fixtures` from a local server,
+    /// with a client that sends every request for `host` there instead of wherever `host` really is
+    async fn replay_server(fixture: &str, host: &str) -> (reqwest::Client, u16) {
+        let page = self::fixture(fixture);
+
+        let app = axum::Router::new().fallback(move || async move { axum::response::Html(page) });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let http_client = reqwest::Client::builder()
+            .resolve(host, std::net::SocketAddr::from(([127, 0, 0, 1], port)))
+            .build()
+            .unwrap();
+
+        (http_client, port)
+    }
+
+    /// Gets the release from a saved page the way `new-release` would from the live one
+    async fn replay_release(fixture: &str, url: &str) -> Result<Release, GetReleaseError> {
+        let url = IriRefBuf::new(url.to_owned()).unwrap();
+        let host = url.authority().unwrap().host().as_str().to_owned();
+        let (http_client, port) = replay_server(fixture, &host).await;
+
+        // the port isn't part of what's resolved, so the URL has to point at the local server's,
+        // which only speaks plain HTTP
+        let local_url = url.as_str().replacen(
+            &format!("https://{host}"),
+            &format!("http://{host}:{port}"),
+            1,
+        );
+        get_release(
+            None,
+            &http_client,
+            &Semaphore::new(1),
+            &Config::default(),
+            IriRefBuf::new(local_url).unwrap(),
+            test_deadline(),
+        )
+        .await
+    }
+
+    /// Replays the release and formats it on [`replay_day`]
+    async fn replay(fixture: &str, url: &str) -> String {
+        let release = replay_release(fixture, url).await.unwrap();
+
+        format(release)
+    }
+
+    #[tokio::test]
+    async fn replays_a_bandcamp_album() {
+        assert_eq!(
+            replay(
+                "bandcamp-album.html",
+                "https://exampleband.bandcamp.com/album/night-drive",
+            )
+            .await,
+            "**The Example Band** - [Night Drive](<https://exampleband.bandcamp.com/album/night-drive>) (EP, 4 tracks) [5/10]"
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_a_bandcamp_single_with_a_feature() {
+        assert_eq!(
+            replay(
+                "bandcamp-track.html",
+                "https://exampleband.bandcamp.com/track/glass",
+            )
+            .await,
+            "**The Example Band** (feat. **Guest Singer**) - [Glass](<https://exampleband.bandcamp.com/track/glass>) [2/2]"
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_a_release_with_no_tracks() {
+        let result = replay_release(
+            "bandcamp-no-tracks.html",
+            "https://exampleband.bandcamp.com/album/coming-soon",
+        )
+        .await;
+
+        assert!(matches!(result, Err(GetReleaseError::EmptyTracklist)));
+    }
+
+    #[tokio::test]
+    async fn replays_an_apple_music_album() {
+        assert_eq!(
+            replay(
+                "apple-music-album.html",
+                "https://music.apple.com/us/album/low-tide/1700000000",
+            )
+            .await,
+            "**Harbor** & **The Lanterns** - [Low Tide](<https://music.apple.com/us/album/low-tide/1700000000>) (LP, 7 tracks) [9/15, LANT-007]"
+        );
+    }
+
+    /// A Bandcamp-like album with only what's needed to be a release, for tests to change what they're about
+    fn ld_json_album() -> serde_json::Value {
+        serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": "MusicAlbum",
+            "@id": "https://harbor.bandcamp.com/album/low-tide",
+            "name": "Low Tide",
+            "albumReleaseType": "AlbumRelease",
+            "datePublished": "31 May 2024 00:00:00 GMT",
+            "byArtist": { "@type": "MusicGroup", "name": "Harbor" },
+            "track": {
+                "@type": "ItemList",
+                "numberOfItems": 2,
+                "itemListElement": [
+                    {
+                        "@type": "ListItem",
+                        "position": 1,
+                        "item": { "@type": "MusicRecording", "name": "Undertow" },
+                    },
+                    {
+                        "@type": "ListItem",
+                        "position": 2,
+                        "item": { "@type": "MusicRecording", "name": "Low Tide" },
+                    },
+                ],
+            },
+            "albumRelease": [{
+                "@type": "MusicRelease",
+                "@id": "https://harbor.bandcamp.com/album/low-tide",
+                "name": "Low Tide",
+                "musicReleaseFormat": "DigitalFormat",
+            }],
+        })
+    }
+
+    /// Gets the release from a page with each of `ld_json` in its own script, the way it would be from a fetched one
+    fn release_from_ld_json(
+        ld_json: &[serde_json::Value],
+    ) -> Result<Release, GetReleaseFromLdJsonError> {
+        let scripts = ld_json
+            .iter()
+            .map(|ld_json| format!(r#"<script type="application/ld+json">{ld_json}</script>"#))
+            .join("");
+        let document =
+            scraper::Html::parse_document(&format!("<html><head>{scripts}</head></html>"));
+
+        get_release_from_ld_json(&document, TrackCountThresholds::default(), &[])
+    }
+
+    #[test]
+    fn broadcasts_are_not_releases() {
+        let mut album = ld_json_album();
+        album["albumReleaseType"] = "BroadcastRelease".into();
+
+        assert!(matches!(
+            release_from_ld_json(&[album]),
+            Err(GetReleaseFromLdJsonError::BroadcastRelease)
+        ));
+    }
+
+    #[test]
+    fn every_spotify_album_type_has_a_release_type() {
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Album,
+                None,
+                2,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::LP
+        );
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Compilation,
+                None,
+                2,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::Compilation
+        );
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Single,
+                None,
+                2,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::Single
+        );
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Single,
+                None,
+                4,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::EP
+        );
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::AppearsOn,
+                None,
+                2,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::Single
+        );
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::AppearsOn,
+                None,
+                4,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::EP
+        );
+    }
+
+    #[test]
+    fn only_tracks_credited_on_their_own_add_artists() {
+        let mut album = ld_json_album();
+        album["track"]["itemListElement"] = serde_json::json!([
+            {
+                "@type": "ListItem",
+                "position": 1,
+                "item": { "@type": "MusicRecording", "name": "Undertow" },
+            },
+            {
+                "@type": "ListItem",
+                "position": 2,
+                "item": {
+                    "@type": "MusicRecording",
+                    "name": "Breakwater",
+                    "byArtist": { "@type": "MusicGroup", "name": "Harbor & Guest Singer" },
+                },
+            },
+            {
+                "@type": "ListItem",
+                "position": 3,
+                "item": { "@type": "MusicRecording", "name": "Low Tide" },
+            },
+        ]);
+
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(
+            Vec::from_iter(release.tracks.iter().map(|track| track.artists_inherited)),
+            [true, false, true]
+        );
+
+        assert_eq!(
+            format_release_at(
+                release,
+                &BTreeMap::new(),
+                &Config::default(),
+                LabelPlacement::Inline,
+                replay_day()
+            )
+            .message,
+            "**Harbor** - [Low Tide](<https://harbor.bandcamp.com/album/low-tide>) (EP, 3 tracks) [5/31]\nwith **Guest Singer**"
+        );
+    }
+
+    #[test]
+    fn a_spotify_link_found_by_cross_searching_follows_the_title() {
+        let mut release = release_from_ld_json(&[ld_json_album()]).unwrap();
+        release.spotify_url =
+            Some(IriRefBuf::new("https://open.spotify.com/album/abc".to_owned()).unwrap());
+
+        assert_eq!(
+            format_release_at(
+                release,
+                &BTreeMap::new(),
+                &Config::default(),
+                LabelPlacement::Inline,
+                replay_day()
+            )
+            .message,
+            "**Harbor** - [Low Tide](<https://harbor.bandcamp.com/album/low-tide>) ([Spotify](<https://open.spotify.com/album/abc>)) [5/31]"
+        );
+    }
+
+    #[test]
+    fn cross_search_similarity_ignores_case() {
+        assert_eq!(similarity("Low Tide", "low tide"), 1.0);
+        assert!(similarity("Low Tide", "High Tide") < 0.9);
+    }
+
+    fn artist(name: &str) -> Artist {
+        Artist {
+            id: None,
+            name: name.to_owned(),
+            role_id: None,
+        }
+    }
+
+    fn track(position: u32, title: &str, artists: Vec<Artist>) -> Track {
+        Track {
+            position: Some(position),
+            disc_number: None,
+            title: Some(title.to_owned()),
+            artists,
+            artists_inherited: false,
+        }
+    }
+
+    /// A release by `artists` out the day before [`replay_day`], with `n_tracks` tracks credited to the same artists
+    fn release(kind: ReleaseType, title: &str, artists: &[&str], n_tracks: u32) -> Release {
+        let main_artists = Vec::from_iter(artists.iter().copied().map(artist));
+
+        Release {
+            url: IriRefBuf::new("https://example.bandcamp.com/album/example".to_owned()).unwrap(),
+            kind,
+            title: title.to_owned(),
+            date: Some(Date::from_calendar_date(2024, time::Month::May, 31).unwrap()),
+            date_precision: DatePrecision::Day,
+            tracks: Vec::from_iter((1..=n_tracks).map(|position| Track {
+                artists_inherited: true,
+                ..track(position, &format!("Track {position}"), main_artists.clone())
+            })),
+            main_artists,
+            record_labels: Vec::new(),
+            catalog_number: None,
+            upc: None,
+            images: Vec::new(),
+            undetermined: BTreeSet::new(),
+            spotify_url: None,
+            languages: Vec::new(),
+            explicit: false,
+            genres: Vec::new(),
+        }
+    }
+
+    /// Formats `release` the way `new-release` would on [`replay_day`], with no roles in the server
+    fn format(release: Release) -> String {
+        format_release_at(
+            release,
+            &BTreeMap::new(),
+            &Config::default(),
+            LabelPlacement::Inline,
+            replay_day(),
+        )
+        .message
+    }
+
+    /// A deadline far enough off that nothing in a test waits past it
+    fn test_deadline() -> tokio::time::Instant {
+        tokio::time::Instant::now() + std::time::Duration::from_secs(60)
+    }
+
+    /// The day replayed releases are formatted on, so how long ago they came out doesn't change from run to run
+    fn replay_day() -> OffsetDateTime {
+        Date::from_calendar_date(2024, time::Month::June, 1)
+            .unwrap()
+            .midnight()
+            .assume_utc()
+    }
+
+    fn artist_with_id(name: &str, id: &str) -> Artist {
+        Artist {
+            id: Some(id.to_owned()),
+            ..artist(name)
+        }
+    }
+
+    #[test]
+    fn artists_are_the_same_by_id_when_they_have_one() {
+        assert_eq!(
+            artist_with_id("Harbor", "0aaaaaaaaaaaaaaaaaaaaa").key(),
+            artist_with_id("HARBOR", "0aaaaaaaaaaaaaaaaaaaaa").key()
+        );
+        assert_ne!(
+            artist_with_id("Harbor", "0aaaaaaaaaaaaaaaaaaaaa").key(),
+            artist_with_id("Harbor", "0bbbbbbbbbbbbbbbbbbbbb").key()
+        );
+        assert_eq!(artist("Harbor").key(), artist("Harbor").key());
+        assert_ne!(
+            artist("Harbor").key(),
+            artist_with_id("Harbor", "0aaaaaaaaaaaaaaaaaaaaa").key()
+        );
+    }
+
+    #[test]
+    fn namesakes_with_different_ids_are_both_credited() {
+        let mut release = release(ReleaseType::LP, "Low Tide", &[], 0);
+        release.main_artists = vec![artist_with_id("Harbor", "0aaaaaaaaaaaaaaaaaaaaa")];
+        release.tracks = vec![
+            track(
+                1,
+                "Undertow",
+                vec![artist_with_id("Harbor", "0aaaaaaaaaaaaaaaaaaaaa")],
+            ),
+            track(
+                2,
+                "Low Tide",
+                vec![artist_with_id("Harbor", "0bbbbbbbbbbbbbbbbbbbbb")],
+            ),
+        ];
+
+        assert_eq!(
+            format(release),
+            "**Harbor** - [Low Tide](<https://example.bandcamp.com/album/example>) (LP, 2 tracks) [5/31]\nwith **Harbor**"
+        );
+    }
+
+    fn resolved_channel(kind: ChannelType, permissions: Permissions) -> InteractionDataResolved {
+        serde_json::from_value(serde_json::json!({
+            "channels": {
+                "10": {
+                    "id": "10",
+                    "type": u8::from(kind),
+                    "name": "releases",
+                    "permissions": permissions.bits().to_string(),
+                },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn direct_post_checks_permissions_in_target_channel() {
+        let config = Config::default();
+        let channel_id = DiscordId::new(10);
+        // able to send messages where the command was run, but not in the target channel
+        let interaction = member_interaction(Permissions::SEND_MESSAGES, &[]);
+
+        let resolved = resolved_channel(ChannelType::GuildText, Permissions::VIEW_CHANNEL);
+        assert!(matches!(
+            require_direct_post_permission(&interaction, Some(&resolved), channel_id, &config),
+            Err(PermissionError::InsufficientPermissions { .. })
+        ));
+
+        let resolved = resolved_channel(
+            ChannelType::GuildText,
+            Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+        );
+        assert!(
+            require_direct_post_permission(&interaction, Some(&resolved), channel_id, &config)
+                .is_ok()
+        );
+
+        assert!(require_direct_post_permission(&interaction, None, channel_id, &config).is_err());
+    }
+
+    #[test]
+    fn direct_post_to_thread_needs_thread_permission() {
+        let config = Config::default();
+        let channel_id = DiscordId::new(10);
+        let interaction = member_interaction(Permissions::empty(), &[]);
+
+        let resolved = resolved_channel(ChannelType::PublicThread, Permissions::SEND_MESSAGES);
+        assert!(
+            require_direct_post_permission(&interaction, Some(&resolved), channel_id, &config)
+                .is_err()
+        );
+
+        let resolved = resolved_channel(
+            ChannelType::PublicThread,
+            Permissions::SEND_MESSAGES_IN_THREADS,
+        );
+        assert!(
+            require_direct_post_permission(&interaction, Some(&resolved), channel_id, &config)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn direct_post_role_overrides_channel_permissions() {
+        let config = Config {
+            direct_post_role_id: Some(DiscordId::new(20)),
+            ..Config::default()
+        };
+        let channel_id = DiscordId::new(10);
+        let resolved = resolved_channel(ChannelType::GuildText, Permissions::empty());
+
+        let interaction = member_interaction(Permissions::empty(), &[20]);
+        assert!(
+            require_direct_post_permission(&interaction, Some(&resolved), channel_id, &config)
+                .is_ok()
+        );
+
+        // without the channel resolved there's nothing to go by but the role
+        let interaction = member_interaction(Permissions::SEND_MESSAGES, &[]);
+        assert!(require_direct_post_permission(&interaction, None, channel_id, &config).is_err());
+    }
+
+    #[test]
+    fn spotify_placeholder_dates() {
+        for placeholder in ["", "  ", "0000", "0000-00-00", "0"] {
+            assert!(is_placeholder_date(placeholder), "{placeholder:?}");
+        }
+        for date in ["2024", "2024-05", "2024-05-31"] {
+            assert!(!is_placeholder_date(date), "{date:?}");
+        }
+    }
+
+    #[test]
+    fn dates_are_only_as_precise_as_theyre_known() {
+        assert_eq!(
+            assemble_parsed_date(parse_date("2024"), DatePrecision::Year).unwrap(),
+            Date::from_calendar_date(2024, time::Month::January, 1).unwrap()
+        );
+        assert_eq!(
+            assemble_parsed_date(parse_date("2024-05"), DatePrecision::Month).unwrap(),
+            Date::from_calendar_date(2024, time::Month::May, 1).unwrap()
+        );
+        assert!(assemble_parsed_date(parse_date("2024"), DatePrecision::Day).is_err());
+
+        let mut release = release(ReleaseType::Single, "Glass", &["Harbor"], 1);
+        release.date_precision = DatePrecision::Year;
+        assert_eq!(
+            format(release.clone()),
+            "**Harbor** - [Glass](<https://example.bandcamp.com/album/example>) [2024]"
+        );
+        release.date_precision = DatePrecision::Month;
+        assert_eq!(
+            format(release),
+            "**Harbor** - [Glass](<https://example.bandcamp.com/album/example>) [2024/5]"
+        );
+    }
+
+    #[test]
+    fn credited_names_are_each_listed_once() {
+        let mut release = release(ReleaseType::LP, "Low Tide", &["Harbor"], 2);
+        release.tracks.push(track(
+            3,
+            "Breakwater",
+            vec![artist("Harbor"), artist("Guest Singer")],
+        ));
+        release.record_labels = vec!["Lighthouse Recordings".to_owned()];
+
+        assert_eq!(
+            release.credited_names(),
+            ["Harbor", "Guest Singer", "Lighthouse Recordings"]
+        );
+    }
+
+    fn image(url: &str, size: Option<u32>) -> ReleaseImage {
+        ReleaseImage {
+            url: IriRefBuf::new(url.to_owned()).unwrap(),
+            width: size,
+            height: size,
+        }
+    }
+
+    #[test]
+    fn choose_image_picks_the_largest() {
+        let images = [
+            image("https://example.com/300", Some(300)),
+            image("https://example.com/640", Some(640)),
+            image("https://example.com/64", Some(64)),
+        ];
+
+        let chosen = choose_image(&images, ImageSize::Largest).unwrap();
+        assert_eq!(chosen.url.as_str(), "https://example.com/640");
+    }
+
+    #[test]
+    fn choose_image_picks_the_closest_to_a_preferred_size() {
+        let images = [
+            image("https://example.com/640", Some(640)),
+            image("https://example.com/300", Some(300)),
+            image("https://example.com/64", Some(64)),
+        ];
+
+        let chosen = choose_image(&images, ImageSize::Closest { pixels: 256 }).unwrap();
+        assert_eq!(chosen.url.as_str(), "https://example.com/300");
+    }
+
+    #[test]
+    fn choose_image_goes_by_the_longer_side() {
+        let images = [
+            ReleaseImage {
+                width: Some(100),
+                height: Some(900),
+                ..image("https://example.com/tall", None)
+            },
+            image("https://example.com/500", Some(500)),
+        ];
+
+        let chosen = choose_image(&images, ImageSize::Largest).unwrap();
+        assert_eq!(chosen.url.as_str(), "https://example.com/tall");
+    }
+
+    #[test]
+    fn choose_image_falls_back_to_the_first_without_dimensions() {
+        let images = [
+            image("https://example.com/first", None),
+            image("https://example.com/second", None),
+        ];
+
+        let chosen = choose_image(&images, ImageSize::Largest).unwrap();
+        assert_eq!(chosen.url.as_str(), "https://example.com/first");
+        assert!(choose_image(&[], ImageSize::Largest).is_none());
+    }
+
+    #[tokio::test]
+    async fn spotify_links_need_spotify() {
+        let url =
+            IriRefBuf::new("https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy".to_owned())
+                .unwrap();
+
+        assert!(matches!(
+            get_release(
+                None,
+                &reqwest::Client::new(),
+                &Semaphore::new(1),
+                &Config::default(),
+                url,
+                test_deadline(),
+            )
+            .await,
+            Err(GetReleaseError::SpotifyUnavailable)
+        ));
+    }
+
+    #[test]
+    fn additional_artists_past_the_limit_are_summarized() {
+        let mut release = release(ReleaseType::LP, "Low Tide", &["Harbor"], 0);
+        release.tracks = Vec::from_iter(
+            ["Guest One", "Guest Two", "Guest Three"]
+                .into_iter()
+                .zip(1..)
+                .map(|(guest, position)| {
+                    track(position, "Low Tide", vec![artist("Harbor"), artist(guest)])
+                }),
+        );
+
+        let second_line = |max_additional_artists| {
+            let config = Config {
+                max_additional_artists,
+                ..Config::default()
+            };
+            let formatted = format_release_at(
+                release.clone(),
+                &BTreeMap::new(),
+                &config,
+                LabelPlacement::Inline,
+                replay_day(),
+            )
+            .message;
+            formatted.lines().nth(1).map(str::to_owned)
+        };
+
+        assert_eq!(
+            second_line(None).as_deref(),
+            Some("with **Guest One**, **Guest Two**, **Guest Three**")
+        );
+        assert_eq!(
+            second_line(Some(1)).as_deref(),
+            Some("with **Guest One** and 2 more")
+        );
+        assert_eq!(second_line(Some(0)).as_deref(), Some("with 3 more"));
+        assert_eq!(
+            second_line(Some(5)),
+            second_line(None),
+            "a limit above the number of artists changes nothing"
+        );
+    }
+
+    #[test]
+    fn only_languages_other_than_the_primary_one_are_shown() {
+        let in_languages = |languages: &[&str], show_language| {
+            let mut release = release(ReleaseType::Single, "Low Tide", &["Harbor"], 1);
+            release.languages = Vec::from_iter(languages.iter().copied().map(str::to_owned));
+            let config = Config {
+                show_language,
+                ..Config::default()
+            };
+            format_release_at(
+                release,
+                &BTreeMap::new(),
+                &config,
+                LabelPlacement::Inline,
+                replay_day(),
+            )
+            .message
+        };
+
+        assert!(in_languages(&["ja"], true).ends_with("[5/31, in Japanese]"));
+        assert!(in_languages(&["ja", "Japanese", "en-US"], true).ends_with("[5/31, in Japanese]"));
+        assert!(in_languages(&["Klingon"], true).ends_with("[5/31, in Klingon]"));
+        assert!(in_languages(&["en-GB"], true).ends_with("[5/31]"));
+        assert!(in_languages(&["ja"], false).ends_with("[5/31]"));
+    }
+
+    #[test]
+    fn login_walls_are_told_apart_from_pages_without_a_release() {
+        let requested = IriRefBuf::new("https://example.com/album/low-tide".to_owned()).unwrap();
+        let landed = |url: &str| reqwest::Url::parse(url).unwrap();
+        let stayed = landed(requested.as_str());
+
+        assert!(is_login_wall(
+            "<html></html>",
+            requested.as_iri_ref(),
+            &landed("https://example.com/account/Sign-In?next=%2Falbum%2Flow-tide"),
+        ));
+        assert!(is_login_wall(
+            r#"<form><input name="user"><input type="password" name="pass"></form>"#,
+            requested.as_iri_ref(),
+            &stayed,
+        ));
+
+        let generic_page = r#"<meta property="og:type" content="website">"#;
+        assert!(is_login_wall(
+            generic_page,
+            requested.as_iri_ref(),
+            &landed("https://example.com/"),
+        ));
+        assert!(
+            !is_login_wall(generic_page, requested.as_iri_ref(), &stayed),
+            "a generic page that wasn't redirected to is just missing a release"
+        );
+        assert!(!is_login_wall(
+            "<html></html>",
+            requested.as_iri_ref(),
+            &landed("https://example.com/albums/low-tide"),
+        ));
+    }
+
+    #[test]
+    fn the_album_group_decides_unless_its_appears_on() {
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Single,
+                Some(AlbumType::Album),
+                4,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::LP
+        );
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Album,
+                Some(AlbumType::Single),
+                4,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::EP
+        );
+        // appearing on it says nothing about the album itself
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Album,
+                Some(AlbumType::AppearsOn),
+                4,
+                TrackCountThresholds::default(),
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::LP
+        );
+    }
+
+    #[test]
+    fn spotify_album_groups() {
+        assert_eq!(
+            parse_spotify_album_group("appears_on"),
+            Some(AlbumType::AppearsOn)
+        );
+        assert_eq!(
+            parse_spotify_album_group("compilation"),
+            Some(AlbumType::Compilation)
+        );
+        assert_eq!(parse_spotify_album_group("podcast"), None);
+    }
+
+    #[test]
+    fn track_count_thresholds_decide_the_type() {
+        let default = TrackCountThresholds::default();
+        assert_eq!(
+            release_type_from_track_count(2, default),
+            ReleaseType::Single
+        );
+        assert_eq!(release_type_from_track_count(3, default), ReleaseType::EP);
+        assert_eq!(release_type_from_track_count(6, default), ReleaseType::EP);
+        assert_eq!(release_type_from_track_count(7, default), ReleaseType::LP);
+
+        let strict = TrackCountThresholds {
+            single_max_tracks: 1,
+            ep_max_tracks: 4,
+        };
+        assert_eq!(
+            release_type_from_track_count(1, strict),
+            ReleaseType::Single
+        );
+        assert_eq!(release_type_from_track_count(2, strict), ReleaseType::EP);
+        assert_eq!(release_type_from_track_count(5, strict), ReleaseType::LP);
+    }
+
+    #[test]
+    fn track_count_thresholds_apply_to_spotify_singles() {
+        let strict = TrackCountThresholds {
+            single_max_tracks: 1,
+            ep_max_tracks: 4,
+        };
+
+        assert_eq!(
+            release_type_from_spotify(
+                AlbumType::Single,
+                None,
+                2,
+                strict,
+                SpotifyAlbumTypeMapping::default()
+            ),
+            ReleaseType::EP
+        );
+    }
+
+    #[test]
+    fn bandcamp_pages_are_told_apart_by_their_path() {
+        let page = |url: &str| parse_bandcamp_page(IriRef::new(url).unwrap());
+
+        assert_eq!(
+            page("https://harbor.bandcamp.com/album/low-tide"),
+            Some(BandcampPage::Album)
+        );
+        assert_eq!(
+            page("https://HARBOR.Bandcamp.com/track/undertow"),
+            Some(BandcampPage::Track)
+        );
+        assert_eq!(page("https://harbor.bandcamp.com/music"), None);
+        assert_eq!(page("https://music.harbor.example/album/low-tide"), None);
+        assert_eq!(page("https://notbandcamp.com/album/low-tide"), None);
+    }
+
+    #[test]
+    fn bandcamp_track_pages_are_singles_however_many_tracks() {
+        let release_type = |url: &str| {
+            let mut album = ld_json_album();
+            album["@id"] = url.into();
+            album["track"]["itemListElement"] =
+                serde_json::Value::from_iter((1..=4).map(|position| {
+                    serde_json::json!({
+                        "@type": "ListItem",
+                        "position": position,
+                        "item": { "@type": "MusicRecording", "name": format!("Part {position}") },
+                    })
+                }));
+
+            release_from_ld_json(&[album]).unwrap().kind
+        };
+
+        assert_eq!(
+            release_type("https://harbor.bandcamp.com/track/low-tide"),
+            ReleaseType::Single
+        );
+        assert_eq!(
+            release_type("https://harbor.bandcamp.com/album/low-tide"),
+            ReleaseType::EP
+        );
+        // custom domains can't be recognized, so they're counted like albums
+        assert_eq!(
+            release_type("https://music.harbor.example/track/low-tide"),
+            ReleaseType::EP
+        );
+    }
+
+    #[test]
+    fn unparseable_ld_json_is_the_error_source() {
+        let error = release_from_ld_json(&[
+            serde_json::json!({ "@type": "Person", "name": "Harbor" }),
+            serde_json::json!({ "@type": "Organization", "name": "Tidewater Records" }),
+        ])
+        .unwrap_err();
+
+        let GetReleaseFromLdJsonError::UnsupportedSemanticDataInPage { other_errors, .. } = &error
+        else {
+            panic!("expected the JSON-LD to be unsupported, got {error:?}");
+        };
+        assert_eq!(other_errors.len(), 1);
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn unparseable_ld_json_is_ignored_next_to_a_release() {
+        let release = release_from_ld_json(&[
+            serde_json::json!({ "@type": "Person", "name": "Harbor" }),
+            ld_json_album(),
+        ])
+        .unwrap();
+
+        assert_eq!(release.title, "Low Tide");
+    }
+
+    #[test]
+    fn edition_qualifiers_are_stripped_from_the_end() {
+        assert_eq!(
+            strip_edition_qualifiers("Low Tide (Deluxe Edition)"),
+            "Low Tide"
+        );
+        assert_eq!(
+            strip_edition_qualifiers("Low Tide - Remastered 2011"),
+            "Low Tide"
+        );
+        assert_eq!(
+            strip_edition_qualifiers("Low Tide (Deluxe) [Explicit]"),
+            "Low Tide"
+        );
+        assert_eq!(
+            strip_edition_qualifiers("Low Tide (Harbor Remix)"),
+            "Low Tide (Harbor Remix)"
+        );
+        assert_eq!(
+            strip_edition_qualifiers("Live - At the Lighthouse"),
+            "Live - At the Lighthouse"
+        );
+        assert_eq!(
+            strip_edition_qualifiers("(Deluxe Edition)"),
+            "(Deluxe Edition)",
+            "the whole title is never stripped away"
+        );
+    }
+
+    #[test]
+    fn explicit_releases_are_marked_when_configured() {
+        let mut release = release(ReleaseType::Single, "Low Tide", &["Harbor"], 1);
+        release.explicit = true;
+
+        let marked = Config {
+            show_explicit_marker: true,
+            ..Config::default()
+        };
+        assert!(
+            format_release_at(
+                release.clone(),
+                &BTreeMap::new(),
+                &marked,
+                LabelPlacement::Inline,
+                replay_day()
+            )
+            .message
+            .contains("[Low Tide](<https://example.bandcamp.com/album/example>) 🅴")
+        );
+        assert!(!format(release).contains('🅴'));
+    }
+
+    fn parse_spotify(url: &str) -> Result<SpotifyResource<'static>, SpotifyResourceFromUrlError> {
+        parse_spotify_resource(&IriRefBuf::new(url.to_owned()).unwrap())
+    }
+
+    const SPOTIFY_ID: &str = "4aawyAB9vmqN3uQ7FjRGTy";
+
+    #[test]
+    fn spotify_resource_kinds() {
+        assert!(matches!(
+            parse_spotify(&format!("https://open.spotify.com/album/{SPOTIFY_ID}")),
+            Ok(SpotifyResource::Album { id }) if id.id() == SPOTIFY_ID
+        ));
+        assert!(matches!(
+            parse_spotify(&format!("https://open.spotify.com/track/{SPOTIFY_ID}")),
+            Ok(SpotifyResource::Track { id }) if id.id() == SPOTIFY_ID
+        ));
+        assert!(matches!(
+            parse_spotify(&format!("https://open.spotify.com/playlist/{SPOTIFY_ID}")),
+            Ok(SpotifyResource::Playlist { id }) if id.id() == SPOTIFY_ID
+        ));
+    }
+
+    #[test]
+    fn spotify_uris() {
+        assert!(matches!(
+            parse_spotify(&format!("spotify:album:{SPOTIFY_ID}")),
+            Ok(SpotifyResource::Album { id }) if id.id() == SPOTIFY_ID
+        ));
+        assert!(matches!(
+            parse_spotify(&format!("spotify:track:{SPOTIFY_ID}")),
+            Ok(SpotifyResource::Track { id }) if id.id() == SPOTIFY_ID
+        ));
+        assert!(matches!(
+            parse_spotify(&format!("spotify:playlist:{SPOTIFY_ID}")),
+            Ok(SpotifyResource::Playlist { id }) if id.id() == SPOTIFY_ID
+        ));
+    }
+
+    #[test]
+    fn spotify_url_variations() {
+        for url in [
+            format!("https://open.spotify.com/album/{SPOTIFY_ID}/"),
+            format!("https://open.spotify.com/intl-es/album/{SPOTIFY_ID}"),
+            format!("https://open.spotify.com/album/{SPOTIFY_ID}?si=abc123"),
+            format!("https://OPEN.SPOTIFY.COM//album//{SPOTIFY_ID}"),
+        ] {
+            assert!(
+                matches!(
+                    parse_spotify(&url),
+                    Ok(SpotifyResource::Album { id }) if id.id() == SPOTIFY_ID
+                ),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn spotify_url_errors() {
+        assert!(matches!(
+            parse_spotify(&format!("https://example.com/album/{SPOTIFY_ID}")),
+            Err(SpotifyResourceFromUrlError::NotSpotify)
+        ));
+        assert!(matches!(
+            parse_spotify("https://open.spotify.com/"),
+            Err(SpotifyResourceFromUrlError::MissingResourceType)
+        ));
+        assert!(matches!(
+            parse_spotify("https://open.spotify.com/intl-es/album/"),
+            Err(SpotifyResourceFromUrlError::MissingResourceId)
+        ));
+        assert!(matches!(
+            parse_spotify(&format!("https://open.spotify.com/artist/{SPOTIFY_ID}")),
+            Err(SpotifyResourceFromUrlError::UnrecognizedResourceType { kind }) if kind.as_str() == "artist"
+        ));
+        assert!(matches!(
+            parse_spotify("https://open.spotify.com/album/not-an-id!"),
+            Err(SpotifyResourceFromUrlError::InvalidResourceId { id, .. }) if id == "not-an-id!"
+        ));
+    }
+
+    #[tokio::test]
+    async fn spotify_playlists_are_not_releases() {
+        let playlist = parse_spotify(&format!("https://open.spotify.com/playlist/{SPOTIFY_ID}"));
+
+        assert!(matches!(
+            get_spotify_release(
+                &rspotify::ClientCredsSpotify::default(),
+                playlist.unwrap(),
+                &Config::default(),
+                test_deadline(),
+            )
+            .await,
+            Err(GetSpotifyReleaseError::PlaylistNotRelease { url })
+                if url == format!("https://open.spotify.com/playlist/{SPOTIFY_ID}")
+        ));
+    }
+
+    #[test]
+    fn spotify_locale_segments_are_skipped() {
+        assert!(matches!(
+            parse_spotify(&format!("https://open.spotify.com/intl-pt-BR/track/{SPOTIFY_ID}")),
+            Ok(SpotifyResource::Track { id }) if id.id() == SPOTIFY_ID
+        ));
+        // only the first segment can be a locale
+        assert!(matches!(
+            parse_spotify(&format!(
+                "https://open.spotify.com/album/intl-es/{SPOTIFY_ID}"
+            )),
+            Err(SpotifyResourceFromUrlError::InvalidResourceId { .. })
+        ));
+    }
+
+    #[test]
+    fn genres_are_the_albums_and_artists_readable_ones() {
+        let mut album = ld_json_album();
+        album["genre"] = serde_json::json!(["ambient", "https://example.com/genres/dream-pop"]);
+        album["byArtist"]["genre"] = serde_json::json!(["ambient", "drone"]);
+
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(release.genres, ["ambient", "drone"]);
+    }
+
+    fn roles_map(roles: &[(&str, u64)]) -> BTreeMap<Uncased<'static>, Role> {
+        BTreeMap::from_iter(roles.iter().map(|&(name, id)| {
+            let role: Role = serde_json::from_value(serde_json::json!({
+                "id": id.to_string(),
+                "name": name,
+                "color": 0,
+                "hoist": false,
+                "managed": false,
+                "mentionable": true,
+                "permissions": "0",
+                "position": 1,
+                "flags": 0,
+            }))
+            .unwrap();
+            (Uncased::from(name).into_owned(), role)
+        }))
+    }
+
+    #[test]
+    fn genres_are_listed_and_optionally_ping_their_roles() {
+        let mut release = release(ReleaseType::Single, "Low Tide", &["Harbor"], 1);
+        release.genres = vec!["ambient".to_owned(), "drone".to_owned()];
+        let roles_map = roles_map(&[("Ambient", 30)]);
+        let genres_line = |config: &Config| {
+            let formatted = format_release_at(
+                release.clone(),
+                &roles_map,
+                config,
+                LabelPlacement::Inline,
+                replay_day(),
+            )
+            .message;
+            formatted.lines().last().map(str::to_owned)
+        };
+
+        let shown = Config {
+            show_genres: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            genres_line(&shown).as_deref(),
+            Some("genres: ambient, drone")
+        );
+
+        let pinged = Config {
+            ping_genre_roles: true,
+            ..shown
+        };
+        assert_eq!(
+            genres_line(&pinged).as_deref(),
+            Some("genres: <@&30>, drone")
+        );
+
+        assert!(!format(release).contains("genres"));
+    }
+
+    #[test]
+    fn track_artists_stand_in_for_a_missing_album_artist() {
+        let mut album = ld_json_album();
+        album.as_object_mut().unwrap().remove("byArtist");
+        album["track"]["itemListElement"][0]["item"]["byArtist"] =
+            serde_json::json!({ "@type": "MusicGroup", "name": "Harbor" });
+        album["track"]["itemListElement"][1]["item"]["byArtist"] =
+            serde_json::json!({ "@type": "MusicGroup", "name": "Reef" });
+
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(
+            Vec::from_iter(
+                release
+                    .main_artists
+                    .iter()
+                    .map(|artist| artist.name.as_str())
+            ),
+            ["Harbor", "Reef"]
+        );
+        assert!(release.tracks.iter().all(|track| !track.artists_inherited));
+    }
+
+    #[test]
+    fn no_artist_anywhere_is_an_error() {
+        let mut album = ld_json_album();
+        album.as_object_mut().unwrap().remove("byArtist");
+
+        assert!(matches!(
+            release_from_ld_json(&[album]),
+            Err(GetReleaseFromLdJsonError::NoArtists)
+        ));
+    }
+
+    #[test]
+    fn the_year_is_left_out_of_dates_from_the_past_year() {
+        let format_on = |year, month, day| {
+            let now = Date::from_calendar_date(year, month, day)
+                .unwrap()
+                .midnight()
+                .assume_utc();
+            let release = release(ReleaseType::Single, "Low Tide", &["Harbor"], 1);
+            format_release_at(
+                release,
+                &BTreeMap::new(),
+                &Config::default(),
+                LabelPlacement::Inline,
+                now,
+            )
+            .message
+        };
+
+        assert!(format_on(2024, time::Month::May, 31).ends_with("[5/31]"));
+        assert!(format_on(2025, time::Month::May, 1).ends_with("[5/31]"));
+        assert!(format_on(2025, time::Month::June, 1).ends_with("[2024/5/31]"));
+    }
+
+    #[test]
+    fn a_release_record_label_is_preferred_over_the_publisher() {
+        let mut album = ld_json_album();
+        album["publisher"] = serde_json::json!({ "@type": "Organization", "name": "Distro Co" });
+
+        let release = release_from_ld_json(&[album.clone()]).unwrap();
+        assert_eq!(release.record_labels, ["Distro Co"]);
+
+        album["albumRelease"][0]["recordLabel"] =
+            serde_json::json!({ "@type": "Organization", "name": "Tidewater Records" });
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(release.record_labels, ["Tidewater Records"]);
+    }
+
+    #[test]
+    fn tracks_with_their_own_artists_can_be_credited_one_by_one() {
+        let mut release = release(ReleaseType::LP, "Low Tide", &["Harbor"], 2);
+        release.tracks.push(track(
+            3,
+            "Breakwater",
+            vec![artist("Harbor"), artist("Guest Singer")],
+        ));
+        release.tracks.push(Track {
+            position: None,
+            ..track(4, "Riptide", vec![artist("Reef")])
+        });
+        let config = Config {
+            per_track_credits: true,
+            ..Config::default()
+        };
+
+        let formatted = format_release_at(
+            release,
+            &BTreeMap::new(),
+            &config,
+            LabelPlacement::Inline,
+            replay_day(),
+        )
+        .message;
+        assert_eq!(
+            Vec::from_iter(formatted.lines().skip(1)),
+            [
+                "3. Breakwater — **Harbor** & **Guest Singer**",
+                "Riptide — **Reef**",
+            ]
+        );
+    }
+
+    #[test]
+    fn ld_json_tracks_go_in_position_order() {
+        let mut album = ld_json_album();
+        album["track"]["itemListElement"] = serde_json::json!([
+            { "@type": "ListItem", "item": { "@type": "MusicRecording", "name": "Bonus" } },
+            { "@type": "ListItem", "position": 3, "item": { "@type": "MusicRecording", "name": "Shoals" } },
+            { "@type": "ListItem", "position": 1, "item": { "@type": "MusicRecording", "name": "Undertow" } },
+            { "@type": "ListItem", "position": 2, "item": { "@type": "MusicRecording", "name": "Low Tide" } },
+        ]);
+
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(
+            Vec::from_iter(
+                release
+                    .tracks
+                    .iter()
+                    .map(|track| track.title.as_deref().unwrap())
+            ),
+            ["Undertow", "Low Tide", "Shoals", "Bonus"]
+        );
+    }
+
+    #[test]
+    fn the_digital_release_describes_the_album() {
+        let mut album = ld_json_album();
+        album.as_object_mut().unwrap().remove("datePublished");
+        album["albumRelease"] = serde_json::json!([
+            {
+                "@type": "MusicRelease",
+                "@id": "https://harbor.bandcamp.com/album/low-tide#cd",
+                "musicReleaseFormat": "CDFormat",
+                "datePublished": "2024-04-01",
+                "catalogNumber": "TIDE-001CD",
+            },
+            {
+                "@type": "MusicRelease",
+                "@id": "https://harbor.bandcamp.com/album/low-tide",
+                "musicReleaseFormat": "DigitalFormat",
+                "datePublished": "2024-05-31",
+                "catalogNumber": "TIDE-001",
+            },
+        ]);
+
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(
+            release.date,
+            Some(Date::from_calendar_date(2024, time::Month::May, 31).unwrap())
+        );
+        assert_eq!(release.catalog_number.as_deref(), Some("TIDE-001"));
+    }
+
+    #[test]
+    fn the_earliest_release_date_is_the_last_resort() {
+        let mut album = ld_json_album();
+        album.as_object_mut().unwrap().remove("datePublished");
+        album["albumRelease"] = serde_json::json!([
+            { "@type": "MusicRelease", "@id": "https://harbor.bandcamp.com/album/low-tide", "musicReleaseFormat": "DigitalFormat" },
+            { "@type": "MusicRelease", "@id": "https://harbor.bandcamp.com/album/low-tide#vinyl", "musicReleaseFormat": "VinylFormat", "datePublished": "2024-07-01" },
+            { "@type": "MusicRelease", "@id": "https://harbor.bandcamp.com/album/low-tide#cd", "musicReleaseFormat": "CDFormat", "datePublished": "2024-06-01" },
+        ]);
+
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(
+            release.date,
+            Some(Date::from_calendar_date(2024, time::Month::June, 1).unwrap())
+        );
+    }
+
+    fn assemble(date: &str, precision: DatePrecision) -> Result<Date, AssembleDateError> {
+        assemble_parsed_date(parse_date(date), precision)
+    }
+
+    #[test]
+    fn dates_at_each_precision() {
+        assert_eq!(
+            assemble("2024-05-31", DatePrecision::Day).unwrap(),
+            Date::from_calendar_date(2024, time::Month::May, 31).unwrap()
+        );
+        assert_eq!(
+            assemble("2024-05", DatePrecision::Month).unwrap(),
+            Date::from_calendar_date(2024, time::Month::May, 1).unwrap()
+        );
+        assert_eq!(
+            assemble("2024", DatePrecision::Year).unwrap(),
+            Date::from_calendar_date(2024, time::Month::January, 1).unwrap()
+        );
+        assert_eq!(
+            assemble(" 2024-05-31\n", DatePrecision::Day).unwrap(),
+            Date::from_calendar_date(2024, time::Month::May, 31).unwrap()
+        );
+    }
+
+    #[test]
+    fn days_past_the_end_of_the_month() {
+        assert!(matches!(
+            assemble("2024-05-32", DatePrecision::Day),
+            Err(AssembleDateError::ParseDayError { .. })
+        ));
+        assert!(matches!(
+            assemble("2024-04-31", DatePrecision::Day),
+            Err(AssembleDateError::DayOutOfRangeForMonth {
+                days_in_month: 30,
+                ..
+            })
+        ));
+        assert!(assemble("2024-02-29", DatePrecision::Day).is_ok());
+        assert!(matches!(
+            assemble("2023-02-29", DatePrecision::Day),
+            Err(AssembleDateError::DayOutOfRangeForMonth {
+                days_in_month: 28,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_and_malformed_date_parts() {
+        assert!(matches!(
+            assemble("2024", DatePrecision::Day),
+            Err(AssembleDateError::MonthMissing)
+        ));
+        assert!(matches!(
+            assemble("2024-05", DatePrecision::Day),
+            Err(AssembleDateError::DayMissing)
+        ));
+        assert!(matches!(
+            assemble("2024-13-01", DatePrecision::Day),
+            Err(AssembleDateError::ParseMonthError { .. })
+        ));
+        assert!(matches!(
+            assemble("twenty", DatePrecision::Year),
+            Err(AssembleDateError::ParseYearError { .. })
+        ));
+        assert!(matches!(
+            assemble("", DatePrecision::Year),
+            Err(AssembleDateError::ParseYearError { .. })
+        ));
+    }
+
+    /// Any day from year 0 through 9999 (the years `time` can represent)
+    fn valid_date() -> impl Strategy<Value = Date> {
+        (0..=9999i32, 1..=12u8)
+            .prop_flat_map(|(year, month)| {
+                let month = time::Month::try_from(month).unwrap();
+                (Just(year), Just(month), 1..=month.length(year))
+            })
+            .prop_map(|(year, month, day)| Date::from_calendar_date(year, month, day).unwrap())
+    }
+
+    fn date_precision() -> impl Strategy<Value = DatePrecision> {
+        prop_oneof![
+            Just(DatePrecision::Year),
+            Just(DatePrecision::Month),
+            Just(DatePrecision::Day),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn valid_dates_round_trip(date in valid_date(), padding in "[ \t\n]{0,2}") {
+            let (year, month, day) = (date.year(), u8::from(date.month()), date.day());
+
+            prop_assert_eq!(
+                assemble(
+                    &format!("{padding}{year:04}-{month:02}-{day:02}{padding}"),
+                    DatePrecision::Day
+                )
+                .unwrap(),
+                date
+            );
+            prop_assert_eq!(
+                assemble(&format!("{year}-{month}-{day}"), DatePrecision::Day).unwrap(),
+                date
+            );
+            prop_assert_eq!(
+                assemble(&format!("{year}-{month:02}"), DatePrecision::Month).unwrap(),
+                date.replace_day(1).unwrap()
+            );
+            prop_assert_eq!(
+                assemble(&format!("{year}"), DatePrecision::Year).unwrap(),
+                Date::from_calendar_date(year, time::Month::January, 1).unwrap()
+            );
+        }
+
+        #[test]
+        fn days_past_the_end_of_the_month_are_refused(
+            year in 0..=9999i32,
+            month in 1..=12u8,
+            days_over in 1..=20u8,
+        ) {
+            let day = time::Month::try_from(month).unwrap().length(year) + days_over;
+
+            prop_assert!(
+                assemble(&format!("{year}-{month:02}-{day:02}"), DatePrecision::Day).is_err()
+            );
+        }
+
+        #[test]
+        fn months_past_december_are_refused(year in 0..=9999i32, month in 13..=99u8) {
+            prop_assert!(matches!(
+                assemble(&format!("{year}-{month}"), DatePrecision::Month),
+                Err(AssembleDateError::ParseMonthError { .. })
+            ));
+        }
+
+        #[test]
+        fn years_that_arent_numbers_are_refused(
+            year in "[a-zA-Z ]{0,8}",
+            precision in date_precision(),
+        ) {
+            prop_assert!(matches!(
+                assemble(&year, precision),
+                Err(AssembleDateError::ParseYearError { .. })
+            ));
+        }
+
+        /// Whatever a page has in its date, it's an error at worst rather than a panic
+        #[test]
+        fn malformed_dates_never_panic(
+            date in "[0-9]{0,5}(-[0-9a-z]{0,3}){0,3}|\\PC{0,12}",
+            precision in date_precision(),
+        ) {
+            let _ = assemble(&date, precision);
+        }
+    }
+
+    #[test]
+    fn days_missing_from_a_month_say_so() {
+        let error = assemble("2023-02-30", DatePrecision::Day).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "February only has 28 days in 2023, so there's no day 30"
+        );
+    }
+
+    #[test]
+    fn overridden_artists_keep_the_ids_of_ones_found() {
+        let mut release = release(ReleaseType::LP, "Low Tide", &[], 7);
+        release.main_artists = vec![
+            artist_with_id("HARBOR", "0aaaaaaaaaaaaaaaaaaaaa"),
+            artist_with_id("Lanterns", "0bbbbbbbbbbbbbbbbbbbbb"),
+        ];
+
+        override_main_artists(&mut release, "Harbor & Reef".to_owned(), &[]);
+
+        assert_eq!(
+            Vec::from_iter(
+                release
+                    .main_artists
+                    .iter()
+                    .map(|artist| (artist.name.as_str(), artist.id.as_deref()))
+            ),
+            [("Harbor", Some("0aaaaaaaaaaaaaaaaaaaaa")), ("Reef", None)]
+        );
+    }
+
+    #[test]
+    fn digests_group_releases_under_their_type() {
+        let messages = digest(Vec::from_iter(
+            [
+                release(ReleaseType::Single, "One", &["Harbor"], 1),
+                release(ReleaseType::LP, "Two", &["Harbor"], 8),
+                release(ReleaseType::Single, "Three", &["Harbor"], 1),
+            ]
+            .map(|release| digest_entry(release, &BTreeMap::new(), &Config::default()).unwrap()),
+        ));
+
+        assert_eq!(messages.len(), 1);
+        let lines = Vec::from_iter(messages[0].lines().filter(|line| !line.is_empty()));
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "## Singles");
+        assert!(lines[1].contains("[One]"));
+        assert!(lines[2].contains("[Three]"));
+        assert_eq!(lines[3], "## LPs");
+        assert!(lines[4].contains("[Two]"));
+    }
+
+    #[test]
+    fn releases_too_long_for_a_digest_message_are_refused() {
+        let too_long = release(
+            ReleaseType::Single,
+            &"x".repeat(MESSAGE_CONTENT_LIMIT + 1),
+            &["Harbor"],
+            1,
+        );
+
+        assert!(matches!(
+            digest_entry(too_long, &BTreeMap::new(), &Config::default()),
+            Err(DigestEntryError::TooLongForMessage { length }) if length > MESSAGE_CONTENT_LIMIT
+        ));
+    }
+
+    #[test]
+    fn digest_headers_are_repeated_in_the_next_message() {
+        let entries = Vec::from_iter((0..4).map(|i| {
+            let release = release(
+                ReleaseType::Single,
+                &format!("{i}{}", "x".repeat(600)),
+                &["Harbor"],
+                1,
+            );
+            digest_entry(release, &BTreeMap::new(), &Config::default()).unwrap()
+        }));
+        let messages = digest(entries);
+
+        assert_eq!(messages.len(), 2);
+        assert!(
+            messages
+                .iter()
+                .all(|message| message.starts_with("## Singles\n"))
+        );
+        assert!(
+            messages
+                .iter()
+                .all(|message| message.chars().count() <= MESSAGE_CONTENT_LIMIT)
+        );
+    }
+
+    #[test]
+    fn apple_music_embed_urls() {
+        let embed_url = |url: &str| {
+            apple_music_embed_url(&IriRefBuf::new(url.to_owned()).unwrap())
+                .map(|embed_url| embed_url.as_str().to_owned())
+        };
+
+        assert_eq!(
+            embed_url("https://music.apple.com/us/album/low-tide/1700000000?i=1700000001")
+                .as_deref(),
+            Some("https://embed.music.apple.com/us/album/low-tide/1700000000?i=1700000001")
+        );
+        assert_eq!(
+            embed_url("https://Music.Apple.com/gb/album/low-tide/1700000000").as_deref(),
+            Some("https://embed.music.apple.com/gb/album/low-tide/1700000000")
+        );
+        assert_eq!(
+            embed_url("https://harbor.bandcamp.com/album/low-tide"),
+            None
+        );
+    }
+
+    #[test]
+    fn music_videos_are_only_recognized_when_configured() {
+        let page = r#"<script type="application/ld+json">
+            {
+                "@context": "https://schema.org",
+                "@type": "MusicVideoObject",
+                "name": "Glass",
+                "byArtist": { "@type": "MusicGroup", "name": "Harbor" },
+                "uploadDate": "2024-05-31"
+            }
+        </script>"#;
+        let url = IriRefBuf::new("https://videos.example.com/glass".to_owned()).unwrap();
+
+        assert!(
+            get_release_from_page(
+                page,
+                url.as_iri_ref(),
+                TrackCountThresholds::default(),
+                false,
+                &[],
+            )
+            .is_err()
+        );
+
+        let release = get_release_from_page(
+            page,
+            url.as_iri_ref(),
+            TrackCountThresholds::default(),
+            true,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(release.kind, ReleaseType::Video);
+        assert_eq!(
+            format(release),
+            "**Harbor** - [Glass](<https://videos.example.com/glass>) (Music Video) [5/31]"
+        );
+    }
+
+    #[test]
+    fn pages_without_release_data_list_what_is_supported() {
+        let error = semantic_data_error(GetSemanticDataError::ReleaseFromLdJsonError {
+            source: GetReleaseFromLdJsonError::NoSemanticDataInPage,
+        });
+
+        assert!(matches!(error, GetReleaseError::UnsupportedService { .. }));
+        assert!(error.to_string().contains("Bandcamp albums and tracks"));
+    }
+
+    #[test]
+    fn every_publisher_is_a_label() {
+        let mut album = ld_json_album();
+        album["publisher"] = serde_json::json!([
+            { "@type": "Organization", "name": "Tidewater Records" },
+            { "@type": "Organization", "name": "Distro Co" },
+            { "@type": "Organization", "name": "Tidewater Records" },
+        ]);
+
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(release.record_labels, ["Tidewater Records", "Distro Co"]);
+
+        let roles_map = roles_map(&[("Tidewater Records", 40), ("Distro Co", 41)]);
+        assert_eq!(
+            format_release_at(
+                release,
+                &roles_map,
+                &Config::default(),
+                LabelPlacement::Inline,
+                replay_day()
+            )
+            .message,
+            "**Harbor** - [Low Tide](<https://harbor.bandcamp.com/album/low-tide>) [5/31 on <@&40> & <@&41>]"
+        );
+    }
+
+    #[test]
+    fn separate_labels_are_left_out_of_the_message() {
+        let mut album = ld_json_album();
+        album["publisher"] = serde_json::json!([
+            { "@type": "Organization", "name": "Tidewater Records" },
+            { "@type": "Organization", "name": "Distro Co" },
+        ]);
+        let release = release_from_ld_json(&[album]).unwrap();
+
+        // unlike inline labels, ones shown separately don't need a role to be shown
+        let roles_map = roles_map(&[("Tidewater Records", 40)]);
+        let formatted = format_release_at(
+            release,
+            &roles_map,
+            &Config::default(),
+            LabelPlacement::Separate,
+            replay_day(),
+        );
+
+        assert_eq!(
+            formatted.message,
+            "**Harbor** - [Low Tide](<https://harbor.bandcamp.com/album/low-tide>) [5/31]"
+        );
+        assert_eq!(
+            formatted.label.as_deref(),
+            Some("Tidewater Records & Distro Co")
+        );
+    }
+
+    #[test]
+    fn labels_are_shown_as_configured() {
+        let mut album = ld_json_album();
+        album["publisher"] = serde_json::json!([
+            { "@type": "Organization", "name": "Tidewater Records" },
+            { "@type": "Organization", "name": "Distro Co" },
+        ]);
+        let release = release_from_ld_json(&[album]).unwrap();
+        let roles_map = roles_map(&[("Tidewater Records", 40)]);
+
+        let format_with = |label_display| {
+            let config = Config {
+                label_display,
+                ..Config::default()
+            };
+            format_release_at(
+                release.clone(),
+                &roles_map,
+                &config,
+                LabelPlacement::Inline,
+                replay_day(),
+            )
+            .message
+        };
+
+        assert_eq!(
+            format_with(LabelDisplay::Never),
+            "**Harbor** - [Low Tide](<https://harbor.bandcamp.com/album/low-tide>) [5/31]"
+        );
+        assert_eq!(
+            format_with(LabelDisplay::WhenRole),
+            "**Harbor** - [Low Tide](<https://harbor.bandcamp.com/album/low-tide>) [5/31 on <@&40>]"
+        );
+        assert_eq!(
+            format_with(LabelDisplay::Always),
+            "**Harbor** - [Low Tide](<https://harbor.bandcamp.com/album/low-tide>) [5/31 on <@&40> & **Distro Co**]"
+        );
+    }
+
+    #[test]
+    fn compilations_by_artist_or_title() {
+        let looks_like =
+            |title, artists: &[&str]| looks_like_compilation(title, artists.iter().copied());
+
+        assert!(looks_like("Summer Sounds", &["Various Artists"]));
+        assert!(looks_like("Tidewater Sampler 2024", &["Tidewater Records"]));
+        assert!(looks_like("VA - Summer Sounds", &["Tidewater Records"]));
+        assert!(looks_like("v.a.: Summer Sounds", &["Tidewater Records"]));
+        assert!(!looks_like("Low Tide", &["Harbor"]));
+        // only whole words count
+        assert!(!looks_like("Samplers of the Sea", &["Harbor"]));
+        assert!(!looks_like("Vast Seas", &["Harbor"]));
+    }
+
+    #[test]
+    fn blobs_for_the_same_album_are_merged() {
+        let minimal = serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": "MusicAlbum",
+            "@id": "https://harbor.bandcamp.com/album/low-tide",
+            "name": "Low Tide",
+            "albumRelease": [],
+            "image": "https://f4.bcbits.com/img/a1234567890_10.jpg",
+            "track": {
+                "@type": "ItemList",
+                "numberOfItems": 1,
+                "itemListElement": [{
+                    "@type": "ListItem",
+                    "position": 1,
+                    "item": { "@type": "MusicRecording", "name": "Undertow" },
+                }],
+            },
+        });
+
+        let release = release_from_ld_json(&[minimal, ld_json_album()]).unwrap();
+        // what only the full blob has
+        assert_eq!(
+            Vec::from_iter(
+                release
+                    .main_artists
+                    .iter()
+                    .map(|artist| artist.name.as_str())
+            ),
+            ["Harbor"]
+        );
+        // the full blob's longer track list
+        assert_eq!(release.tracks.len(), 2);
+        // what only the minimal blob has
+        assert_eq!(
+            Vec::from_iter(release.images.iter().map(|image| image.url.as_str())),
+            ["https://f4.bcbits.com/img/a1234567890_10.jpg"]
+        );
+    }
+
+    #[test]
+    fn blobs_for_other_albums_are_not_merged() {
+        let mut other = ld_json_album();
+        other["@id"] = "https://harbor.bandcamp.com/album/high-tide".into();
+        other["name"] = "High Tide".into();
+
+        let release = release_from_ld_json(&[ld_json_album(), other]).unwrap();
+        assert_eq!(release.title, "Low Tide");
+    }
+
+    #[test]
+    fn open_spotify_urls_have_no_locale() {
+        assert_eq!(
+            open_spotify_url(&AlbumId::from_id(SPOTIFY_ID).unwrap()),
+            format!("https://open.spotify.com/album/{SPOTIFY_ID}")
+        );
+        assert_eq!(
+            open_spotify_url(&ArtistId::from_uri(&format!("spotify:artist:{SPOTIFY_ID}")).unwrap()),
+            format!("https://open.spotify.com/artist/{SPOTIFY_ID}")
+        );
+
+        // a link to a localized page comes back as the plain one
+        let Ok(SpotifyResource::Album { id }) = parse_spotify(&format!(
+            "https://open.spotify.com/intl-de/album/{SPOTIFY_ID}?si=abc"
+        )) else {
+            panic!("the link should be to an album");
+        };
+        assert_eq!(
+            open_spotify_url(&id),
+            format!("https://open.spotify.com/album/{SPOTIFY_ID}")
+        );
+    }
+
+    #[test]
+    fn another_music_release_date_stands_in_for_the_albums() {
+        let mut album = ld_json_album();
+        album.as_object_mut().unwrap().remove("datePublished");
+        // the digital release is still the chosen one, even though only the CD has a date
+        album["albumRelease"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({
+                "@type": "MusicRelease",
+                "@id": "https://harbor.bandcamp.com/album/low-tide#cd",
+                "name": "Low Tide",
+                "musicReleaseFormat": "CDFormat",
+                "dateCreated": "2024-05-30",
+            }));
+
+        let release = release_from_ld_json(&[album.clone()]).unwrap();
+        assert_eq!(
+            release.date,
+            Some(Date::from_calendar_date(2024, time::Month::May, 30).unwrap())
+        );
+
+        album["albumRelease"][1]
+            .as_object_mut()
+            .unwrap()
+            .remove("dateCreated");
+        assert!(matches!(
+            release_from_ld_json(&[album]),
+            Err(GetReleaseFromLdJsonError::NoDate)
+        ));
+    }
+
+    #[test]
+    fn artist_roles_go_by_spotify_id_in_this_server() {
+        let guild_id = DiscordId::new(3);
+        let artist_roles = [
+            ArtistRole {
+                guild_id,
+                spotify_artist_id: "0aaaaaaaaaaaaaaaaaaaaa".to_owned(),
+                role_id: DiscordId::new(30),
+            },
+            ArtistRole {
+                guild_id: DiscordId::new(4),
+                spotify_artist_id: "0bbbbbbbbbbbbbbbbbbbbb".to_owned(),
+                role_id: DiscordId::new(40),
+            },
+        ];
+
+        let mut release = release(ReleaseType::Single, "Glass", &[], 0);
+        release.main_artists = vec![
+            artist_with_id("Harbour", "spotify:artist:0aaaaaaaaaaaaaaaaaaaaa"),
+            artist_with_id("Reef", "0bbbbbbbbbbbbbbbbbbbbb"),
+        ];
+        release.tracks = vec![track(
+            1,
+            "Glass",
+            vec![artist_with_id(
+                "Harbour",
+                "spotify:artist:0aaaaaaaaaaaaaaaaaaaaa",
+            )],
+        )];
+        assign_artist_roles(&mut release, &artist_roles, guild_id);
+
+        assert_eq!(release.main_artists[0].role_id, Some(DiscordId::new(30)));
+        // configured, but for another server
+        assert_eq!(release.main_artists[1].role_id, None);
+        assert_eq!(
+            release.tracks[0].artists[0].role_id,
+            Some(DiscordId::new(30))
+        );
+        // the role is pinged even though it's named differently from how the artist's credited
+        assert_eq!(
+            format(release),
+            "<@&30> & **Reef** - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+    }
+
+    /// An error like the one rspotify gives when Spotify answers with 429 Too Many Requests
+    fn rate_limited(retry_after: &str) -> rspotify::ClientError {
+        let response = axum::http::Response::builder()
+            .status(reqwest::StatusCode::TOO_MANY_REQUESTS)
+            .header(reqwest::header::RETRY_AFTER, retry_after)
+            .body("")
+            .unwrap();
+
+        rspotify::ClientError::Http(Box::new(rspotify::http::HttpError::StatusCode(
+            reqwest::Response::from(response),
+        )))
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_are_retried_a_few_times() {
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(30);
+
+        let mut attempts = 0;
+        let result = with_spotify_retries(deadline, || {
+            attempts += 1;
+            let result = if attempts < 3 {
+                Err(rate_limited("0"))
+            } else {
+                Ok(attempts)
+            };
+            async move { result }
+        })
+        .await;
+        assert!(matches!(result, Ok(3)));
+
+        let mut attempts = 0;
+        let result = with_spotify_retries(deadline, || {
+            attempts += 1;
+            async { Err::<(), _>(rate_limited("0")) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, SPOTIFY_MAX_RETRIES + 1);
+
+        // waiting as long as Spotify asks would go past the deadline, so there's no point
+        let mut attempts = 0;
+        let result = with_spotify_retries(deadline, || {
+            attempts += 1;
+            async { Err::<(), _>(rate_limited("60")) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn releases_that_format_to_nothing_are_caught() {
+        let formatted = |message: &str| FormattedRelease {
+            message: message.to_owned(),
+            label: None,
+            upc: None,
+            artist_missing: false,
+            title_missing: false,
+        };
+
+        assert!(matches!(
+            formatted("").validate(&Config::default()),
+            Err(EmptyFormattedReleaseError::NothingFormatted)
+        ));
+        assert!(matches!(
+            formatted("  \nwith **Reef**").validate(&Config::default()),
+            Err(EmptyFormattedReleaseError::NothingFormatted)
+        ));
+
+        let formatted_release = |release| {
+            format_release_at(
+                release,
+                &BTreeMap::new(),
+                &Config::default(),
+                LabelPlacement::Inline,
+                replay_day(),
+            )
+        };
+        assert!(matches!(
+            formatted_release(release(ReleaseType::LP, "", &["Harbor"], 9))
+                .validate(&Config::default()),
+            Err(EmptyFormattedReleaseError::NoTitleFormatted)
+        ));
+        assert!(matches!(
+            formatted_release(release(ReleaseType::LP, "  ", &["Harbor"], 9))
+                .validate(&Config::default()),
+            Err(EmptyFormattedReleaseError::NoTitleFormatted)
+        ));
+        assert!(
+            formatted_release(release(ReleaseType::LP, "Low Tide", &["Harbor"], 9))
+                .validate(&Config::default())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn creators_stand_in_for_a_missing_album_artist() {
+        let mut album = ld_json_album();
+        album.as_object_mut().unwrap().remove("byArtist");
+        album["creator"] = serde_json::json!([
+            { "@type": "Person", "name": "Harbor" },
+            { "@type": "Person", "name": "Reef" },
+            { "@type": "Person", "name": "Harbor" },
+        ]);
+        // the creators come before the tracks' artists
+        album["track"]["itemListElement"][0]["item"]["byArtist"] =
+            serde_json::json!({ "@type": "MusicGroup", "name": "Shoal" });
+
+        let release = release_from_ld_json(&[album.clone()]).unwrap();
+        assert_eq!(
+            Vec::from_iter(
+                release
+                    .main_artists
+                    .iter()
+                    .map(|artist| artist.name.as_str())
+            ),
+            ["Harbor", "Reef"]
+        );
+
+        // but only when there's no byArtist
+        album["byArtist"] = serde_json::json!({ "@type": "MusicGroup", "name": "Tidepool" });
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(
+            Vec::from_iter(
+                release
+                    .main_artists
+                    .iter()
+                    .map(|artist| artist.name.as_str())
+            ),
+            ["Tidepool"]
+        );
+    }
+
+    #[test]
+    fn smart_link_hosts() {
+        let is_smart = |url: &str| is_smart_link(IriRef::new(url).unwrap());
+
+        assert!(is_smart("https://songwhip.com/harbor/low-tide"));
+        assert!(is_smart("https://harbor.lnk.to/LowTide"));
+        assert!(is_smart("https://FFM.to/lowtide"));
+        assert!(!is_smart("https://notlnk.to/LowTide"));
+        assert!(!is_smart("https://harbor.bandcamp.com/album/low-tide"));
+    }
+
+    #[test]
+    fn smart_link_pages_list_the_services_they_link_to() {
+        let page = format!(
+            r#"<a href="https://harbor.bandcamp.com/album/low-tide?from=lnk">Bandcamp</a>
+            <a href="https://harbor.lnk.to/LowTide">Share</a>
+            <script>{{"links":["https:\/\/open.spotify.com\/album\/{SPOTIFY_ID}?si=abc","https://harbor.bandcamp.com/album/low-tide"]}}</script>"#
+        );
+
+        assert_eq!(
+            Vec::from_iter(
+                service_links_in_page(&page)
+                    .iter()
+                    .map(|(service, link)| (*service, link.as_str()))
+            ),
+            [
+                (
+                    Service::Bandcamp,
+                    "https://harbor.bandcamp.com/album/low-tide"
+                ),
+                (
+                    Service::Spotify,
+                    format!("https://open.spotify.com/album/{SPOTIFY_ID}").as_str()
+                ),
+            ]
+        );
+    }
+
+    /// Stands in for both Discord's and Spotify's APIs, answering with saved responses
+    async fn mock_apis() -> u16 {
+        let json = |body: String| {
+            move || async move {
+                (
+                    [(axum::http::header::CONTENT_TYPE, "application/json")],
+                    body,
+                )
+            }
+        };
+
+        let app = axum::Router::new()
+            .route(
+                "/api/v10/guilds/{guild_id}/roles",
+                axum::routing::get(json(fixture("discord-roles.json"))),
+            )
+            .route(
+                "/api/token",
+                axum::routing::post(json(
+                    r#"{"access_token":"token","token_type":"Bearer","expires_in":3600}"#
+                        .to_owned(),
+                )),
+            )
+            .route(
+                "/v1/albums/{album_id}",
+                axum::routing::get(json(fixture("spotify-album.json"))),
+            )
+            .route(
+                "/v1/albums/{album_id}/tracks",
+                axum::routing::get(json(fixture("spotify-album-tracks.json"))),
+            )
+            // a standalone single, and the one track on it
+            .route(
+                "/v1/albums/5bbbbbbbbbbbbbbbbbbbbb",
+                axum::routing::get(json(fixture("spotify-single.json"))),
+            )
+            .route(
+                "/v1/albums/5bbbbbbbbbbbbbbbbbbbbb/tracks",
+                axum::routing::get(json(fixture("spotify-single-tracks.json"))),
+            )
+            .route(
+                "/v1/tracks/{track_id}",
+                axum::routing::get(json(fixture("spotify-track.json"))),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        port
+    }
+
+    fn new_release_interaction(url: &str) -> Interaction {
+        let mut interaction = member_interaction(Permissions::SEND_MESSAGES, &[]);
+        interaction.kind =
+            twilight_model::application::interaction::InteractionType::ApplicationCommand;
+        interaction.data = Some(
+            serde_json::from_value(serde_json::json!({
+                "id": "5",
+                "name": NAME,
+                "type": 1,
+                "options": [{ "name": "url", "type": 3, "value": url }],
+            }))
+            .map(|command_data| InteractionData::ApplicationCommand(Box::new(command_data)))
+            .unwrap(),
+        );
+
+        interaction
+    }
+
+    /// A [`State`] whose clients talk to [`mock_apis`] instead of Discord and Spotify
+    async fn mocked_state() -> State {
+        let port = mock_apis().await;
+        let local = format!("http://127.0.0.1:{port}/");
+
+        let discord_client = twilight_http::Client::builder()
+            .proxy(format!("127.0.0.1:{port}"), true)
+            .build();
+        let spotify_client = rspotify::ClientCredsSpotify::with_config(
+            rspotify::Credentials::new("id", "secret"),
+            rspotify::Config {
+                api_base_url: format!("{local}v1/"),
+                auth_base_url: local,
+                ..rspotify::Config::default()
+            },
+        );
+        State {
+            discord_client: Arc::new(discord_client),
+            spotify_client: Some(Arc::new(spotify_client)),
+            ..state(Config::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn handles_a_spotify_album_end_to_end() {
+        let response = handle(
+            mocked_state().await,
+            new_release_interaction("https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy?si=abc"),
+        )
+        .await;
+
+        let CommandResponse::Succeeded(response) = response else {
+            panic!("expected the release, got {response:?}");
+        };
+        let embeds = response.data.unwrap().embeds.unwrap();
+        let [content, preview] = embeds.as_slice() else {
+            panic!("expected a Content and a Preview embed, got {embeds:?}");
+        };
+        assert_eq!(content.title.as_deref(), Some("Content"));
+        // the main artist has a role in the server, so they're pinged
+        let message = "<@&30> - [Low Tide](<https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy>) (LP, 2 tracks) [2024/5/31]\nwith **Reef**";
+        assert_eq!(
+            content.description.as_deref(),
+            Some(format!("```\n{message}\n```").as_str())
+        );
+        assert_eq!(preview.title.as_deref(), Some(PREVIEW_EMBED_TITLE));
+        assert_eq!(preview.description.as_deref(), Some(message));
+        assert_eq!(
+            preview
+                .thumbnail
+                .as_ref()
+                .map(|thumbnail| thumbnail.url.as_str()),
+            Some("https://i.scdn.co/image/ab67616d0000b273aaaaaaaaaaaaaaaaaaaaaaaa")
+        );
+    }
+
+    #[tokio::test]
+    async fn a_spotify_track_that_is_a_single_is_that_single() {
+        let response = handle(
+            mocked_state().await,
+            new_release_interaction("https://open.spotify.com/track/3aaaaaaaaaaaaaaaaaaaaa"),
+        )
+        .await;
+
+        let CommandResponse::Succeeded(response) = response else {
+            panic!("expected the release, got {response:?}");
+        };
+        let embeds = response.data.unwrap().embeds.unwrap();
+        let [_content, preview] = embeds.as_slice() else {
+            panic!("expected a Content and a Preview embed, got {embeds:?}");
+        };
+        // linked as the single rather than the track, and not wrapped in anything else
+        assert_eq!(
+            preview.description.as_deref(),
+            Some(
+                "<@&30> - [Riptide](<https://open.spotify.com/album/5bbbbbbbbbbbbbbbbbbbbb>) [2024/4/12]"
+            )
+        );
+    }
+
+    #[test]
+    fn spotify_album_types_map_to_release_types() {
+        let release_type = |album_type, n_tracks, mapping| {
+            release_type_from_spotify(
+                album_type,
+                None,
+                n_tracks,
+                TrackCountThresholds::default(),
+                mapping,
+            )
+        };
+
+        let default = SpotifyAlbumTypeMapping::default();
+        assert_eq!(release_type(AlbumType::Album, 2, default), ReleaseType::LP);
+        assert_eq!(
+            release_type(AlbumType::Single, 2, default),
+            ReleaseType::Single
+        );
+        assert_eq!(release_type(AlbumType::Single, 3, default), ReleaseType::EP);
+        assert_eq!(
+            release_type(AlbumType::Compilation, 2, default),
+            ReleaseType::Compilation
+        );
+
+        let custom = SpotifyAlbumTypeMapping {
+            single: SpotifyReleaseType::EP,
+            compilation: SpotifyReleaseType::LP,
+            ..default
+        };
+        assert_eq!(release_type(AlbumType::Single, 1, custom), ReleaseType::EP);
+        assert_eq!(
+            release_type(AlbumType::Compilation, 20, custom),
+            ReleaseType::LP
+        );
+        assert_eq!(release_type(AlbumType::Album, 20, custom), ReleaseType::LP);
+    }
+
+    #[test]
+    fn upcs_come_from_spotify_external_ids() {
+        let external_ids = |ids: &[(&str, &str)]| {
+            HashMap::from_iter(
+                ids.iter()
+                    .map(|(key, value)| ((*key).to_owned(), (*value).to_owned())),
+            )
+        };
+
+        assert_eq!(
+            spotify_upc(&external_ids(&[("upc", "196922877401")])).as_deref(),
+            Some("196922877401")
+        );
+        // albums released outside of North America have an EAN instead
+        assert_eq!(
+            spotify_upc(&external_ids(&[("ean", "5021732160943")])).as_deref(),
+            Some("5021732160943")
+        );
+        assert_eq!(
+            spotify_upc(&external_ids(&[
+                ("ean", "5021732160943"),
+                ("upc", "196922877401")
+            ]))
+            .as_deref(),
+            Some("196922877401")
+        );
+        assert_eq!(spotify_upc(&external_ids(&[("upc", "")])), None);
+        assert_eq!(
+            spotify_upc(&external_ids(&[("isrc", "USRC17607839")])),
+            None
+        );
+    }
+
+    #[test]
+    fn upcs_come_from_the_music_releases_gtin() {
+        let mut album = ld_json_album();
+        album["albumRelease"][0]["gtin"] = "196922877401".into();
+        let release = release_from_ld_json(&[album.clone()]).unwrap();
+        assert_eq!(release.upc.as_deref(), Some("196922877401"));
+
+        album["albumRelease"][0]["gtin13"] = "0196922877401".into();
+        let release = release_from_ld_json(&[album]).unwrap();
+        assert_eq!(release.upc.as_deref(), Some("0196922877401"));
+    }
+
+    #[test]
+    fn upcs_are_only_kept_when_shown() {
+        let mut release = release(ReleaseType::LP, "Low Tide", &["Harbor"], 9);
+        release.upc = Some("196922877401".to_owned());
+
+        let upc_with = |show_upc| {
+            let config = Config {
+                show_upc,
+                ..Config::default()
+            };
+            format_release_at(
+                release.clone(),
+                &BTreeMap::new(),
+                &config,
+                LabelPlacement::Separate,
+                replay_day(),
+            )
+            .upc
+        };
+
+        assert_eq!(upc_with(true).as_deref(), Some("196922877401"));
+        assert_eq!(upc_with(false), None);
+    }
+
+    #[test]
+    fn release_type_indicators_go_before_the_first_line() {
+        let config = Config {
+            release_type_indicators: ReleaseTypeIndicators {
+                single: Some("🎵".to_owned()),
+                lp: Some("💿".to_owned()),
+                ..ReleaseTypeIndicators::default()
+            },
+            ..Config::default()
+        };
+        let format_with_indicators = |release| {
+            format_release_at(
+                release,
+                &BTreeMap::new(),
+                &config,
+                LabelPlacement::Inline,
+                replay_day(),
+            )
+            .message
+        };
+
+        assert_eq!(
+            format_with_indicators(release(ReleaseType::Single, "Glass", &["Harbor"], 1)),
+            "🎵 **Harbor** - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+        assert_eq!(
+            format_with_indicators(release(ReleaseType::LP, "Low Tide", &["Harbor"], 9)),
+            "💿 **Harbor** - [Low Tide](<https://example.bandcamp.com/album/example>) (LP, 9 tracks) [5/31]"
+        );
+        assert_eq!(
+            format_with_indicators(release(ReleaseType::EP, "Shoals", &["Harbor"], 4)),
+            "**Harbor** - [Shoals](<https://example.bandcamp.com/album/example>) (EP, 4 tracks) [5/31]"
+        );
+        assert_eq!(
+            format_with_indicators(release(
+                ReleaseType::Other("Mixtape".to_owned()),
+                "Tapes",
+                &["Harbor"],
+                12
+            )),
+            "🎶 **Harbor** - [Tapes](<https://example.bandcamp.com/album/example>) (Mixtape, 12 tracks) [5/31]"
+        );
+    }
+
+    #[test]
+    fn other_release_types_only_get_an_indicator_when_indicators_are_in_use() {
+        let mixtape = || {
+            release(
+                ReleaseType::Other("Mixtape".to_owned()),
+                "Tapes",
+                &["Harbor"],
+                12,
+            )
+        };
+        let config = Config {
+            release_type_indicators: ReleaseTypeIndicators {
+                other: Some("📼".to_owned()),
+                ..ReleaseTypeIndicators::default()
+            },
+            ..Config::default()
+        };
+        let configured = format_release_at(
+            mixtape(),
+            &BTreeMap::new(),
+            &config,
+            LabelPlacement::Inline,
+            replay_day(),
+        );
+
+        assert_eq!(
+            format(mixtape()),
+            "**Harbor** - [Tapes](<https://example.bandcamp.com/album/example>) (Mixtape, 12 tracks) [5/31]"
+        );
+        assert_eq!(
+            configured.message,
+            "📼 **Harbor** - [Tapes](<https://example.bandcamp.com/album/example>) (Mixtape, 12 tracks) [5/31]"
+        );
+    }
+
+    #[test]
+    fn release_types_round_trip_through_their_names() {
+        for release_type in [
+            ReleaseType::Single,
+            ReleaseType::EP,
+            ReleaseType::LP,
+            ReleaseType::Compilation,
+            ReleaseType::Remixes,
+            ReleaseType::Video,
+            ReleaseType::Other("Mixtape".to_owned()),
+        ] {
+            let parsed: ReleaseType = release_type.to_string().parse().unwrap();
+            assert_eq!(parsed, release_type);
+        }
+    }
+
+    #[test]
+    fn release_types_parse_ignoring_case_and_surrounding_whitespace() {
+        assert_eq!("ALBUM".parse::<ReleaseType>().unwrap(), ReleaseType::LP);
+        assert_eq!(" ep ".parse::<ReleaseType>().unwrap(), ReleaseType::EP);
+        assert_eq!("Video".parse::<ReleaseType>().unwrap(), ReleaseType::Video);
+    }
+
+    #[test]
+    fn unknown_release_types_are_kept_as_written() {
+        let parsed: ReleaseType = "  Live Session ".parse().unwrap();
+
+        assert_eq!(parsed, ReleaseType::Other("Live Session".to_owned()));
+        assert_eq!(parsed.to_string(), "Live Session");
+        assert!("   ".parse::<ReleaseType>().is_err());
+    }
+
+    /// Formats a single by Harbor and Reef that's also credited as featuring Reef
+    fn format_overlapping(overlapping_credits: OverlappingCredits) -> String {
+        let config = Config {
+            overlapping_credits,
+            ..Config::default()
+        };
+
+        format_release_at(
+            release(
+                ReleaseType::Single,
+                "Glass (feat. Reef)",
+                &["Harbor", "Reef"],
+                1,
+            ),
+            &BTreeMap::new(),
+            &config,
+            LabelPlacement::Inline,
+            replay_day(),
+        )
+        .message
+    }
+
+    #[test]
+    fn overlapping_credits_go_where_configured() {
+        assert_eq!(
+            format_overlapping(OverlappingCredits::Feature),
+            "**Harbor** (feat. **Reef**) - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+        assert_eq!(
+            format_overlapping(OverlappingCredits::Main),
+            "**Harbor** & **Reef** - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+        assert_eq!(
+            format_overlapping(OverlappingCredits::Both),
+            "**Harbor** & **Reef** (feat. **Reef**) - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+    }
+
+    #[test]
+    fn featuring_every_main_artist_keeps_them_as_main() {
+        assert_eq!(
+            format(release(
+                ReleaseType::Single,
+                "Glass (feat. Reef)",
+                &["Reef"],
+                1
+            )),
+            "**Reef** - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+    }
+
+    #[test]
+    fn co_release_labels_are_split_apart() {
+        let roles_map = roles_map(&[("Label A", 10), ("Salt & Pepper Records", 20)]);
+
+        assert_eq!(
+            split_record_label("Label A / Label B", &roles_map),
+            ["Label A", "Label B"]
+        );
+        assert_eq!(
+            split_record_label("Label A & Label B", &roles_map),
+            ["Label A", "Label B"]
+        );
+        assert_eq!(
+            split_record_label("Label A / Label B & Label C", &roles_map),
+            ["Label A", "Label B", "Label C"]
+        );
+        // a role's whole name stays together
+        assert_eq!(
+            split_record_label("salt & pepper records", &roles_map),
+            ["salt & pepper records"]
+        );
+        // nor are labels split on commas
+        assert_eq!(
+            split_record_label("Harbor Music, Inc. / Reef Records, LLC", &roles_map),
+            ["Harbor Music, Inc.", "Reef Records, LLC"]
+        );
+
+        let mut release = release(ReleaseType::LP, "Low Tide", &["Harbor"], 9);
+        release.record_labels = vec!["Label A / Label B".to_owned()];
+        let formatted = format_release_at(
+            release,
+            &roles_map,
+            &Config::default(),
+            LabelPlacement::Inline,
+            replay_day(),
+        );
+        // only the label with a role is shown, and it's pinged
+        assert!(
+            formatted.message.contains("<@&10>"),
+            "{}",
+            formatted.message
+        );
+        assert!(
+            !formatted.message.contains("Label B"),
+            "{}",
+            formatted.message
+        );
+    }
+
+    #[test]
+    fn a_singles_features_come_from_its_track_title_too() {
+        let single = |title: &str, track_title: &str| {
+            let mut release = release(ReleaseType::Single, title, &["Harbor"], 0);
+            release.tracks = vec![track(
+                1,
+                track_title,
+                vec![artist("Harbor"), artist("Reef"), artist("Shoal")],
+            )];
+            format(release)
+        };
+
+        // and the featured artist isn't also credited as an additional one
+        assert_eq!(
+            single("Glass", "Glass (feat. Reef & Shoal)"),
+            "**Harbor** (feat. **Reef** & **Shoal**) - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+        assert_eq!(
+            single("Glass (feat. Reef)", "Glass (feat. Reef & Shoal)"),
+            "**Harbor** (feat. **Reef** & **Shoal**) - [Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+    }
+
+    /// Formats a single by Harbor out on `date`, as of [`replay_day`] (6/1/2024)
+    fn format_dated(date: Date, config: &Config) -> String {
+        let mut release = release(ReleaseType::Single, "Glass", &["Harbor"], 1);
+        release.date = Some(date);
+        format_release_at(
+            release,
+            &BTreeMap::new(),
+            config,
+            LabelPlacement::Inline,
+            replay_day(),
+        )
+        .message
+    }
+
+    fn day_of_2024(month: time::Month, day: u8) -> Date {
+        Date::from_calendar_date(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn relative_dates_past_today_and_upcoming() {
+        assert_eq!(format_relative_date(0), "out today");
+        assert_eq!(format_relative_date(1), "yesterday");
+        assert_eq!(format_relative_date(3), "3 days ago");
+        assert_eq!(format_relative_date(13), "13 days ago");
+        assert_eq!(format_relative_date(14), "2 weeks ago");
+        assert_eq!(format_relative_date(59), "8 weeks ago");
+        assert_eq!(format_relative_date(60), "2 months ago");
+        assert_eq!(format_relative_date(364), "12 months ago");
+        assert_eq!(format_relative_date(365), "1 year ago");
+        assert_eq!(format_relative_date(800), "2 years ago");
+        assert_eq!(format_relative_date(-1), "tomorrow");
+        assert_eq!(format_relative_date(-3), "in 3 days");
+        assert_eq!(format_relative_date(-13), "in 13 days");
+        assert_eq!(format_relative_date(-14), "in 2 weeks");
+        assert_eq!(format_relative_date(-30), "in 4 weeks");
+        assert_eq!(format_relative_date(-60), "in 2 months");
+        assert_eq!(format_relative_date(-365), "in 1 year");
+
+        let config = Config {
+            show_relative_date: true,
+            ..Config::default()
+        };
+        let relative_date = |date| {
+            let message = format_dated(date, &config);
+            message[message.rfind('[').unwrap()..].to_owned()
+        };
+        assert_eq!(
+            relative_date(day_of_2024(time::Month::May, 29)),
+            "[5/29 (3 days ago)]"
+        );
+        assert_eq!(
+            relative_date(day_of_2024(time::Month::June, 1)),
+            "[6/1 (out today)]"
+        );
+        assert_eq!(
+            relative_date(day_of_2024(time::Month::June, 4)),
+            "[out 6/4 (in 3 days)]"
+        );
+
+        // off unless it's configured
+        assert!(
+            format_dated(day_of_2024(time::Month::May, 29), &Config::default()).ends_with("[5/29]")
+        );
+    }
+
+    #[test]
+    fn upcoming_releases_are_out_on_their_dates() {
+        let config = Config::default();
+
+        assert!(format_dated(day_of_2024(time::Month::July, 15), &config).ends_with("[out 7/15]"));
+        // the year is left out up to a year ahead, the same as a year behind
+        assert!(
+            format_dated(
+                Date::from_calendar_date(2025, time::Month::January, 15).unwrap(),
+                &config
+            )
+            .ends_with("[out 1/15]")
+        );
+        assert!(
+            format_dated(
+                Date::from_calendar_date(2025, time::Month::July, 1).unwrap(),
+                &config
+            )
+            .ends_with("[out 2025/7/1]")
+        );
+    }
+
+    #[tokio::test]
+    async fn responses_that_arent_pages_arent_blamed_on_the_service() {
+        let app = axum::Router::new()
+            .route(
+                "/cover.png",
+                axum::routing::get(|| async {
+                    (
+                        [(reqwest::header::CONTENT_TYPE, "image/png")],
+                        b"\x89PNG\0".to_vec(),
+                    )
+                }),
+            )
+            .route(
+                "/empty",
+                axum::routing::get(|| async { axum::response::Html("  ") }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // a name that resolves to the local server, since its address on its own isn't public
+        let http_client = reqwest::Client::builder()
+            .resolve(
+                "files.example.com",
+                std::net::SocketAddr::from(([127, 0, 0, 1], port)),
+            )
+            .build()
+            .unwrap();
+
+        for path in ["cover.png", "empty"] {
+            let url = IriRefBuf::new(format!("http://files.example.com:{port}/{path}")).unwrap();
+            let result = fetch_page(&http_client, url.as_iri_ref(), &Config::default()).await;
+
+            assert!(
+                matches!(
+                    result,
+                    Err(GetSemanticDataError::EmptyOrUnparseableBody { .. })
+                ),
+                "{path}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn artist_names_with_commas_stay_together() {
+        let parse = |artists_joined: &str, comma_artist_names: &[String]| {
+            Vec::from(parse_list_of_artists(
+                artists_joined.to_owned(),
+                comma_artist_names,
+            ))
+        };
+
+        assert_eq!(
+            parse("Harbor, Reef & Shoal", &[]),
+            ["Harbor", "Reef", "Shoal"]
+        );
+        assert_eq!(parse("Tyler, The Creator", &[]), ["Tyler, The Creator"]);
+        assert_eq!(parse("Earth, Wind & Fire", &[]), ["Earth, Wind & Fire"]);
+        assert_eq!(
+            parse("Harbor, Tyler, The Creator & Earth, Wind & Fire", &[]),
+            ["Harbor", "Tyler, The Creator", "Earth, Wind & Fire"]
+        );
+
+        // more can be configured
+        assert_eq!(
+            parse("Salt, Pepper & Reef", &[]),
+            ["Salt", "Pepper", "Reef"]
+        );
+        assert_eq!(
+            parse("Salt, Pepper & Reef", &["Salt, Pepper".to_owned()]),
+            ["Salt, Pepper", "Reef"]
+        );
+    }
+
+    #[test]
+    fn releases_with_no_artist_at_all_are_warned_about_or_refused() {
+        let formatted = |release, config: &Config| {
+            format_release_at(
+                release,
+                &BTreeMap::new(),
+                config,
+                LabelPlacement::Inline,
+                replay_day(),
+            )
+        };
+
+        let no_artist = formatted(
+            release(ReleaseType::Single, "Glass", &[], 1),
+            &Config::default(),
+        );
+        assert!(no_artist.artist_missing);
+        assert_eq!(
+            no_artist.message,
+            "[Glass](<https://example.bandcamp.com/album/example>) [5/31]"
+        );
+        // shown anyway by default
+        assert!(no_artist.validate(&Config::default()).is_ok());
+
+        let config = Config {
+            missing_artist: MissingArtist::Error,
+            ..Config::default()
+        };
+        assert!(matches!(
+            no_artist.validate(&config),
+            Err(EmptyFormattedReleaseError::NoArtistFormatted)
+        ));
+
+        // "Various Artists" says who it's by well enough
+        let various_artists = formatted(
+            release(ReleaseType::Compilation, "Tides", &["Various Artists"], 9),
+            &config,
+        );
+        assert!(!various_artists.artist_missing);
+        assert!(various_artists.validate(&config).is_ok());
+    }
+
+    /// A press of the "Post publicly" button on an ephemeral `new-release` response whose preview says `description`
+    fn post_publicly_interaction(
+        permissions: Permissions,
+        roles: &[u64],
+        description: &str,
+    ) -> Interaction {
+        let mut interaction = member_interaction(permissions, roles);
+        interaction.message = Some(
+            serde_json::from_value(serde_json::json!({
+                "id": "6",
+                "channel_id": "7",
+                "type": 0,
+                "author": { "id": "2", "username": "newsroom", "discriminator": "0000", "avatar": null },
+                "content": "",
+                "timestamp": "2024-06-01T00:00:00+00:00",
+                "edited_timestamp": null,
+                "tts": false,
+                "pinned": false,
+                "mention_everyone": false,
+                "mentions": [],
+                "mention_roles": [],
+                "attachments": [],
+                "embeds": [{ "type": "rich", "title": PREVIEW_EMBED_TITLE, "description": description }],
+            }))
+            .unwrap(),
+        );
+
+        interaction
+    }
+
+    #[test]
+    fn posting_publicly_only_pings_roles() {
+        let message = "<@&30> - [Low Tide](<https://example.bandcamp.com/album/low-tide>) [5/31]";
+        let interaction = post_publicly_interaction(Permissions::SEND_MESSAGES, &[], message);
+
+        let data = post_publicly(&interaction, &Config::default())
+            .unwrap()
+            .data
+            .unwrap();
+        assert_eq!(data.content.as_deref(), Some(message));
+        assert_eq!(
+            data.allowed_mentions
+                .map(|allowed_mentions| allowed_mentions.parse),
+            Some(vec![MentionType::Roles])
+        );
+    }
+
+    #[test]
+    fn posting_publicly_needs_to_be_allowed_in_the_channel() {
+        let message = "**Harbor** - [Low Tide](<https://example.bandcamp.com/album/low-tide>)";
+        let config = Config {
+            direct_post_role_id: Some(DiscordId::new(20)),
+            ..Config::default()
+        };
+
+        let cant_send = post_publicly_interaction(Permissions::empty(), &[], message);
+        assert!(matches!(
+            post_publicly(&cant_send, &config),
+            Err(PostPubliclyError::NotAllowed {
+                source: PermissionError::InsufficientPermissions { .. }
+            })
+        ));
+
+        let has_role = post_publicly_interaction(Permissions::empty(), &[20], message);
+        assert!(post_publicly(&has_role, &config).is_ok());
+    }
+
+    #[test]
+    fn releases_too_long_for_a_message_arent_posted_publicly() {
+        let message = "x".repeat(MESSAGE_CONTENT_LIMIT + 1);
+        let interaction = post_publicly_interaction(Permissions::SEND_MESSAGES, &[], &message);
+
+        assert!(matches!(
+            post_publicly(&interaction, &Config::default()),
+            Err(PostPubliclyError::TooLong { length }) if length == MESSAGE_CONTENT_LIMIT + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn releases_too_long_for_a_message_arent_posted_directly() {
+        let message = "x".repeat(MESSAGE_CONTENT_LIMIT + 1);
+
+        assert!(matches!(
+            post_directly(
+                &twilight_http::Client::new(String::new()),
+                DiscordId::new(3),
+                DiscordId::new(4),
+                &message
+            )
+            .await,
+            Err(DirectPostError::TooLong { length }) if length == MESSAGE_CONTENT_LIMIT + 1
+        ));
     }
 }
@@ -0,0 +1,77 @@
+use super::new_release::{GetRolesMapError, refresh_roles_map};
+use crate::command::{COLOR_SUCCESS, CommandResponse, State, error_response};
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::sync::LazyLock;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::Interaction,
+    },
+    channel::message::MessageFlags,
+    guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+use twilight_util::builder::{
+    InteractionResponseDataBuilder, command::CommandBuilder, embed::EmbedBuilder,
+};
+
+const NAME: &str = "refresh-roles";
+const DESCRIPTION: &str =
+    "Fetch this server's roles again right away, for after they've been added, renamed, or removed";
+
+pub static COMMAND: LazyLock<Command> = LazyLock::new(|| {
+    CommandBuilder::new(NAME, DESCRIPTION, CommandType::ChatInput)
+        // only the people who can edit roles know when they've changed
+        .default_member_permissions(Permissions::MANAGE_ROLES)
+        .validate()
+        .expect("command wasn't correct")
+        .build()
+});
+
+#[derive(Debug, Snafu)]
+enum HandleError {
+    /// the command was run outside of a Discord server
+    NotUsedInGuild,
+
+    /// couldn't get the roles in this server from Discord
+    RolesMapError { source: GetRolesMapError },
+}
+
+#[tracing::instrument(skip(state), ret)]
+async fn handle_impl(
+    state: State,
+    interaction: Interaction,
+) -> Result<InteractionResponse, HandleError> {
+    let guild_id = interaction.guild_id.context(NotUsedInGuildSnafu)?;
+
+    let roles_map = refresh_roles_map(&state.discord_client, &state.roles_cache, guild_id)
+        .await
+        .context(RolesMapSnafu)?;
+
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .embeds([EmbedBuilder::new()
+            .color(COLOR_SUCCESS)
+            .title("Roles refreshed")
+            .description(format!(
+                "Fetched this server's {} roles again.",
+                roles_map.len()
+            ))
+            .build()])
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
+}
+
+#[tracing::instrument]
+pub async fn handle(state: State, interaction: Interaction) -> CommandResponse {
+    let config = state.config.clone();
+
+    match handle_impl(state, interaction).await {
+        Ok(interaction_response) => CommandResponse::Succeeded(interaction_response),
+        Err(error) => CommandResponse::Failed(error_response(error, &config)),
+    }
+}
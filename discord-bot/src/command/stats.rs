@@ -0,0 +1,71 @@
+use crate::command::{COLOR_SUCCESS, CommandResponse, State, error_response};
+use snafu::{OptionExt, Snafu};
+use std::sync::LazyLock;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::Interaction,
+    },
+    channel::message::MessageFlags,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    command::CommandBuilder,
+    embed::{EmbedBuilder, EmbedFieldBuilder},
+};
+
+const NAME: &str = "stats";
+const DESCRIPTION: &str = "Show how many releases have been formatted in this server";
+
+pub static COMMAND: LazyLock<Command> = LazyLock::new(|| {
+    CommandBuilder::new(NAME, DESCRIPTION, CommandType::ChatInput)
+        .validate()
+        .expect("command wasn't correct")
+        .build()
+});
+
+#[derive(Debug, Snafu)]
+enum HandleError {
+    /// the command was run outside of a Discord server
+    NotUsedInGuild,
+
+    /// this instance of the bot isn't set up to keep usage stats
+    NotKept,
+}
+
+#[tracing::instrument(skip(state), ret)]
+async fn handle_impl(
+    state: State,
+    interaction: Interaction,
+) -> Result<InteractionResponse, HandleError> {
+    let guild_id = interaction.guild_id.context(NotUsedInGuildSnafu)?;
+    let usage_stats = state.usage_stats.context(NotKeptSnafu)?;
+
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .embeds([EmbedBuilder::new()
+            .color(COLOR_SUCCESS)
+            .title("Stats")
+            .field(EmbedFieldBuilder::new(
+                "Releases formatted",
+                usage_stats.releases(guild_id).await.to_string(),
+            ))
+            .build()])
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
+}
+
+#[tracing::instrument]
+pub async fn handle(state: State, interaction: Interaction) -> CommandResponse {
+    let config = state.config.clone();
+
+    match handle_impl(state, interaction).await {
+        Ok(interaction_response) => CommandResponse::Succeeded(interaction_response),
+        Err(error) => CommandResponse::Failed(error_response(error, &config)),
+    }
+}
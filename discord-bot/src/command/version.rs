@@ -0,0 +1,49 @@
+use crate::command::{COLOR_SUCCESS, CommandResponse, State};
+use std::sync::LazyLock;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::Interaction,
+    },
+    channel::message::MessageFlags,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    command::CommandBuilder,
+    embed::{EmbedBuilder, EmbedFieldBuilder},
+};
+
+const NAME: &str = "version";
+const DESCRIPTION: &str = "Show which version of this bot is running, for bug reports";
+
+/// Set by the build script, unless the build had no way to tell which commit it was
+const GIT_SHA: Option<&str> = option_env!("GIT_SHA");
+
+pub static COMMAND: LazyLock<Command> = LazyLock::new(|| {
+    CommandBuilder::new(NAME, DESCRIPTION, CommandType::ChatInput)
+        .validate()
+        .expect("command wasn't correct")
+        .build()
+});
+
+#[tracing::instrument]
+pub async fn handle(_state: State, _interaction: Interaction) -> CommandResponse {
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .embeds([EmbedBuilder::new()
+            .color(COLOR_SUCCESS)
+            .title("Version")
+            .field(EmbedFieldBuilder::new("Version", env!("CARGO_PKG_VERSION")))
+            .field(EmbedFieldBuilder::new(
+                "Commit",
+                GIT_SHA.unwrap_or("Unknown"),
+            ))
+            .build()])
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+
+    CommandResponse::Succeeded(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    })
+}
@@ -0,0 +1,601 @@
+use crate::RateLimit;
+use ahash::AHashSet;
+use snafu::{OptionExt, ResultExt, Snafu, ensure};
+use std::{num::ParseIntError, path::PathBuf, str::FromStr, time::Duration};
+use twilight_model::id::{
+    Id,
+    marker::{GuildMarker, RoleMarker},
+};
+
+/// Which of a release's images to show when there are several sizes to choose from
+#[derive(Debug, Clone, Copy)]
+pub enum ImageSize {
+    Largest,
+    /// The one whose longer side is closest to this many pixels, to save on bandwidth
+    Closest {
+        pixels: u32,
+    },
+}
+
+/// Whether to make sure the Spotify credentials work on startup, rather than finding out on the first Spotify link
+#[derive(Debug, Clone, Copy)]
+pub enum SpotifyCredentialCheck {
+    Skip,
+    /// Refuse to start if the credentials don't work
+    Required,
+    /// Keep running with Spotify integration disabled if the credentials don't work
+    Optional,
+}
+
+/// Which of a release's record labels to show
+#[derive(Debug, Clone, Copy)]
+pub enum LabelDisplay {
+    Never,
+    /// Only the labels that have a role in the server, which get pinged
+    WhenRole,
+    /// Every label, pinging the ones that have a role in the server
+    Always,
+}
+
+/// Where to credit an artist who's both one of a release's main artists and featured on it (or remixing it)
+#[derive(Debug, Clone, Copy)]
+pub enum OverlappingCredits {
+    /// Only as a feature or remixer, unless that would leave no main artists at all (then only as a main artist)
+    Feature,
+    /// Only as a main artist (remixers are still credited as such, since they're part of the title)
+    Main,
+    /// In both places, pinging them in each
+    Both,
+}
+
+/// What to do with a release that would be shown without any artist at all (not even "Various Artists"),
+/// which happens when the data source doesn't say who it's by
+#[derive(Debug, Clone, Copy)]
+pub enum MissingArtist {
+    /// Show it anyway, but say that the artist needs filling in
+    Warn,
+    /// Refuse to show it
+    Error,
+}
+
+/// A role to ping for a Spotify artist in one server, whatever name either of them goes by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtistRole {
+    pub guild_id: Id<GuildMarker>,
+    /// The ID at the end of the artist's open.spotify.com link
+    pub spotify_artist_id: String,
+    pub role_id: Id<RoleMarker>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ParseArtistRoleError {
+    /// expected a server ID, a Spotify artist ID, and a role ID separated by colons (like `GUILD_ID:SPOTIFY_ARTIST_ID:ROLE_ID`)
+    WrongNumberOfParts,
+
+    /// the server ID wasn't a valid ID
+    GuildIdInvalid { source: ParseIntError },
+
+    /// the Spotify artist ID was empty
+    SpotifyArtistIdMissing,
+
+    /// the role ID wasn't a valid ID
+    RoleIdInvalid { source: ParseIntError },
+}
+
+impl FromStr for ArtistRole {
+    type Err = ParseArtistRoleError;
+
+    /// Parses `GUILD_ID:SPOTIFY_ARTIST_ID:ROLE_ID`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let (Some(guild_id), Some(spotify_artist_id), Some(role_id), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return WrongNumberOfPartsSnafu.fail();
+        };
+
+        Ok(Self {
+            guild_id: guild_id.trim().parse().context(GuildIdInvalidSnafu)?,
+            spotify_artist_id: Some(spotify_artist_id.trim())
+                .filter(|id| !id.is_empty())
+                .context(SpotifyArtistIdMissingSnafu)?
+                .to_owned(),
+            role_id: role_id.trim().parse().context(RoleIdInvalidSnafu)?,
+        })
+    }
+}
+
+/// The longest a direct post's prefix or suffix can be, so the release itself still has room in the message
+const DIRECT_POST_WRAPPER_MAX_LENGTH: usize = 500;
+
+#[derive(Debug, Snafu)]
+pub enum DirectPostWrapperError {
+    /// the direct post {which} has a code block fence (```) in it, which would swallow the release into a code block
+    CodeFence { which: &'static str },
+
+    #[snafu(display(
+        "the direct post {which} is {length} characters long, but it can only be up to {DIRECT_POST_WRAPPER_MAX_LENGTH} so the release still fits in the message"
+    ))]
+    TooLong { which: &'static str, length: usize },
+}
+
+/// the Spotify market {market:?} isn't a two-letter country code that Spotify knows about
+#[derive(Debug, Snafu)]
+pub struct SpotifyMarketError {
+    market: String,
+}
+
+/// The most tracks a release can have to be labelled as each type, when the type is decided by counting tracks
+#[derive(Debug, Clone, Copy)]
+pub struct TrackCountThresholds {
+    pub single_max_tracks: usize,
+    pub ep_max_tracks: usize,
+}
+
+impl Default for TrackCountThresholds {
+    fn default() -> Self {
+        Self {
+            single_max_tracks: 2,
+            ep_max_tracks: 6,
+        }
+    }
+}
+
+/// What one of Spotify's album types is labelled as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpotifyReleaseType {
+    Single,
+    EP,
+    LP,
+    Compilation,
+    /// A single, or an EP once there are more tracks than `TrackCountThresholds::single_max_tracks`
+    SingleOrEP,
+}
+
+/// What each of Spotify's album types is labelled as, since servers file releases differently
+/// (like calling every single with more than one track an EP, with `single_max_tracks` set to 1)
+#[derive(Debug, Clone, Copy)]
+pub struct SpotifyAlbumTypeMapping {
+    pub album: SpotifyReleaseType,
+    pub single: SpotifyReleaseType,
+    pub compilation: SpotifyReleaseType,
+    /// Only used when Spotify gives nothing more specific than that the artist appears on the album
+    pub appears_on: SpotifyReleaseType,
+}
+
+impl Default for SpotifyAlbumTypeMapping {
+    /// Spotify files EPs as singles, so those are told apart by their track count
+    fn default() -> Self {
+        Self {
+            album: SpotifyReleaseType::LP,
+            single: SpotifyReleaseType::SingleOrEP,
+            compilation: SpotifyReleaseType::Compilation,
+            appears_on: SpotifyReleaseType::SingleOrEP,
+        }
+    }
+}
+
+/// What to put at the start of a formatted release to show its type at a glance (like an emoji), if anything, for each type
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseTypeIndicators {
+    pub single: Option<String>,
+    pub ep: Option<String>,
+    pub lp: Option<String>,
+    pub compilation: Option<String>,
+    pub remixes: Option<String>,
+    pub video: Option<String>,
+    /// For releases of any type not listed above (like one given in an override).
+    /// When this isn't set but any of the others are, [`DEFAULT_OTHER_INDICATOR`] is used so those releases don't stand out by having none
+    pub other: Option<String>,
+}
+
+pub const DEFAULT_OTHER_INDICATOR: &str = "🎶";
+
+impl ReleaseTypeIndicators {
+    /// The indicator for a release of a type not listed, if indicators are in use at all
+    pub fn other_or_default(&self) -> Option<&str> {
+        let Self {
+            single,
+            ep,
+            lp,
+            compilation,
+            remixes,
+            video,
+            other,
+        } = self;
+
+        other.as_deref().or_else(|| {
+            [single, ep, lp, compilation, remixes, video]
+                .into_iter()
+                .any(Option::is_some)
+                .then_some(DEFAULT_OTHER_INDICATOR)
+        })
+    }
+}
+
+/// Which hosts the bot is willing to fetch pages from when it has to scrape a release,
+/// since otherwise it fetches whatever URL it's given
+#[derive(Debug, Clone, Default)]
+pub struct HostPolicy {
+    /// Only these hosts (and their subdomains) can be fetched from, if fetching is limited at all
+    pub allowed_hosts: Option<Vec<String>>,
+    /// These hosts (and their subdomains) can never be fetched from, even if they're allowed
+    pub denied_hosts: Vec<String>,
+}
+
+impl HostPolicy {
+    /// Whether a page can be fetched from this host, which only http and https URLs can be
+    pub fn allows(&self, scheme: &str, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let matches = |entry: &String| {
+            let entry = entry.trim_end_matches('.').to_ascii_lowercase();
+            host == entry
+                || host
+                    .strip_suffix(&entry)
+                    .is_some_and(|subdomains| subdomains.ends_with('.'))
+        };
+
+        let is_web = scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https");
+        let is_allowed = self
+            .allowed_hosts
+            .as_ref()
+            .is_none_or(|allowed_hosts| allowed_hosts.iter().any(matches));
+        let is_denied = self.denied_hosts.iter().any(matches);
+
+        is_web && is_allowed && !is_denied
+    }
+}
+
+/// Behavior that operators can tune for their instance
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Whether releases found somewhere other than Spotify should also be looked up on Spotify, so a Spotify link can be posted alongside the original
+    pub spotify_cross_search: bool,
+    /// How similar (from 0 to 1) a Spotify search result's title and artist have to be to the original release's before it's trusted to be the same release
+    pub spotify_cross_search_threshold: f64,
+    /// The country (as a two-letter code, like `US`) whose Spotify catalog albums are looked up in, if any in particular.
+    /// Albums that aren't available there are looked up again in any market
+    pub spotify_market: Option<String>,
+
+    /// Whether to register commands with Discord on startup even when the registered ones already match
+    pub force_command_registration: bool,
+    /// The only commands registered with Discord and answered, by name, or all of them when this isn't given.
+    /// None at all leaves an instance that only answers Discord's PINGs (like while trying out an interactions endpoint URL),
+    /// and clears any commands that were registered before.
+    /// An Activity's Entry Point command is never cleared, since Discord doesn't allow overwriting the commands without it
+    pub enabled_commands: Option<AHashSet<String>>,
+
+    /// Shown at the bottom of every error message, to tell people where to report problems
+    pub report_footer_text: String,
+    /// Where the title of every error message links to, if anywhere
+    pub report_url: Option<String>,
+
+    /// How often commands can be used in each server, if limited at all
+    pub guild_rate_limit: Option<RateLimit>,
+    /// How often each user can use commands, if limited at all
+    pub user_rate_limit: Option<RateLimit>,
+
+    pub preferred_image_size: ImageSize,
+
+    /// Whether to explain what to do with the formatted release, which people who have used the bot before don't need
+    pub show_helper_text: bool,
+
+    /// Which of a release's record labels to show
+    pub label_display: LabelDisplay,
+    /// Where to credit an artist who's both a main artist and featured (or remixing)
+    pub overlapping_credits: OverlappingCredits,
+    /// What to do with a release that would be shown without any artist
+    pub missing_artist: MissingArtist,
+
+    /// Whether to show the record label in the preview's footer as plain text, instead of in the brackets after the title,
+    /// for servers that want it there but out of the way
+    pub label_in_footer: bool,
+
+    /// Whether to mark releases with explicit tracks with "🅴", when the data source says so
+    pub show_explicit_marker: bool,
+
+    /// Whether to say how long ago releases came out (or how soon they will) after their dates, like "(3 days ago)",
+    /// when the exact day is known
+    pub show_relative_date: bool,
+
+    pub track_count_thresholds: TrackCountThresholds,
+    pub spotify_album_type_mapping: SpotifyAlbumTypeMapping,
+    pub release_type_indicators: ReleaseTypeIndicators,
+
+    /// Whether Spotify albums that look like compilations (by "Various Artists", or with a title like "Label Sampler") are labelled as compilations,
+    /// since Spotify sometimes calls those plain albums
+    pub detect_spotify_compilations: bool,
+
+    /// Whether pages about a music video (rather than a release) can be formatted too, with whatever a video says about itself
+    pub recognize_music_videos: bool,
+
+    pub spotify_credential_check: SpotifyCredentialCheck,
+
+    /// How many additional artists (the ones credited on tracks but not the whole release) to list before summarizing the rest as "and N more", if there's a limit at all
+    pub max_additional_artists: Option<usize>,
+    /// Whether to credit each track's artists on a line of its own (like "3. Title — Artist") instead of listing them all after "with"
+    pub per_track_credits: bool,
+
+    /// Whether to mention the language of releases that aren't in `primary_language`
+    pub show_language: bool,
+    /// The language most releases posted in this instance are in, as either a code (like `en`) or a name (like `English`)
+    pub primary_language: String,
+
+    /// Whether to show releases' barcodes (UPC or EAN) in the preview's footer, when the data source has them
+    pub show_upc: bool,
+
+    /// Whether to list the genres of releases, when the data source has any
+    pub show_genres: bool,
+    /// Whether genres that have a role of the same name should ping it
+    pub ping_genre_roles: bool,
+
+    /// The only servers commands are handled in, for instances that aren't meant for everyone.
+    /// Commands are handled in every server (and outside of them) when this is empty
+    pub allowed_guilds: AHashSet<Id<GuildMarker>>,
+
+    /// Artists whose names have ", " in them (like "Tyler, The Creator"), so they aren't split into several artists,
+    /// on top of the well-known ones the bot already knows about
+    pub comma_artist_names: Vec<String>,
+
+    /// Roles to ping for Spotify artists by their IDs, which takes precedence over pinging the role named after the artist
+    pub artist_roles: Vec<ArtistRole>,
+
+    /// Who besides the bot's owners can use the `debug` command, if anyone
+    pub debug_role_id: Option<Id<RoleMarker>>,
+
+    /// Who can have `new-release` post in a channel that they can't send messages in themselves, if anyone
+    pub direct_post_role_id: Option<Id<RoleMarker>>,
+
+    /// Who besides people who can manage roles can use the `check-roles` command, if anyone
+    pub check_roles_role_id: Option<Id<RoleMarker>>,
+
+    pub host_policy: HostPolicy,
+
+    /// How many releases can be fetched at once across every interaction, so that a rush of them doesn't open a connection each
+    pub max_concurrent_fetches: usize,
+
+    /// How long a server's roles are reused for before they're fetched again, if they're reused at all.
+    /// `refresh-roles` fetches them again sooner, for when they've just been edited
+    pub roles_cache_ttl: Option<Duration>,
+
+    /// Where to keep how many releases have been formatted in each server, for the `stats` command, if anywhere.
+    /// It has to be somewhere that lasts between restarts for the counts to, too
+    pub usage_stats_path: Option<PathBuf>,
+
+    /// Put before every release posted directly in a channel (like a ping for a "new releases" role), on a line of its own
+    pub direct_post_prefix: String,
+    /// Put after every release posted directly in a channel, on a line of its own
+    pub direct_post_suffix: String,
+}
+
+impl Config {
+    /// The configured Spotify market, which `init` checks is valid so it can be trusted afterwards
+    pub fn spotify_country(&self) -> Result<Option<rspotify::model::Country>, SpotifyMarketError> {
+        self.spotify_market
+            .as_deref()
+            .map(|market| {
+                // rspotify only knows how to read countries as their codes in JSON
+                let code = serde_json::Value::String(market.trim().to_uppercase());
+                serde_json::from_value(code)
+                    .ok()
+                    .context(SpotifyMarketSnafu { market })
+            })
+            .transpose()
+    }
+
+    /// Makes sure the direct post prefix and suffix can't break the formatting of the release they go around
+    pub fn validate_direct_post_wrappers(&self) -> Result<(), DirectPostWrapperError> {
+        for (which, wrapper) in [
+            ("prefix", &self.direct_post_prefix),
+            ("suffix", &self.direct_post_suffix),
+        ] {
+            ensure!(!wrapper.contains("```"), CodeFenceSnafu { which });
+
+            let length = wrapper.chars().count();
+            ensure!(
+                length <= DIRECT_POST_WRAPPER_MAX_LENGTH,
+                TooLongSnafu { which, length }
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            spotify_cross_search: false,
+            spotify_cross_search_threshold: 0.9,
+            spotify_market: None,
+            force_command_registration: false,
+            enabled_commands: None,
+            report_footer_text: "Please report this to whoever runs this bot!".to_owned(),
+            report_url: None,
+            guild_rate_limit: None,
+            user_rate_limit: None,
+            preferred_image_size: ImageSize::Largest,
+            show_helper_text: true,
+            label_display: LabelDisplay::WhenRole,
+            overlapping_credits: OverlappingCredits::Feature,
+            missing_artist: MissingArtist::Warn,
+            label_in_footer: false,
+            show_explicit_marker: false,
+            show_relative_date: false,
+            track_count_thresholds: TrackCountThresholds::default(),
+            spotify_album_type_mapping: SpotifyAlbumTypeMapping::default(),
+            release_type_indicators: ReleaseTypeIndicators::default(),
+            detect_spotify_compilations: false,
+            recognize_music_videos: false,
+            spotify_credential_check: SpotifyCredentialCheck::Skip,
+            max_additional_artists: None,
+            per_track_credits: false,
+            show_language: false,
+            primary_language: "en".to_owned(),
+            show_upc: false,
+            show_genres: false,
+            ping_genre_roles: false,
+            allowed_guilds: AHashSet::new(),
+            comma_artist_names: Vec::new(),
+            artist_roles: Vec::new(),
+            debug_role_id: None,
+            direct_post_role_id: None,
+            check_roles_role_id: None,
+            host_policy: HostPolicy::default(),
+            max_concurrent_fetches: 16,
+            roles_cache_ttl: None,
+            usage_stats_path: None,
+            direct_post_prefix: String::new(),
+            direct_post_suffix: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only_bandcamp() -> HostPolicy {
+        HostPolicy {
+            allowed_hosts: Some(vec!["bandcamp.com".to_owned()]),
+            denied_hosts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn allowed_hosts_include_their_subdomains() {
+        let host_policy = only_bandcamp();
+
+        assert!(host_policy.allows("https", "bandcamp.com"));
+        assert!(host_policy.allows("https", "harbor.bandcamp.com"));
+        assert!(host_policy.allows("HTTP", "Harbor.Bandcamp.com"));
+        assert!(!host_policy.allows("https", "evilbandcamp.com"));
+        assert!(!host_policy.allows("https", "bandcamp.com.evil.example"));
+    }
+
+    #[test]
+    fn trailing_dots_are_the_same_host() {
+        let host_policy = only_bandcamp();
+
+        assert!(host_policy.allows("https", "harbor.bandcamp.com."));
+        assert!(
+            HostPolicy {
+                allowed_hosts: Some(vec!["bandcamp.com.".to_owned()]),
+                denied_hosts: Vec::new(),
+            }
+            .allows("https", "harbor.bandcamp.com")
+        );
+    }
+
+    #[test]
+    fn only_web_pages_can_be_fetched() {
+        assert!(HostPolicy::default().allows("https", "example.com"));
+        assert!(!HostPolicy::default().allows("ftp", "example.com"));
+        assert!(!only_bandcamp().allows("file", "bandcamp.com"));
+    }
+
+    #[test]
+    fn denied_hosts_win_over_allowed_ones() {
+        let host_policy = HostPolicy {
+            denied_hosts: vec!["evil.bandcamp.com".to_owned()],
+            ..only_bandcamp()
+        };
+
+        assert!(host_policy.allows("https", "harbor.bandcamp.com"));
+        assert!(!host_policy.allows("https", "evil.bandcamp.com"));
+        assert!(!host_policy.allows("https", "www.evil.bandcamp.com"));
+    }
+
+    #[test]
+    fn artist_roles_parse() {
+        let artist_role = " 3 : 0aaaaaaaaaaaaaaaaaaaaa : 30 "
+            .parse::<ArtistRole>()
+            .unwrap();
+
+        assert_eq!(
+            artist_role,
+            ArtistRole {
+                guild_id: Id::new(3),
+                spotify_artist_id: "0aaaaaaaaaaaaaaaaaaaaa".to_owned(),
+                role_id: Id::new(30),
+            }
+        );
+    }
+
+    #[test]
+    fn artist_roles_need_every_part() {
+        assert!(matches!(
+            "3:0aaaaaaaaaaaaaaaaaaaaa".parse::<ArtistRole>(),
+            Err(ParseArtistRoleError::WrongNumberOfParts)
+        ));
+        assert!(matches!(
+            "3:0aaaaaaaaaaaaaaaaaaaaa:30:40".parse::<ArtistRole>(),
+            Err(ParseArtistRoleError::WrongNumberOfParts)
+        ));
+        assert!(matches!(
+            "3: :30".parse::<ArtistRole>(),
+            Err(ParseArtistRoleError::SpotifyArtistIdMissing)
+        ));
+        assert!(matches!(
+            "server:0aaaaaaaaaaaaaaaaaaaaa:30".parse::<ArtistRole>(),
+            Err(ParseArtistRoleError::GuildIdInvalid { .. })
+        ));
+        assert!(matches!(
+            "3:0aaaaaaaaaaaaaaaaaaaaa:role".parse::<ArtistRole>(),
+            Err(ParseArtistRoleError::RoleIdInvalid { .. })
+        ));
+    }
+
+    #[test]
+    fn direct_post_wrappers_are_empty_by_default() {
+        assert!(Config::default().validate_direct_post_wrappers().is_ok());
+    }
+
+    #[test]
+    fn direct_post_wrappers_cant_have_code_fences() {
+        let config = Config {
+            direct_post_suffix: "```".to_owned(),
+            ..Config::default()
+        };
+
+        assert!(matches!(
+            config.validate_direct_post_wrappers(),
+            Err(DirectPostWrapperError::CodeFence { which: "suffix" })
+        ));
+    }
+
+    #[test]
+    fn direct_post_wrappers_have_a_length_limit() {
+        let with_prefix = |length| Config {
+            direct_post_prefix: "é".repeat(length),
+            ..Config::default()
+        };
+
+        assert!(
+            with_prefix(DIRECT_POST_WRAPPER_MAX_LENGTH)
+                .validate_direct_post_wrappers()
+                .is_ok()
+        );
+        assert!(matches!(
+            with_prefix(DIRECT_POST_WRAPPER_MAX_LENGTH + 1).validate_direct_post_wrappers(),
+            Err(DirectPostWrapperError::TooLong { which: "prefix", length })
+                if length == DIRECT_POST_WRAPPER_MAX_LENGTH + 1
+        ));
+    }
+
+    #[test]
+    fn spotify_markets_are_country_codes() {
+        let with_market = |market: &str| Config {
+            spotify_market: Some(market.to_owned()),
+            ..Config::default()
+        };
+
+        assert!(matches!(Config::default().spotify_country(), Ok(None)));
+        assert!(matches!(
+            with_market(" us ").spotify_country(),
+            Ok(Some(rspotify::model::Country::UnitedStates))
+        ));
+        assert!(with_market("ZZ").spotify_country().is_err());
+        assert!(with_market("USA").spotify_country().is_err());
+    }
+}
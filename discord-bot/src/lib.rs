@@ -1,28 +1,72 @@
 use rspotify::{ClientCredsSpotify, Credentials};
 use secrecy::{ExposeSecret, SecretString};
-use snafu::{Report, ResultExt, Snafu};
-use std::{sync::Arc, time::Duration};
-use tokio::{sync::oneshot, time::timeout};
+use snafu::{OptionExt, Report, ResultExt, Snafu, ensure};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Semaphore, time::timeout};
 pub use twilight_http::Client;
+pub use twilight_model::id::{
+    Id,
+    marker::{GuildMarker, RoleMarker},
+};
 pub use twilight_model::{
     application::interaction::Interaction, http::interaction::InteractionResponse,
 };
 use twilight_model::{
-    application::interaction::InteractionType,
+    application::{command::Command, interaction::InteractionType},
     channel::message::MessageFlags,
     http::interaction::InteractionResponseType,
-    id::{Id, marker::ApplicationMarker},
+    id::marker::{ApplicationMarker, UserMarker},
 };
 use twilight_util::builder::InteractionResponseDataBuilder;
 
 mod command;
+mod config;
+mod public_address;
+mod rate_limit;
+mod roles_cache;
+mod usage_stats;
 
+pub use command::UnknownCommandError;
+pub use config::{
+    ArtistRole, Config, DirectPostWrapperError, HostPolicy, ImageSize, LabelDisplay, MissingArtist,
+    OverlappingCredits, ParseArtistRoleError, ReleaseTypeIndicators, SpotifyAlbumTypeMapping,
+    SpotifyCredentialCheck, SpotifyMarketError, SpotifyReleaseType, TrackCountThresholds,
+};
+pub use rate_limit::RateLimit;
+use rate_limit::RateLimiter;
+use roles_cache::RolesCache;
+use usage_stats::UsageStats;
+pub use usage_stats::UsageStatsError;
+
+/// Everything interactions are handled with, made once by [`init`] and then cloned for each interaction.
+///
+/// The clients are behind [`Arc`]s so that every clone shares the same ones (and their connection pools and tokens),
+/// and the Discord client is the only thing that holds on to the Discord token, so nothing needs the raw token after [`init`]
 #[derive(Debug, Clone)]
 pub struct State {
     pub discord_client: Arc<Client>,
     pub discord_application_id: Id<ApplicationMarker>,
+    /// Whoever owns the Discord application, either directly or as a member of the team that owns it
+    pub discord_application_owner_ids: Arc<[Id<UserMarker>]>,
+
+    /// Missing when Spotify integration is disabled because the credentials didn't work at startup
+    pub spotify_client: Option<Arc<ClientCredsSpotify>>,
 
-    pub spotify_client: Arc<ClientCredsSpotify>,
+    /// Used for every outbound request to a web page, so that the timeout and user agent are configured in one place
+    pub http_client: Arc<reqwest::Client>,
+    /// Shared by every interaction, so there are only ever `config.max_concurrent_fetches` releases being fetched at once
+    pub fetch_permits: Arc<Semaphore>,
+
+    /// Shared by every interaction, so a server's roles are only fetched again once `config.roles_cache_ttl` has passed
+    pub roles_cache: Arc<RolesCache>,
+
+    /// Missing when there's nowhere configured to keep usage stats
+    pub usage_stats: Option<Arc<UsageStats>>,
+
+    pub config: Arc<Config>,
 }
 
 #[derive(Debug, Snafu)]
@@ -34,30 +78,188 @@ pub enum InitError {
         source: twilight_http::response::DeserializeBodyError,
     },
 
+    #[snafu(display("the enabled commands aren't all ones this bot has"))]
+    UnknownCommandError { source: UnknownCommandError },
+
+    #[snafu(display("couldn't get the already registered Discord interaction commands"))]
+    GetInteractionCommandsError { source: twilight_http::Error },
+
     #[snafu(display("couldn't set the Discord interaction commands"))]
     SetInteractionCommandsError { source: twilight_http::Error },
     #[snafu(display("couldn't deserialize the returned Discord interaction commands"))]
     DeserializeInteractionCommandsError {
         source: twilight_http::response::DeserializeBodyError,
     },
+
+    #[snafu(display("couldn't authenticate with Spotify (check the client ID and secret)"))]
+    SpotifyAuthError { source: rspotify::ClientError },
+
+    #[snafu(display("couldn't build the HTTP client for fetching web pages"))]
+    HttpClientError { source: reqwest::Error },
+
+    #[snafu(display(
+        "max_concurrent_fetches is 0, which would leave every release waiting for a fetch that can never start"
+    ))]
+    NoConcurrentFetches,
+
+    #[snafu(display("the configured direct post prefix or suffix isn't usable"))]
+    DirectPostWrapperError { source: DirectPostWrapperError },
+
+    #[snafu(display("the configured Spotify market isn't usable"))]
+    SpotifyMarketError { source: SpotifyMarketError },
+
+    #[snafu(display("couldn't load the usage stats"))]
+    UsageStatsError { source: UsageStatsError },
+}
+
+const HTTP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// The same as reqwest's default
+const HTTP_MAX_REDIRECTS: usize = 10;
+
+/// How long Discord accepts an interaction's token for, so a deferred response has to be sent before then
+pub(crate) const INTERACTION_TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+/// Taken off of [`INTERACTION_TOKEN_LIFETIME`] to allow for the time Discord took to send the interaction here
+pub(crate) const INTERACTION_TOKEN_LEEWAY: Duration = Duration::from_secs(10);
+
+/// Follows redirects like reqwest does by default, except to hosts that the host policy doesn't allow
+/// or to IP addresses that aren't public (which [`public_address::PublicOnlyResolver`] never sees),
+/// so that a page on an allowed host can't be used to reach a disallowed one
+fn redirect_policy(host_policy: HostPolicy) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        let url = attempt.url();
+        let allowed = url
+            .host_str()
+            .is_some_and(|host| host_policy.allows(url.scheme(), host));
+
+        if !allowed {
+            let message = format!("redirected to {url}, which isn't allowed to be fetched from");
+            attempt.error(message)
+        } else if let Some(host) = url
+            .host_str()
+            .filter(|host| public_address::is_non_public_ip_host(host))
+        {
+            let error = public_address::NonPublicAddressSnafu { host }.build();
+            attempt.error(error)
+        } else if attempt.previous().len() >= HTTP_MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// The client ID and secret of the Spotify app to use
+#[derive(Debug)]
+pub struct SpotifyCredentials {
+    pub client_id: String,
+    pub client_secret: SecretString,
+}
+
+/// Where to reach Spotify's API and its accounts service (for tokens),
+/// which only need changing to go through a proxy or to talk to a mock of Spotify
+#[derive(Debug, Clone)]
+pub struct SpotifyApiUrls {
+    pub api_base_url: String,
+    pub auth_base_url: String,
+}
+
+impl Default for SpotifyApiUrls {
+    fn default() -> Self {
+        Self {
+            api_base_url: rspotify::DEFAULT_API_BASE_URL.to_owned(),
+            auth_base_url: rspotify::DEFAULT_AUTH_BASE_URL.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum SpotifyCredentialsError {
+    #[snafu(display(
+        "the Spotify credentials were given both combined and separately, so there's no telling which to use"
+    ))]
+    BothStyles,
+
+    #[snafu(display(
+        "the Spotify credentials weren't given, either combined or as a client ID and secret"
+    ))]
+    Missing,
+
+    #[snafu(display("only the Spotify client ID was given, without its secret"))]
+    ClientSecretMissing,
+
+    #[snafu(display("only the Spotify client secret was given, without its ID"))]
+    ClientIdMissing,
+
+    #[snafu(display(
+        "the combined Spotify credentials have to be the client ID and secret separated by a colon (like `client_id:client_secret`)"
+    ))]
+    CombinedMissingColon,
+}
+
+impl SpotifyCredentials {
+    /// From a single `client_id:client_secret` value, which is easier to keep in some secret managers
+    pub fn from_combined(combined: &SecretString) -> Result<Self, SpotifyCredentialsError> {
+        let (client_id, client_secret) = combined
+            .expose_secret()
+            .split_once(':')
+            .context(CombinedMissingColonSnafu)?;
+        ensure!(!client_id.is_empty(), ClientIdMissingSnafu);
+        ensure!(!client_secret.is_empty(), ClientSecretMissingSnafu);
+
+        Ok(Self {
+            client_id: client_id.to_owned(),
+            client_secret: SecretString::from(client_secret.to_owned()),
+        })
+    }
+
+    /// From whichever way they were given (separately, or combined as `client_id:client_secret`),
+    /// as long as it's exactly one way and it's complete
+    pub fn from_either(
+        client_id: Option<String>,
+        client_secret: Option<SecretString>,
+        combined: Option<SecretString>,
+    ) -> Result<Self, SpotifyCredentialsError> {
+        match (client_id, client_secret, combined) {
+            (None, None, Some(combined)) => Self::from_combined(&combined),
+            (Some(client_id), Some(client_secret), None) => Ok(Self {
+                client_id,
+                client_secret,
+            }),
+            (_, _, Some(_)) => BothStylesSnafu.fail(),
+            (Some(_), None, None) => ClientSecretMissingSnafu.fail(),
+            (None, Some(_), None) => ClientIdMissingSnafu.fail(),
+            (None, None, None) => MissingSnafu.fail(),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct InitArgs {
     pub discord_token: SecretString,
 
-    pub spotify_client_id: String,
-    pub spotify_client_secret: SecretString,
+    pub spotify_credentials: SpotifyCredentials,
+    pub spotify_api_urls: SpotifyApiUrls,
+
+    pub config: Config,
 }
 
 #[tracing::instrument]
 pub async fn init(
     InitArgs {
         discord_token,
-        spotify_client_id,
-        spotify_client_secret,
+        spotify_credentials,
+        spotify_api_urls,
+        config,
     }: InitArgs,
 ) -> Result<(InteractionHandler, State), InitError> {
+    ensure!(config.max_concurrent_fetches > 0, NoConcurrentFetchesSnafu);
+
+    config
+        .validate_direct_post_wrappers()
+        .context(DirectPostWrapperSnafu)?;
+    config.spotify_country().context(SpotifyMarketSnafu)?;
+
     let discord_client = Client::new(discord_token.expose_secret().into());
 
     let current_application = discord_client
@@ -69,10 +271,28 @@ pub async fn init(
         .context(DeserializeCurrentApplicationSnafu)?;
 
     let discord_application_id = current_application.id;
+    let discord_application_owner_ids = Arc::from_iter(
+        current_application
+            .owner
+            .iter()
+            .map(|owner| owner.id)
+            .chain(
+                current_application
+                    .team
+                    .iter()
+                    .flat_map(|team| team.members.iter().map(|member| member.user.id)),
+            ),
+    );
 
     let discord_interaction_client = discord_client.interaction(discord_application_id);
 
-    let all_commands = command::all();
+    let all_commands =
+        command::enabled(config.enabled_commands.as_ref()).context(UnknownCommandSnafu)?;
+    if all_commands.is_empty() {
+        tracing::warn!(
+            "no commands were chosen, so only Discord's PINGs will be answered (and any commands registered before will be cleared, except for an Entry Point)"
+        );
+    }
 
     let discord_commands = Vec::from_iter(
         all_commands
@@ -80,39 +300,136 @@ pub async fn init(
             .map(|(command, _handler)| (*command).to_owned()),
     );
 
-    let _returned_commands = discord_interaction_client
-        .set_global_commands(&discord_commands)
+    let registered_commands = discord_interaction_client
+        .global_commands()
         .await
-        .context(SetInteractionCommandsSnafu)?
+        .context(GetInteractionCommandsSnafu)?
         .models()
         .await
         .context(DeserializeInteractionCommandsSnafu)?;
 
-    let command_router = command::CommandRouter::from_iter(all_commands);
+    let drift = command::drift(&discord_commands, &registered_commands);
+
+    if config.force_command_registration || !drift.is_empty() {
+        tracing::info!(?drift, "registering commands with Discord");
 
-    let interaction_handler = InteractionHandler { command_router };
+        // the overwrite has to keep the Entry Point, or Discord refuses all of it
+        let discord_commands = Vec::from_iter(
+            discord_commands.iter().cloned().chain(
+                registered_commands
+                    .iter()
+                    .filter(|command| command::is_entry_point(command))
+                    .cloned(),
+            ),
+        );
+        let _returned_commands = discord_interaction_client
+            .set_global_commands(&discord_commands)
+            .await
+            .context(SetInteractionCommandsSnafu)?
+            .models()
+            .await
+            .context(DeserializeInteractionCommandsSnafu)?;
+    } else {
+        tracing::info!("commands registered with Discord are already up to date");
+    }
+
+    let interaction_handler = InteractionHandler::with_commands(&config, all_commands);
 
-    let spotify_credentials =
-        Credentials::new(&spotify_client_id, spotify_client_secret.expose_secret());
-    let spotify_client = ClientCredsSpotify::new(spotify_credentials);
+    let spotify_credentials = Credentials::new(
+        &spotify_credentials.client_id,
+        spotify_credentials.client_secret.expose_secret(),
+    );
+    let spotify_client = ClientCredsSpotify::with_config(
+        spotify_credentials,
+        rspotify::Config {
+            api_base_url: spotify_api_urls.api_base_url,
+            auth_base_url: spotify_api_urls.auth_base_url,
+            ..rspotify::Config::default()
+        },
+    );
 
-    let discord_client = Arc::new(discord_client);
-    let spotify_client = Arc::new(spotify_client);
+    let spotify_client = match config.spotify_credential_check {
+        SpotifyCredentialCheck::Skip => Some(spotify_client),
+        SpotifyCredentialCheck::Required => {
+            spotify_client
+                .request_token()
+                .await
+                .context(SpotifyAuthSnafu)?;
+            Some(spotify_client)
+        }
+        SpotifyCredentialCheck::Optional => match spotify_client.request_token().await {
+            Ok(()) => Some(spotify_client),
+            Err(error) => {
+                tracing::warn!(
+                    error = %Report::from_error(error),
+                    "couldn't authenticate with Spotify, so Spotify integration is disabled"
+                );
+                None
+            }
+        },
+    };
 
-    let state = State {
+    let state = State::new(
         discord_client,
         discord_application_id,
+        discord_application_owner_ids,
         spotify_client,
-    };
+        config,
+    )?;
 
     Ok((interaction_handler, state))
 }
 
+impl State {
+    /// Everything [`init`] makes once it knows who the Discord application is, without asking Discord or Spotify anything itself
+    pub fn new(
+        discord_client: Client,
+        discord_application_id: Id<ApplicationMarker>,
+        discord_application_owner_ids: Arc<[Id<UserMarker>]>,
+        spotify_client: Option<ClientCredsSpotify>,
+        config: Config,
+    ) -> Result<Self, InitError> {
+        let http_client = reqwest::Client::builder()
+            .user_agent(HTTP_USER_AGENT)
+            .timeout(HTTP_TIMEOUT)
+            .redirect(redirect_policy(config.host_policy.clone()))
+            .dns_resolver(Arc::new(public_address::PublicOnlyResolver))
+            .build()
+            .context(HttpClientSnafu)?;
+        let usage_stats = config
+            .usage_stats_path
+            .clone()
+            .map(UsageStats::load)
+            .transpose()
+            .context(UsageStatsSnafu)?;
+
+        Ok(Self {
+            discord_client: Arc::new(discord_client),
+            discord_application_id,
+            discord_application_owner_ids,
+            spotify_client: spotify_client.map(Arc::new),
+            http_client: Arc::new(http_client),
+            fetch_permits: Arc::new(Semaphore::new(config.max_concurrent_fetches)),
+            roles_cache: Arc::new(RolesCache::new(config.roles_cache_ttl)),
+            usage_stats: usage_stats.map(Arc::new),
+            config: Arc::new(config),
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct InteractionHandler {
     command_router: command::CommandRouter,
+
+    guild_rate_limiter: Arc<RateLimiter<Id<GuildMarker>>>,
+    user_rate_limiter: Arc<RateLimiter<Id<UserMarker>>>,
 }
 
+/// Shown instead of nothing at all when a command handler panics
+#[derive(Debug, Snafu)]
+#[snafu(display("the command crashed while handling this"))]
+struct CommandPanicked;
+
 #[derive(Debug, Clone, Snafu)]
 pub enum InteractionHandleError {
     #[snafu(display("error handling command"))]
@@ -120,6 +437,80 @@ pub enum InteractionHandleError {
 }
 
 impl InteractionHandler {
+    /// Routes to the commands that are enabled in the config, with the configured rate limits
+    pub fn new(config: &Config) -> Result<Self, UnknownCommandError> {
+        let commands = command::enabled(config.enabled_commands.as_ref())?;
+
+        Ok(Self::with_commands(config, commands))
+    }
+
+    /// Routes to only `commands`, so a command that isn't registered isn't run either
+    /// (even when Discord sends it anyway, like when it's still registered from before)
+    fn with_commands(
+        config: &Config,
+        commands: Vec<(&'static Command, command::ArcedHandler)>,
+    ) -> Self {
+        Self {
+            command_router: command::CommandRouter::from_iter(commands),
+            guild_rate_limiter: Arc::new(RateLimiter::new(config.guild_rate_limit)),
+            user_rate_limiter: Arc::new(RateLimiter::new(config.user_rate_limit)),
+        }
+    }
+
+    /// Commands used outside of a server are only allowed when every server is
+    fn in_allowed_guild(config: &Config, interaction: &Interaction) -> bool {
+        config.allowed_guilds.is_empty()
+            || interaction
+                .guild_id
+                .is_some_and(|guild_id| config.allowed_guilds.contains(&guild_id))
+    }
+
+    /// Only uses up the user's allowance once the server's has been spared, so one doesn't drain without the other being used
+    fn within_rate_limits(&self, interaction: &Interaction) -> bool {
+        let within_guild_limit = interaction
+            .guild_id
+            .is_none_or(|guild_id| self.guild_rate_limiter.try_acquire(guild_id));
+
+        within_guild_limit
+            && interaction
+                .author_id()
+                .is_none_or(|user_id| self.user_rate_limiter.try_acquire(user_id))
+    }
+
+    /// The response to give instead of handling the interaction, when it's used outside of the allowed servers or past the rate limits.
+    /// Components are held to this as well as commands, so a button can't be used to get around either
+    fn refusal(&self, config: &Config, interaction: &Interaction) -> Option<InteractionResponse> {
+        if !Self::in_allowed_guild(config, interaction) {
+            tracing::info!(guild_id = ?interaction.guild_id, "used outside of the allowed servers");
+
+            return Some(InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .content("Sorry, this instance of the bot is private, so it only works in the servers it was set up for.")
+                        .flags(MessageFlags::EPHEMERAL)
+                        .build(),
+                ),
+            });
+        }
+
+        if !self.within_rate_limits(interaction) {
+            tracing::info!(guild_id = ?interaction.guild_id, "rate limited");
+
+            return Some(InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .content("Slow down! Too many commands have been used here recently, so try again in a bit.")
+                        .flags(MessageFlags::EPHEMERAL)
+                        .build(),
+                ),
+            });
+        }
+
+        None
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn handle(
         &self,
@@ -132,44 +523,103 @@ impl InteractionHandler {
                 data: None,
             }),
             InteractionType::ApplicationCommand => {
-                let interaction_token = interaction.token.clone();
+                if let Some(refusal) = self.refusal(&state.config, &interaction) {
+                    return Ok(refusal);
+                }
 
-                let (tx, rx) = oneshot::channel();
+                let received_at = Instant::now();
+                let interaction_token = interaction.token.clone();
 
                 let command_router = self.command_router.clone();
+                let config = state.config.clone();
                 let discord_client = state.discord_client.clone();
                 let discord_application_id = state.discord_application_id;
 
-                let response_task = tokio::spawn(async move {
-                    let ret = command_router.handle(state, interaction).await;
-                    tx.send(ret).unwrap();
-                });
+                // spawned so that a panic in a command handler ends up as a `JoinError` here instead of taking down the whole request
+                let mut response_task =
+                    tokio::spawn(async move { command_router.handle(state, interaction).await });
 
-                match timeout(Duration::from_millis(500), response_task).await {
-                    Ok(in_time) => {
-                        in_time.unwrap();
-                        rx.await.unwrap().context(CommandHandleSnafu)
+                match timeout(Duration::from_millis(500), &mut response_task).await {
+                    Ok(Ok(ret)) => ret
+                        .map(command::CommandResponse::into_interaction_response)
+                        .context(CommandHandleSnafu),
+                    Ok(Err(join_error)) => {
+                        tracing::error!(error = %Report::from_error(join_error), "the command handler panicked");
+
+                        Ok(command::error_response(CommandPanicked, &config))
                     }
                     Err(_) => {
                         tokio::spawn(async move {
-                            let response_res = rx.await.unwrap();
-
-                            match response_res {
-                                Ok(response) => discord_client
-                                    .interaction(discord_application_id)
-                                    .update_response(&interaction_token)
-                                    .content(
-                                        response.data.as_ref().expect("TODO").content.as_deref(),
-                                    )
-                                    .embeds(response.data.as_ref().expect("TODO").embeds.as_deref())
-                                    .await
-                                    .unwrap(),
-                                Err(handling_error) => discord_client
-                                    .interaction(discord_application_id)
-                                    .update_response(&interaction_token)
-                                    .content(Some(&Report::from_error(handling_error).to_string()))
-                                    .await
-                                    .unwrap(),
+                            let interaction_client =
+                                discord_client.interaction(discord_application_id);
+
+                            let response = response_task.await;
+
+                            // Discord would only refuse anything sent with an expired token, so there's nobody left to tell
+                            let elapsed = received_at.elapsed();
+                            if elapsed > INTERACTION_TOKEN_LIFETIME - INTERACTION_TOKEN_LEEWAY {
+                                tracing::error!(
+                                    ?elapsed,
+                                    "the command took so long that the interaction token has likely expired, so its response is being dropped"
+                                );
+                                return;
+                            }
+
+                            // errors are sent as a followup of their own instead of taking the placeholder's place,
+                            // so they stand out from a response that just took a while
+                            let error_embeds = match response {
+                                Ok(Ok(command::CommandResponse::Failed(response))) => response
+                                    .data
+                                    .and_then(|data| data.embeds)
+                                    .unwrap_or_default(),
+                                Ok(Ok(command::CommandResponse::Succeeded(response))) => {
+                                    // a response without data just clears the placeholder
+                                    let data = response.data.unwrap_or_default();
+                                    let updated = interaction_client
+                                        .update_response(&interaction_token)
+                                        .content(data.content.as_deref())
+                                        .embeds(data.embeds.as_deref())
+                                        .components(data.components.as_deref())
+                                        .await;
+
+                                    if let Err(error) = updated {
+                                        tracing::error!(
+                                            error = %Report::from_error(error),
+                                            "couldn't send the deferred response to Discord"
+                                        );
+                                    }
+                                    return;
+                                }
+                                Ok(Err(handling_error)) => {
+                                    vec![command::error_embed(handling_error, &config)]
+                                }
+                                Err(join_error) => {
+                                    tracing::error!(error = %Report::from_error(join_error), "the command handler panicked");
+
+                                    vec![command::error_embed(CommandPanicked, &config)]
+                                }
+                            };
+
+                            let followed_up = interaction_client
+                                .create_followup(&interaction_token)
+                                .embeds(&error_embeds)
+                                .flags(MessageFlags::EPHEMERAL)
+                                .await;
+                            if let Err(error) = followed_up {
+                                tracing::error!(
+                                    error = %Report::from_error(error),
+                                    "couldn't send the error as a followup to Discord"
+                                );
+                            }
+
+                            // otherwise the placeholder would keep saying the bot is thinking
+                            let deleted =
+                                interaction_client.delete_response(&interaction_token).await;
+                            if let Err(error) = deleted {
+                                tracing::error!(
+                                    error = %Report::from_error(error),
+                                    "couldn't delete the deferred placeholder from Discord"
+                                );
                             }
                         });
 
@@ -185,10 +635,189 @@ impl InteractionHandler {
                     }
                 }
             }
-            InteractionType::MessageComponent => todo!(),
+            InteractionType::MessageComponent => {
+                if let Some(refusal) = self.refusal(&state.config, &interaction) {
+                    return Ok(refusal);
+                }
+
+                Ok(command::handle_component(state, interaction))
+            }
             InteractionType::ApplicationCommandAutocomplete => todo!(),
             InteractionType::ModalSubmit => todo!(),
             _ => todo!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::AHashSet;
+    use twilight_model::guild::Permissions;
+
+    fn secret(secret: &str) -> SecretString {
+        SecretString::from(secret.to_owned())
+    }
+
+    fn from_either(
+        client_id: Option<&str>,
+        client_secret: Option<&str>,
+        combined: Option<&str>,
+    ) -> Result<SpotifyCredentials, SpotifyCredentialsError> {
+        SpotifyCredentials::from_either(
+            client_id.map(ToOwned::to_owned),
+            client_secret.map(secret),
+            combined.map(secret),
+        )
+    }
+
+    #[test]
+    fn spotify_credentials_can_be_given_separately() {
+        let credentials = from_either(Some("id"), Some("secret"), None).unwrap();
+
+        assert_eq!(credentials.client_id, "id");
+        assert_eq!(credentials.client_secret.expose_secret(), "secret");
+    }
+
+    #[test]
+    fn spotify_credentials_can_be_given_combined() {
+        let credentials = from_either(None, None, Some("id:secret:with:colons")).unwrap();
+
+        assert_eq!(credentials.client_id, "id");
+        assert_eq!(
+            credentials.client_secret.expose_secret(),
+            "secret:with:colons"
+        );
+    }
+
+    #[test]
+    fn spotify_credentials_have_to_be_given_one_complete_way() {
+        assert!(matches!(
+            from_either(Some("id"), Some("secret"), Some("id:secret")),
+            Err(SpotifyCredentialsError::BothStyles)
+        ));
+        assert!(matches!(
+            from_either(Some("id"), None, Some("id:secret")),
+            Err(SpotifyCredentialsError::BothStyles)
+        ));
+        assert!(matches!(
+            from_either(Some("id"), None, None),
+            Err(SpotifyCredentialsError::ClientSecretMissing)
+        ));
+        assert!(matches!(
+            from_either(None, Some("secret"), None),
+            Err(SpotifyCredentialsError::ClientIdMissing)
+        ));
+        assert!(matches!(
+            from_either(None, None, None),
+            Err(SpotifyCredentialsError::Missing)
+        ));
+        assert!(matches!(
+            from_either(None, None, Some("id-and-secret")),
+            Err(SpotifyCredentialsError::CombinedMissingColon)
+        ));
+    }
+
+    #[test]
+    fn combined_spotify_credentials_need_both_halves() {
+        assert!(matches!(
+            from_either(None, None, Some(":secret")),
+            Err(SpotifyCredentialsError::ClientIdMissing)
+        ));
+        assert!(matches!(
+            from_either(None, None, Some("id:")),
+            Err(SpotifyCredentialsError::ClientSecretMissing)
+        ));
+        assert!(matches!(
+            from_either(None, None, Some(":")),
+            Err(SpotifyCredentialsError::ClientIdMissing)
+        ));
+    }
+
+    #[test]
+    fn only_allowed_guilds_are_handled_when_there_are_any() {
+        let in_guild = command::tests::member_interaction(Permissions::empty(), &[]);
+        let mut outside_of_guilds = in_guild.clone();
+        outside_of_guilds.guild_id = None;
+
+        let every_guild = Config::default();
+        assert!(InteractionHandler::in_allowed_guild(
+            &every_guild,
+            &in_guild
+        ));
+        assert!(InteractionHandler::in_allowed_guild(
+            &every_guild,
+            &outside_of_guilds
+        ));
+
+        let only_guild = |guild_id| Config {
+            allowed_guilds: AHashSet::from_iter([Id::new(guild_id)]),
+            ..Config::default()
+        };
+        assert!(InteractionHandler::in_allowed_guild(
+            &only_guild(3),
+            &in_guild
+        ));
+        assert!(!InteractionHandler::in_allowed_guild(
+            &only_guild(4),
+            &in_guild
+        ));
+        assert!(!InteractionHandler::in_allowed_guild(
+            &only_guild(3),
+            &outside_of_guilds
+        ));
+    }
+
+    #[tokio::test]
+    async fn components_are_held_to_the_allowed_guilds_too() {
+        let config = Config {
+            allowed_guilds: AHashSet::from_iter([Id::new(4)]),
+            ..Config::default()
+        };
+        let mut interaction = command::tests::member_interaction(Permissions::all(), &[]);
+        interaction.kind = InteractionType::MessageComponent;
+
+        let response = InteractionHandler::new(&config)
+            .unwrap()
+            .handle(command::tests::state(config), interaction)
+            .await
+            .unwrap();
+        let content = response.data.and_then(|data| data.content);
+        assert!(
+            content
+                .as_deref()
+                .is_some_and(|content| content.contains("private")),
+            "{content:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_commands_arent_run_even_if_discord_sends_them() {
+        let config = Config {
+            enabled_commands: Some(AHashSet::from_iter(["version".to_owned()])),
+            ..Config::default()
+        };
+        let interaction_handler = InteractionHandler::new(&config).unwrap();
+
+        let disabled = interaction_handler
+            .handle(
+                command::tests::state(config.clone()),
+                command::tests::command_interaction("batch"),
+            )
+            .await;
+        assert!(matches!(
+            disabled,
+            Err(InteractionHandleError::CommandHandleError {
+                source: command::HandlingError::CommandDoesntExist { name },
+            }) if name == "batch"
+        ));
+
+        let enabled = interaction_handler
+            .handle(
+                command::tests::state(config),
+                command::tests::command_interaction("version"),
+            )
+            .await;
+        assert!(enabled.is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use snafu::Snafu;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// {host} only points to private, loopback, or link-local addresses, which this bot won't fetch from
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub struct NonPublicAddressError {
+    pub host: String,
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    // 100.64.0.0/10, for carrier-grade NAT
+    let is_shared = first == 100 && (second & 0b1100_0000) == 64;
+    // 0.0.0.0/8, which some systems connect to as if it were the machine itself
+    let is_this_network = first == 0;
+    // 240.0.0.0/4 (including the broadcast address), which is reserved
+    let is_reserved = first >= 240;
+
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_multicast()
+        || is_shared
+        || is_this_network
+        || is_reserved)
+}
+
+/// 64:ff9b::/96, where NAT64 puts IPv4 addresses inside of IPv6 ones
+fn nat64_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let [a, b, c, d, e, f, _, _] = ip.segments();
+    let [.., w, x, y, z] = ip.octets();
+
+    ([a, b, c, d, e, f] == [0x64, 0xff9b, 0, 0, 0, 0]).then(|| Ipv4Addr::new(w, x, y, z))
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(ipv4) = ip.to_ipv4_mapped().or_else(|| nat64_ipv4(ip)) {
+        return is_public_ipv4(ipv4);
+    }
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local())
+}
+
+/// Whether fetching from this address can't reach services that are only meant to be reachable from inside the network the bot runs in
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+/// Whether a URL's host is an IP address that isn't public.
+/// Hosts that are names are left to [`PublicOnlyResolver`], because they aren't resolved until they're connected to.
+pub fn is_non_public_ip_host(host: &str) -> bool {
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+
+    host.parse().is_ok_and(|ip| !is_public(ip))
+}
+
+/// Whether a URL is headed straight for an IP address that isn't public, reading its host the way the HTTP client will
+/// (which turns hosts like `127.1`, `2130706433`, and `0x7f.1` into `127.0.0.1`).
+/// Requests to IP addresses never go through [`PublicOnlyResolver`], so this has to be checked before they're sent.
+/// URLs that the HTTP client can't read at all are left for the request itself to fail on
+pub fn is_non_public_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| url.host_str().is_some_and(is_non_public_ip_host))
+}
+
+/// Resolves host names like usual, but leaves out any addresses that aren't public,
+/// failing if there are none left.
+///
+/// Since this is where every connection's addresses come from (including redirects),
+/// a host can't get around it by resolving to a public address when checked and a private one when connected to.
+#[derive(Debug)]
+pub struct PublicOnlyResolver;
+
+impl Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs = tokio::net::lookup_host((host, 0)).await?;
+            let public_addrs = Vec::from_iter(addrs.filter(|addr| is_public(addr.ip())));
+
+            if public_addrs.is_empty() {
+                return Err(NonPublicAddressSnafu { host }.build().into());
+            }
+
+            let addrs: Addrs = Box::new(public_addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether the reason a request failed was that it was headed for an address that isn't public
+pub fn is_non_public_address_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if error.is::<NonPublicAddressError>() {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn loopback_and_metadata_addresses_are_not_public() {
+        assert!(is_non_public_url("http://127.0.0.1/"));
+        assert!(is_non_public_url(
+            "http://169.254.169.254/latest/meta-data/"
+        ));
+        assert!(is_non_public_url("http://[::1]/"));
+        assert!(!is_non_public_url("https://93.184.215.14/"));
+        assert!(!is_non_public_url("https://open.spotify.com/album/x"));
+    }
+
+    #[test]
+    fn unusual_ipv4_spellings_are_read_like_the_http_client_reads_them() {
+        assert!(is_non_public_url("http://127.1/"));
+        assert!(is_non_public_url("http://2130706433/"));
+        assert!(is_non_public_url("http://0x7f.1/"));
+        assert!(is_non_public_url("http://0/"));
+    }
+
+    #[test]
+    fn reserved_ranges_are_not_public() {
+        for ip in [
+            "0.1.2.3",
+            "224.0.0.1",
+            "240.0.0.1",
+            "255.255.255.255",
+            "100.64.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip} should not be public");
+        }
+        for ip in [
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "::ffff:10.0.0.1",
+            "ff02::1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip} should not be public");
+        }
+        assert!(is_public("64:ff9b::808:808".parse().unwrap()));
+        assert!(is_public("8.8.8.8".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn names_that_resolve_to_private_addresses_are_refused() {
+        let result = PublicOnlyResolver
+            .resolve(Name::from_str("localhost").unwrap())
+            .await;
+
+        let error = result
+            .err()
+            .expect("localhost should only resolve to loopback addresses");
+        assert!(is_non_public_address_error(error.as_ref()));
+    }
+}
@@ -0,0 +1,136 @@
+use ahash::AHashMap;
+use std::{
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Allows bursts of up to `capacity` uses, refilling at a steady rate so that `capacity` more are allowed every `per`
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub per: Duration,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// The buckets in use, along with when the ones that have sat long enough to refill were last let go of
+#[derive(Debug)]
+struct Buckets<K> {
+    by_key: AHashMap<K, Bucket>,
+    last_pruned: Instant,
+}
+
+/// A token bucket for each key, kept in memory (so limits reset when the process does)
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    limit: Option<RateLimit>,
+    buckets: Mutex<Buckets<K>>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    /// With no `limit`, everything is always allowed
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(Buckets {
+                by_key: AHashMap::new(),
+                last_pruned: Instant::now(),
+            }),
+        }
+    }
+
+    /// Uses up one token for `key` if it has one to spare
+    pub fn try_acquire(&self, key: K) -> bool {
+        self.try_acquire_at(key, Instant::now())
+    }
+
+    fn try_acquire_at(&self, key: K, now: Instant) -> bool {
+        let Some(RateLimit { capacity, per }) = self.limit else {
+            return true;
+        };
+        let capacity = f64::from(capacity);
+
+        let mut buckets = self.buckets.lock().expect("mutex was poisoned");
+
+        // a bucket that's gone unused for `per` has refilled all the way, which is the same as a new one,
+        // so it's let go of instead of being kept around for every key that was ever seen
+        if now.saturating_duration_since(buckets.last_pruned) >= per {
+            buckets
+                .by_key
+                .retain(|_key, bucket| now.saturating_duration_since(bucket.last_refill) < per);
+            buckets.last_pruned = now;
+        }
+
+        let bucket = buckets.by_key.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let refilled = now
+            .saturating_duration_since(bucket.last_refill)
+            .as_secs_f64()
+            * capacity
+            / per.as_secs_f64();
+        bucket.tokens = (bucket.tokens + refilled).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        capacity: 2,
+        per: Duration::from_secs(10),
+    };
+
+    #[test]
+    fn allows_bursts_up_to_the_capacity_then_refills() {
+        let rate_limiter = RateLimiter::new(Some(LIMIT));
+        let start = Instant::now();
+
+        assert!(rate_limiter.try_acquire_at(1, start));
+        assert!(rate_limiter.try_acquire_at(1, start));
+        assert!(!rate_limiter.try_acquire_at(1, start));
+        // other keys have buckets of their own
+        assert!(rate_limiter.try_acquire_at(2, start));
+
+        assert!(rate_limiter.try_acquire_at(1, start + Duration::from_secs(5)));
+        assert!(!rate_limiter.try_acquire_at(1, start + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn lets_go_of_buckets_that_have_refilled() {
+        let rate_limiter = RateLimiter::new(Some(LIMIT));
+        let start = Instant::now();
+
+        rate_limiter.try_acquire_at(1, start);
+        rate_limiter.try_acquire_at(2, start + Duration::from_secs(5));
+        rate_limiter.try_acquire_at(3, start + Duration::from_secs(12));
+
+        let buckets = rate_limiter.buckets.lock().unwrap();
+        let mut keys = Vec::from_iter(buckets.by_key.keys().copied());
+        keys.sort_unstable();
+        assert_eq!(keys, [2, 3]);
+    }
+
+    #[test]
+    fn allows_everything_without_a_limit() {
+        let rate_limiter = RateLimiter::new(None);
+
+        assert!((0..100).all(|_| rate_limiter.try_acquire(1)));
+    }
+}
@@ -0,0 +1,88 @@
+use ahash::AHashMap;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use twilight_model::{
+    guild::Role,
+    id::{Id, marker::GuildMarker},
+};
+use uncased::Uncased;
+
+/// Every role in a server, by its name (ignoring case)
+pub type RolesMap = BTreeMap<Uncased<'static>, Role>;
+
+#[derive(Debug)]
+struct Entry {
+    roles_map: Arc<RolesMap>,
+    fetched_at: Instant,
+}
+
+/// Each server's roles as they were last fetched, reused until they're `ttl` old,
+/// since roles rarely change but servers can have hundreds of them.
+/// Kept in memory, so it's empty again whenever the process restarts
+#[derive(Debug)]
+pub struct RolesCache {
+    ttl: Option<Duration>,
+    entries: Mutex<AHashMap<Id<GuildMarker>, Entry>>,
+}
+
+impl RolesCache {
+    /// With no `ttl`, nothing is ever reused, so roles are fetched every time
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// The server's roles, if they were fetched recently enough to still be trusted
+    pub fn get(&self, guild_id: Id<GuildMarker>) -> Option<Arc<RolesMap>> {
+        let ttl = self.ttl?;
+
+        let entries = self.entries.lock().expect("mutex was poisoned");
+        let entry = entries.get(&guild_id)?;
+
+        (entry.fetched_at.elapsed() < ttl).then(|| entry.roles_map.clone())
+    }
+
+    pub fn insert(&self, guild_id: Id<GuildMarker>, roles_map: Arc<RolesMap>) {
+        if self.ttl.is_none() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("mutex was poisoned");
+        entries.insert(
+            guild_id,
+            Entry {
+                roles_map,
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_are_reused_only_until_they_expire() {
+        let guild_id = Id::new(1);
+
+        let uncached = RolesCache::new(None);
+        uncached.insert(guild_id, Arc::default());
+        assert!(uncached.get(guild_id).is_none());
+
+        let cached = RolesCache::new(Some(Duration::from_secs(60)));
+        assert!(cached.get(guild_id).is_none());
+        cached.insert(guild_id, Arc::default());
+        assert!(cached.get(guild_id).is_some());
+        assert!(cached.get(Id::new(2)).is_none());
+
+        let expired = RolesCache::new(Some(Duration::ZERO));
+        expired.insert(guild_id, Arc::default());
+        assert!(expired.get(guild_id).is_none());
+    }
+}
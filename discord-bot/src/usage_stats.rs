@@ -0,0 +1,97 @@
+use snafu::{ResultExt, Snafu};
+use std::{collections::BTreeMap, io::ErrorKind, path::PathBuf};
+use tokio::sync::Mutex;
+use twilight_model::id::{Id, marker::GuildMarker};
+
+#[derive(Debug, Snafu)]
+pub enum UsageStatsError {
+    /// couldn't read the usage stats file at {path:?}
+    ReadError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// the usage stats file at {path:?} isn't valid
+    ParseError {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    /// couldn't save the usage stats file at {path:?}
+    WriteError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// How many releases `new-release` has formatted in each server, kept in a JSON file so the counts survive restarts.
+/// Only kept at all when a path for the file is configured, since not every deployment has somewhere lasting to put one
+#[derive(Debug)]
+pub struct UsageStats {
+    path: PathBuf,
+    releases_per_guild: Mutex<BTreeMap<Id<GuildMarker>, u64>>,
+}
+
+impl UsageStats {
+    /// Starts from the counts already in the file, or from nothing when there's no file yet
+    pub fn load(path: PathBuf) -> Result<Self, UsageStatsError> {
+        let releases_per_guild = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).context(ParseSnafu { path: &path })?,
+            Err(error) if error.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(error).context(ReadSnafu { path }),
+        };
+
+        Ok(Self {
+            path,
+            releases_per_guild: Mutex::new(releases_per_guild),
+        })
+    }
+
+    pub async fn releases(&self, guild_id: Id<GuildMarker>) -> u64 {
+        let releases_per_guild = self.releases_per_guild.lock().await;
+
+        releases_per_guild
+            .get(&guild_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Counts another release in the server, saving right away so a restart can't lose it.
+    /// The counts stay locked until the file is saved, so saves can't overtake each other and leave an older count behind
+    pub async fn record_release(&self, guild_id: Id<GuildMarker>) -> Result<(), UsageStatsError> {
+        let mut releases_per_guild = self.releases_per_guild.lock().await;
+        *releases_per_guild.entry(guild_id).or_default() += 1;
+
+        let contents = serde_json::to_string(&*releases_per_guild)
+            .expect("a map of IDs to numbers should always be serializable");
+
+        // written next to the file and then moved over it, so a crash partway through can't leave it half written
+        let temporary_path = self.path.with_extension("tmp");
+        let written = match tokio::fs::write(&temporary_path, contents).await {
+            Ok(()) => tokio::fs::rename(&temporary_path, &self.path).await,
+            Err(error) => Err(error),
+        };
+        written.context(WriteSnafu { path: &self.path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn counts_survive_being_loaded_again() {
+        let path = std::env::temp_dir().join(format!("usage-stats-{}.json", std::process::id()));
+        let guild_id = Id::new(1);
+
+        let usage_stats = UsageStats::load(path.clone()).unwrap();
+        usage_stats.record_release(guild_id).await.unwrap();
+        usage_stats.record_release(guild_id).await.unwrap();
+
+        let reloaded = UsageStats::load(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(reloaded.releases(guild_id).await, 2);
+        assert_eq!(reloaded.releases(Id::new(2)).await, 0);
+    }
+}
@@ -16,10 +16,19 @@ struct Args {
     #[arg(env)]
     discord_application_public_key: Hex<PublicKeyOrphanRuleAvoidance>,
 
+    /// Either this and the client secret, or the combined credentials, have to be given
     #[arg(env)]
-    spotify_client_id: String,
+    spotify_client_id: Option<String>,
     #[arg(env)]
-    spotify_client_secret: SecretString,
+    spotify_client_secret: Option<SecretString>,
+    /// The Spotify client ID and secret separated by a colon (like `client_id:client_secret`), instead of giving them separately
+    #[arg(long, env)]
+    spotify_credentials: Option<SecretString>,
+    #[command(flatten)]
+    spotify_api_urls: config_args::SpotifyApiUrlArgs,
+
+    #[command(flatten)]
+    config: config_args::ConfigArgs,
 }
 
 #[tokio::main]
@@ -30,15 +39,25 @@ async fn main() -> Result<(), lambda_http::Error> {
             Hex(PublicKeyOrphanRuleAvoidance(discord_application_public_key)),
         spotify_client_id,
         spotify_client_secret,
+        spotify_credentials,
+        spotify_api_urls,
+        config,
     } = Args::parse();
 
     lambda_http::tracing::init_default_subscriber();
 
+    let spotify_credentials = discord_bot::SpotifyCredentials::from_either(
+        spotify_client_id,
+        spotify_client_secret,
+        spotify_credentials,
+    )?;
+
     let router = via_axum::init(via_axum::InitArgs {
         discord_token,
         discord_application_public_key,
-        spotify_client_id,
-        spotify_client_secret,
+        spotify_credentials,
+        spotify_api_urls: spotify_api_urls.into(),
+        discord_bot_config: config.into(),
     })
     .await
     .context(AxumInitSnafu)?;
@@ -18,14 +18,28 @@ struct Args {
     #[arg(long, env)]
     discord_application_public_key: Hex<PublicKeyOrphanRuleAvoidance>,
 
+    /// Either this and the client secret, or the combined credentials, have to be given
     #[arg(long, env)]
-    spotify_client_id: String,
+    spotify_client_id: Option<String>,
     #[arg(long, env)]
-    spotify_client_secret: SecretString,
+    spotify_client_secret: Option<SecretString>,
+    /// The Spotify client ID and secret separated by a colon (like `client_id:client_secret`), instead of giving them separately
+    #[arg(long, env)]
+    spotify_credentials: Option<SecretString>,
+    #[command(flatten)]
+    spotify_api_urls: config_args::SpotifyApiUrlArgs,
+
+    #[command(flatten)]
+    config: config_args::ConfigArgs,
 }
 
 #[derive(Debug, Snafu)]
 enum AppError {
+    #[snafu(display("couldn't make sense of the Spotify credentials"))]
+    SpotifyCredentialsError {
+        source: discord_bot::SpotifyCredentialsError,
+    },
+
     #[snafu(display("couldn't initialize the web server"))]
     AxumInitError { source: via_axum::InitError },
 
@@ -47,18 +61,29 @@ async fn main() -> Result<(), AppError> {
             Hex(PublicKeyOrphanRuleAvoidance(discord_application_public_key)),
         spotify_client_id,
         spotify_client_secret,
+        spotify_credentials,
+        spotify_api_urls,
+        config,
     } = Args::parse();
 
     tracing_subscriber::fmt().pretty().init();
 
+    let spotify_credentials = discord_bot::SpotifyCredentials::from_either(
+        spotify_client_id,
+        spotify_client_secret,
+        spotify_credentials,
+    )
+    .context(SpotifyCredentialsSnafu)?;
+
     let addr = SocketAddr::new(ip, port);
     let listener = TcpListener::bind(addr).await.context(BindSnafu)?;
 
     let router = via_axum::init(via_axum::InitArgs {
         discord_token,
         discord_application_public_key,
-        spotify_client_id,
-        spotify_client_secret,
+        spotify_credentials,
+        spotify_api_urls: spotify_api_urls.into(),
+        discord_bot_config: config.into(),
     })
     .await
     .context(AxumInitSnafu)?;
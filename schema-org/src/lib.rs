@@ -4,7 +4,7 @@ use std::{
 };
 
 use iref::IriRefBuf;
-use serde_with::serde_as;
+use serde_with::{DisplayFromStr, OneOrMany, PickFirst, serde_as};
 use snafu::Snafu;
 
 mod derive_alias {
@@ -15,11 +15,15 @@ mod derive_alias {
     }
 }
 
+#[serde_as]
 #[derive_aliases::derive(..SchemaOrg)]
 pub struct Thing {
     #[serde(rename = "@id")]
     pub id: Option<IriRefBuf>,
 
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    pub image: Option<Vec<ImageObjectOrURL>>,
+
     pub name: Option<Text>,
 }
 
@@ -59,9 +63,9 @@ impl FromStr for DateTime {
             return Ok(Self(datetime));
         }
 
-        return Err(DateTimeParseError::Unmatched {
+        Err(DateTimeParseError::Unmatched {
             original: s.to_owned(),
-        });
+        })
     }
 }
 
@@ -134,14 +138,15 @@ pub enum TextOrURL {
     URL(URL),
 }
 
-#[derive_aliases::derive(..SchemaOrg)]
 #[serde_as]
+#[derive_aliases::derive(..SchemaOrg)]
 #[serde(rename_all = "camelCase")]
 pub struct MusicGroup {
-    #[serde_as(as = "OneOrMany<_>")]
+    #[serde_as(as = "Option<OneOrMany<_>>")]
     pub album: Option<Vec<MusicAlbum>>,
 
-    pub genre: Option<TextOrURL>,
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    pub genre: Option<Vec<TextOrURL>>,
 
     #[serde(flatten)]
     pub performing_group: PerformingGroup,
@@ -232,6 +237,15 @@ pub enum OrganizationOrSub {
     Sub(SubOfOrganization),
     Organization(Organization),
 }
+
+impl From<OrganizationOrSub> for Thing {
+    fn from(value: OrganizationOrSub) -> Self {
+        match value {
+            OrganizationOrSub::Sub(sub_of_organization) => Self::from(sub_of_organization),
+            OrganizationOrSub::Organization(organization) => Self::from(organization),
+        }
+    }
+}
 #[derive_aliases::derive(..SchemaOrg)]
 #[serde(untagged)]
 pub enum PersonOrSubOrOrganizationOrSub {
@@ -252,16 +266,30 @@ impl From<PersonOrSubOrOrganizationOrSub> for Thing {
     }
 }
 
+#[serde_as]
 #[derive_aliases::derive(..SchemaOrg)]
 #[serde(rename_all = "camelCase")]
 pub struct CreativeWork {
+    /// Some feeds credit an album's artists here instead of in `byArtist`
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    pub creator: Option<Vec<PersonOrSubOrOrganizationOrSub>>,
+
     pub date_created: Option<DateOrDateTime>,
 
     pub date_modified: Option<DateOrDateTime>,
 
     pub date_published: Option<DateOrDateTime>,
 
-    pub publisher: Option<PersonOrSubOrOrganizationOrSub>,
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    pub genre: Option<Vec<TextOrURL>>,
+
+    /// Either BCP 47 codes (like `ja` or `en-US`) or full language names, depending on the provider
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    pub in_language: Option<Vec<Text>>,
+
+    /// Some feeds list more than one, like when labels put a release out together
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    pub publisher: Option<Vec<PersonOrSubOrOrganizationOrSub>>,
 
     #[serde(flatten)]
     pub thing: Thing,
@@ -272,25 +300,86 @@ pub struct CreativeWork {
 pub struct MusicRecording {
     pub by_artist: Option<MusicGroup>, // TODO: MusicGroupOrPerson
 
+    /// The recording's International Standard Recording Code, like `USRC17607839`
+    pub isrc_code: Option<Text>,
+
     #[serde(flatten)]
     pub creative_work: CreativeWork,
 }
 
 pub type Integer = i64;
 
+#[serde_as]
+#[derive_aliases::derive(..SchemaOrg)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaObject {
+    pub content_url: Option<URL>,
+
+    /// Providers give this as either a number or a string of one
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    pub width: Option<Integer>,
+
+    /// Providers give this as either a number or a string of one
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    pub height: Option<Integer>,
+
+    #[serde(flatten)]
+    pub creative_work: CreativeWork,
+}
+
+#[derive_aliases::derive(..SchemaOrg)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageObject {
+    #[serde(flatten)]
+    pub media_object: MediaObject,
+}
+
+/// Music videos are given as either, since <https://schema.org/MusicVideoObject> has nothing more to it than <https://schema.org/VideoObject>
+#[derive_aliases::derive(..SchemaOrgEnum)]
+pub enum VideoObjectType {
+    MusicVideoObject,
+    VideoObject,
+}
+
+#[serde_as]
+#[derive_aliases::derive(..SchemaOrg)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoObject {
+    /// Required so that other JSON-LD in a page (which would otherwise fit, since everything else is optional) isn't taken for a video
+    #[serde(rename = "@type")]
+    pub kind: VideoObjectType,
+
+    pub by_artist: Option<MusicGroup>, // TODO: MusicGroupOrPerson
+
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    pub thumbnail_url: Option<Vec<URL>>,
+
+    pub upload_date: Option<DateOrDateTime>,
+
+    #[serde(flatten)]
+    pub media_object: MediaObject,
+}
+
 #[derive_aliases::derive(..SchemaOrg)]
+#[serde(untagged)]
+pub enum ImageObjectOrURL {
+    ImageObject(Box<ImageObject>),
+    URL(URL),
+}
+
 #[serde_as]
+#[derive_aliases::derive(..SchemaOrg)]
 #[serde(rename_all = "camelCase")]
 pub struct Intangible {
     #[serde(flatten)]
     pub thing: Thing,
 }
 
-#[derive_aliases::derive(..SchemaOrg)]
 #[serde_as]
+#[derive_aliases::derive(..SchemaOrg)]
 #[serde(rename_all = "camelCase")]
 pub struct ListItem<T> {
-    pub item: T,
+    pub item: ItemOrItemList<T>,
 
     pub position: Option<Integer>, // TODO: or text
 
@@ -298,9 +387,25 @@ pub struct ListItem<T> {
     pub intangible: Intangible,
 }
 
+/// Lists can be nested, like an album's tracks grouped by disc
 #[derive_aliases::derive(..SchemaOrg)]
+#[serde(untagged)]
+pub enum ItemOrItemList<T> {
+    // tried first, since an item could otherwise be missing everything it's made up of and still be taken as one
+    ItemList(ItemList<T>),
+    Item(T),
+}
+
 #[serde_as]
-#[serde(rename_all = "camelCase")]
+#[derive_aliases::derive(..SchemaOrg)]
+#[serde(
+    rename_all = "camelCase",
+    // converting the elements with `serde_as` keeps serde from working out these bounds itself
+    bound(
+        serialize = "T: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>"
+    )
+)]
 pub struct ItemList<T> {
     #[serde_as(as = "OneOrMany<_>")]
     pub item_list_element: Vec<ListItem<T>>,
@@ -309,8 +414,54 @@ pub struct ItemList<T> {
     pub intangible: Intangible,
 }
 
-#[derive_aliases::derive(..SchemaOrg)]
+/// An item from an [`ItemList`] once any nested lists are flattened out
+#[derive_aliases::derive(..Standard)]
+pub struct FlatListItem<T> {
+    pub item: T,
+
+    pub position: Option<Integer>,
+
+    /// The position of the nested list the item was in (like the number of the disc a track is on), if it was in one
+    pub list_position: Option<Integer>,
+}
+
+impl<T> ItemList<T> {
+    /// How many items there are, counting the ones in nested lists instead of the lists themselves
+    pub fn n_items(&self) -> usize {
+        self.item_list_element
+            .iter()
+            .map(|list_item| match &list_item.item {
+                ItemOrItemList::ItemList(item_list) => item_list.n_items(),
+                ItemOrItemList::Item(_) => 1,
+            })
+            .sum()
+    }
+
+    /// Every item in order, with the items of nested lists in place of the lists themselves
+    pub fn into_flattened(self) -> Vec<FlatListItem<T>> {
+        let mut flattened = Vec::new();
+        self.flatten_into(None, &mut flattened);
+        flattened
+    }
+
+    fn flatten_into(self, list_position: Option<Integer>, flattened: &mut Vec<FlatListItem<T>>) {
+        for ListItem { item, position, .. } in self.item_list_element {
+            match item {
+                ItemOrItemList::ItemList(item_list) => {
+                    item_list.flatten_into(position.or(list_position), flattened);
+                }
+                ItemOrItemList::Item(item) => flattened.push(FlatListItem {
+                    item,
+                    position,
+                    list_position,
+                }),
+            }
+        }
+    }
+}
+
 #[serde_as]
+#[derive_aliases::derive(..SchemaOrg)]
 #[serde(rename_all = "camelCase")]
 pub struct MusicPlaylist {
     pub num_tracks: Option<Integer>,
@@ -321,11 +472,37 @@ pub struct MusicPlaylist {
     pub creative_work: CreativeWork,
 }
 
+#[derive_aliases::derive(..SchemaOrgEnum)]
+pub enum MusicReleaseFormatType {
+    CDFormat,
+    CassetteFormat,
+    DVDFormat,
+    DigitalAudioTapeFormat,
+    DigitalFormat,
+    LaserDiscFormat,
+    VinylFormat,
+}
+
 #[derive_aliases::derive(..SchemaOrg)]
 #[serde(rename_all = "camelCase")]
 pub struct MusicRelease {
     pub catalog_number: Option<Text>,
 
+    /// The release's barcode, of whichever length
+    pub gtin: Option<Text>,
+    /// The release's barcode as an 8-digit EAN
+    pub gtin8: Option<Text>,
+    /// The release's barcode as a 12-digit UPC
+    pub gtin12: Option<Text>,
+    /// The release's barcode as a 13-digit EAN
+    pub gtin13: Option<Text>,
+    /// The release's barcode as a 14-digit GTIN, which is used for packages of several copies
+    pub gtin14: Option<Text>,
+
+    pub music_release_format: Option<MusicReleaseFormatType>,
+
+    pub record_label: Option<OrganizationOrSub>,
+
     #[serde(flatten)]
     pub music_playlist: MusicPlaylist,
 }
@@ -351,8 +528,8 @@ pub enum MusicAlbumReleaseType {
     SingleRelease,
 }
 
-#[derive_aliases::derive(..SchemaOrg)]
 #[serde_as]
+#[derive_aliases::derive(..SchemaOrg)]
 #[serde(rename_all = "camelCase")]
 pub struct MusicAlbum {
     pub album_production_type: Option<MusicAlbumProductionType>,
@@ -368,3 +545,263 @@ pub struct MusicAlbum {
     #[serde(flatten)]
     pub music_playlist: MusicPlaylist,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::{Serialize, de::DeserializeOwned};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn music_release_barcodes_of_every_length() {
+        let release: MusicRelease = serde_json::from_value(json!({
+            "@type": "MusicRelease",
+            "gtin": "196588000007",
+            "gtin8": "96385074",
+            "gtin12": "196588000007",
+            "gtin13": "0196588000007",
+            "gtin14": "00196588000007"
+        }))
+        .unwrap();
+
+        assert_eq!(release.gtin.as_deref(), Some("196588000007"));
+        assert_eq!(release.gtin8.as_deref(), Some("96385074"));
+        assert_eq!(release.gtin12.as_deref(), Some("196588000007"));
+        assert_eq!(release.gtin13.as_deref(), Some("0196588000007"));
+        assert_eq!(release.gtin14.as_deref(), Some("00196588000007"));
+    }
+
+    #[test]
+    fn music_recordings_have_isrcs() {
+        let recording: MusicRecording = serde_json::from_value(json!({
+            "@type": "MusicRecording",
+            "name": "Glass",
+            "isrcCode": "USRC17607839"
+        }))
+        .unwrap();
+
+        assert_eq!(recording.isrc_code.as_deref(), Some("USRC17607839"));
+    }
+
+    #[test]
+    fn tracks_grouped_by_disc_are_flattened() {
+        let album: MusicAlbum = serde_json::from_value(json!({
+            "@type": "MusicAlbum",
+            "name": "Night Drive",
+            "track": {
+                "@type": "ItemList",
+                "itemListElement": [
+                    {
+                        "@type": "ListItem",
+                        "position": 1,
+                        "item": {
+                            "@type": "ItemList",
+                            "itemListElement": [
+                                { "@type": "ListItem", "position": 1, "item": { "@type": "MusicRecording", "name": "On-Ramp" } },
+                                { "@type": "ListItem", "position": 2, "item": { "@type": "MusicRecording", "name": "Overpass" } }
+                            ]
+                        }
+                    },
+                    {
+                        "@type": "ListItem",
+                        "position": 2,
+                        "item": {
+                            "@type": "ItemList",
+                            "itemListElement": [
+                                { "@type": "ListItem", "position": 1, "item": { "@type": "MusicRecording", "name": "Off-Ramp" } }
+                            ]
+                        }
+                    }
+                ]
+            }
+        }))
+        .unwrap();
+
+        let track = album.music_playlist.track.unwrap();
+        assert_eq!(track.n_items(), 3);
+
+        let tracks = Vec::from_iter(track.into_flattened().into_iter().map(|track| {
+            (
+                track.list_position,
+                track.position,
+                track.item.creative_work.thing.name,
+            )
+        }));
+        assert_eq!(
+            tracks,
+            [
+                (Some(1), Some(1), Some("On-Ramp".to_owned())),
+                (Some(1), Some(2), Some("Overpass".to_owned())),
+                (Some(2), Some(1), Some("Off-Ramp".to_owned())),
+            ]
+        );
+    }
+
+    /// Reads `value`, writes it back out, and reads that again, checking that nothing changed on the way
+    fn round_trip<T: Serialize + DeserializeOwned>(value: serde_json::Value) -> T {
+        let read: T = serde_json::from_value(value).unwrap();
+        let written = serde_json::to_value(&read).unwrap();
+        let read_again: T = serde_json::from_value(written.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&read_again).unwrap(), written);
+        read_again
+    }
+
+    #[test]
+    fn music_album_round_trips() {
+        let album: MusicAlbum = round_trip(json!({
+            "@type": "MusicAlbum",
+            "@id": "https://example.bandcamp.com/album/night-drive",
+            "name": "Night Drive",
+            "albumReleaseType": "EPRelease",
+            "albumProductionType": "StudioAlbum",
+            "datePublished": "2024-05-10",
+            "byArtist": { "@type": "MusicGroup", "name": "The Example Band" },
+            "albumRelease": {
+                "@type": "MusicRelease",
+                "catalogNumber": "EX-001",
+                "gtin13": "0196588000007",
+                "musicReleaseFormat": "DigitalFormat",
+                "recordLabel": { "@type": "Organization", "name": "Example Records" }
+            },
+            "track": {
+                "@type": "ItemList",
+                "itemListElement": [
+                    { "@type": "ListItem", "position": 1, "item": { "@type": "MusicRecording", "name": "On-Ramp" } },
+                    { "@type": "ListItem", "position": 2, "item": { "@type": "MusicRecording", "name": "Overpass" } }
+                ]
+            }
+        }));
+
+        assert!(matches!(
+            album.album_release_type,
+            Some(MusicAlbumReleaseType::EPRelease)
+        ));
+        assert!(matches!(
+            album.album_production_type,
+            Some(MusicAlbumProductionType::StudioAlbum)
+        ));
+        assert_eq!(
+            album.music_playlist.creative_work.thing.name.as_deref(),
+            Some("Night Drive")
+        );
+        assert!(matches!(
+            album.music_playlist.creative_work.date_published,
+            Some(DateOrDateTime::Date(Date(date))) if date.to_string() == "2024-05-10"
+        ));
+
+        let [release] = album.album_release.as_slice() else {
+            panic!("expected one albumRelease, got {:?}", album.album_release);
+        };
+        assert_eq!(release.catalog_number.as_deref(), Some("EX-001"));
+        assert_eq!(release.gtin13.as_deref(), Some("0196588000007"));
+        assert!(matches!(
+            release.music_release_format,
+            Some(MusicReleaseFormatType::DigitalFormat)
+        ));
+        assert_eq!(
+            release
+                .record_label
+                .clone()
+                .map(Thing::from)
+                .and_then(|label| label.name)
+                .as_deref(),
+            Some("Example Records")
+        );
+
+        let tracks = album.music_playlist.track.unwrap().into_flattened();
+        let titles = Vec::from_iter(
+            tracks
+                .iter()
+                .map(|track| track.item.creative_work.thing.name.as_deref()),
+        );
+        assert_eq!(titles, [Some("On-Ramp"), Some("Overpass")]);
+        assert_eq!(tracks[1].position, Some(2));
+    }
+
+    #[test]
+    fn music_group_round_trips() {
+        let group: MusicGroup = round_trip(json!({
+            "@type": "MusicGroup",
+            "@id": "https://example.bandcamp.com",
+            "name": "The Example Band",
+            "genre": "Electronic",
+            "album": { "@type": "MusicAlbum", "name": "Night Drive", "albumRelease": [] }
+        }));
+
+        assert_eq!(
+            group.performing_group.organization.thing.name.as_deref(),
+            Some("The Example Band")
+        );
+        assert!(matches!(
+            group.genre.as_deref(),
+            Some([TextOrURL::Text(genre)]) if genre == "Electronic"
+        ));
+        assert_eq!(group.album.map(|albums| albums.len()), Some(1));
+    }
+
+    #[test]
+    fn music_recording_round_trips() {
+        let recording: MusicRecording = round_trip(json!({
+            "@type": "MusicRecording",
+            "name": "Glass (feat. Guest Singer)",
+            "isrcCode": "USRC17607839",
+            "byArtist": { "@type": "MusicGroup", "name": "The Example Band" },
+            "inLanguage": "en"
+        }));
+
+        assert_eq!(recording.isrc_code.as_deref(), Some("USRC17607839"));
+        assert_eq!(
+            recording.creative_work.thing.name.as_deref(),
+            Some("Glass (feat. Guest Singer)")
+        );
+        assert_eq!(
+            recording.creative_work.in_language.as_deref(),
+            Some(["en".to_owned()].as_slice())
+        );
+        assert_eq!(
+            recording
+                .by_artist
+                .map(Thing::from)
+                .and_then(|artist| artist.name)
+                .as_deref(),
+            Some("The Example Band")
+        );
+    }
+
+    #[test]
+    fn creative_work_round_trips() {
+        let work: CreativeWork = round_trip(json!({
+            "@id": "https://example.com/work",
+            "name": "Example",
+            "dateCreated": "2023-09-15T12:30:00Z",
+            "genre": ["Alternative", "Music"],
+            "image": ["https://example.com/cover.jpg"],
+            "publisher": [
+                { "@type": "MusicGroup", "name": "Example Records" },
+                { "@type": "MusicGroup", "name": "Other Records" }
+            ]
+        }));
+
+        assert_eq!(
+            work.thing.id.as_ref().map(IriRefBuf::as_str),
+            Some("https://example.com/work")
+        );
+        assert!(matches!(
+            work.date_created,
+            Some(DateOrDateTime::DateTime(DateTime(date_time)))
+                if date_time.to_rfc3339() == "2023-09-15T12:30:00+00:00"
+        ));
+        assert_eq!(work.genre.map(|genres| genres.len()), Some(2));
+        assert_eq!(work.thing.image.map(|images| images.len()), Some(1));
+
+        let publishers = Vec::from_iter(
+            work.publisher
+                .into_iter()
+                .flatten()
+                .filter_map(|publisher| Thing::from(publisher).name),
+        );
+        assert_eq!(publishers, ["Example Records", "Other Records"]);
+    }
+}
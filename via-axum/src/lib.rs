@@ -6,11 +6,17 @@ use snafu::{ResultExt, Snafu};
 
 mod routes;
 
+#[cfg(all(feature = "dev-insecure", not(debug_assertions)))]
+compile_error!(
+    "the dev-insecure feature turns off signature verification, so it can only be used in debug builds"
+);
+
 #[derive(Clone)]
 struct AppState {
     discord_application_public_key: PublicKey,
     discord_interaction_handler: InteractionHandler,
 
+    /// Holds the one Discord client that every request shares
     discord_bot_state: discord_bot::State,
 }
 
@@ -18,12 +24,19 @@ struct AppState {
 pub struct InitArgs {
     pub discord_token: SecretString,
     pub discord_application_public_key: PublicKey,
-    pub spotify_client_id: String,
-    pub spotify_client_secret: SecretString,
+    pub spotify_credentials: discord_bot::SpotifyCredentials,
+    pub spotify_api_urls: discord_bot::SpotifyApiUrls,
+
+    pub discord_bot_config: discord_bot::Config,
 }
 
 #[derive(Debug, Snafu)]
 pub enum InitError {
+    #[snafu(display(
+        "signature verification doesn't work even with a sample signed just now, so no interactions would be accepted"
+    ))]
+    VerificationSelfTestError { source: routes::VerificationError },
+
     #[snafu(display("couldn't initialize the discord bot"))]
     DiscordBotInitError { source: discord_bot::InitError },
 }
@@ -33,15 +46,30 @@ pub async fn init(
     InitArgs {
         discord_token,
         discord_application_public_key,
-        spotify_client_id,
-        spotify_client_secret,
+        spotify_credentials,
+        spotify_api_urls,
+        discord_bot_config,
     }: InitArgs,
 ) -> Result<Router<()>, InitError> {
+    if cfg!(feature = "dev-insecure") {
+        tracing::warn!(
+            "SIGNATURE VERIFICATION IS OFF because the dev-insecure feature is enabled, so anyone who can reach this server can send it interactions; never deploy this build"
+        );
+    }
+
+    routes::verification_self_test().context(VerificationSelfTestSnafu)?;
+    // the public key isn't a secret, so it's logged in full to compare against the one in the Discord developer portal
+    tracing::info!(
+        discord_application_public_key = hex::encode(*discord_application_public_key),
+        "signature verification works, and interactions will be verified with this public key"
+    );
+
     let (discord_interaction_handler, discord_bot_state) =
         discord_bot::init(discord_bot::InitArgs {
             discord_token,
-            spotify_client_id,
-            spotify_client_secret,
+            spotify_credentials,
+            spotify_api_urls,
+            config: discord_bot_config,
         })
         .await
         .context(DiscordBotInitSnafu)?;
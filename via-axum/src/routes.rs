@@ -4,6 +4,8 @@ use crate::AppState;
 
 mod discord;
 
+pub(crate) use discord::{VerificationError, verification_self_test};
+
 pub fn create_router() -> Router<AppState> {
     Router::new().nest("/discord", discord::create_router())
 }
@@ -3,6 +3,8 @@ use axum::Router;
 
 mod interactions;
 
+pub(crate) use interactions::{VerificationError, verification_self_test};
+
 pub fn create_router() -> Router<AppState> {
     Router::new().nest("/interactions", interactions::create_router())
 }
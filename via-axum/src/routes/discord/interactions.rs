@@ -9,10 +9,10 @@ use axum::{
 };
 use axum_extra::TypedHeader;
 use discord_bot::Interaction;
-use ed25519_compact::{PublicKey, Signature};
+use ed25519_compact::{KeyPair, PublicKey, Seed, Signature};
 use headers::Header;
 use serde::de::DeserializeOwned;
-use snafu::{Report, ResultExt, Snafu};
+use snafu::{Report, ResultExt, Snafu, ensure};
 
 pub fn create_router() -> Router<AppState> {
     Router::new().route("/", post(handle_post))
@@ -28,7 +28,7 @@ impl FromRef<AppState> for PublicKey {
 #[snafu(display(
     "all the needed information was provided, but this message was not signed with the private key corresponding to this public key, so something suspicious may be going on"
 ))]
-struct VerificationError {
+pub(crate) struct VerificationError {
     source: ed25519_compact::Error,
 }
 
@@ -43,19 +43,82 @@ impl IntoResponse for VerificationError {
     }
 }
 
+/// Discord signs the timestamp header exactly as sent followed by the raw body (which is empty for PINGs);
+/// only the signature header is hex-encoded, so nothing else here should be decoded before concatenating
+fn signed_message(timestamp: &[u8], body: &[u8]) -> Vec<u8> {
+    [timestamp, body].concat()
+}
+
 fn verify(
     body: &[u8],
     timestamp: &[u8],
     signature: Signature,
     public_key: &PublicKey,
 ) -> Result<(), VerificationError> {
-    let message = [timestamp, body].concat();
+    let message = signed_message(timestamp, body);
 
     public_key
         .verify(message, &signature)
         .context(VerificationSnafu)
 }
 
+/// Signs a sample interaction with a throwaway key and checks it the same way real interactions are checked,
+/// so that a broken verification path shows up at startup instead of as Discord failing to validate the endpoint
+pub(crate) fn verification_self_test() -> Result<(), VerificationError> {
+    let key_pair = KeyPair::from_seed(Seed::new([0x42; Seed::BYTES]));
+
+    let timestamp = b"1700000000";
+    let body = br#"{"type":1}"#;
+    let signature = key_pair.sk.sign(signed_message(timestamp, body), None);
+
+    verify(body, timestamp, signature, &key_pair.pk)
+}
+
+/// Surrounding whitespace would otherwise break hex decoding or the signed message.
+/// Conflicting repeats are rejected by [`ensure_unambiguous`] before this ever runs
+fn decode_trimmed<'i, I>(values: &mut I) -> Result<Vec<u8>, headers::Error>
+where
+    I: Iterator<Item = &'i HeaderValue>,
+{
+    let value = values.next().ok_or_else(headers::Error::invalid)?;
+
+    Ok(value.as_bytes().trim_ascii().to_owned())
+}
+
+#[derive(Debug, Clone, Snafu)]
+#[snafu(display(
+    "the {name} header was given more than once with conflicting values, so there's no telling which one is genuine"
+))]
+struct ConflictingHeaderValues {
+    name: &'static HeaderName,
+}
+impl IntoResponse for ConflictingHeaderValues {
+    fn into_response(self) -> Response {
+        let status_code = StatusCode::BAD_REQUEST;
+
+        let report = Report::from_error(self);
+        let body = report.to_string();
+
+        (status_code, body).into_response()
+    }
+}
+
+fn ensure_unambiguous<H: Header>(req: &Request) -> Result<(), ConflictingHeaderValues> {
+    let mut values = req
+        .headers()
+        .get_all(H::name())
+        .iter()
+        .map(|value| value.as_bytes().trim_ascii());
+
+    let first = values.next();
+    ensure!(
+        values.all(|value| Some(value) == first),
+        ConflictingHeaderValuesSnafu { name: H::name() }
+    );
+
+    Ok(())
+}
+
 #[derive(Debug)]
 struct XSignatureEd25519(Vec<u8>);
 static X_SIGNATURE_ED25519_HEADER_NAME: HeaderName = HeaderName::from_static("x-signature-ed25519");
@@ -68,9 +131,7 @@ impl Header for XSignatureEd25519 {
     where
         I: Iterator<Item = &'i HeaderValue>,
     {
-        let value = values.next().ok_or_else(headers::Error::invalid)?;
-
-        Ok(Self(value.as_bytes().to_owned()))
+        decode_trimmed(values).map(Self)
     }
 
     fn encode<E>(&self, values: &mut E)
@@ -96,9 +157,7 @@ impl Header for XSignatureTimestamp {
     where
         I: Iterator<Item = &'i HeaderValue>,
     {
-        let value = values.next().ok_or_else(headers::Error::invalid)?;
-
-        Ok(Self(value.as_bytes().to_owned()))
+        decode_trimmed(values).map(Self)
     }
 
     fn encode<E>(&self, values: &mut E)
@@ -154,6 +213,20 @@ impl FromRequest<PublicKey> for Ed25519Verified {
         public_key: &PublicKey,
     ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
         async move {
+            if cfg!(feature = "dev-insecure") {
+                tracing::warn!(
+                    "NOT verifying this interaction's signature, because the dev-insecure feature is enabled"
+                );
+
+                let body = Bytes::from_request(req, public_key)
+                    .await
+                    .map_err(IntoResponse::into_response)?;
+                return Ok(Self(body));
+            }
+
+            ensure_unambiguous::<XSignatureEd25519>(&req).map_err(IntoResponse::into_response)?;
+            ensure_unambiguous::<XSignatureTimestamp>(&req).map_err(IntoResponse::into_response)?;
+
             let TypedHeader(XSignatureEd25519(signature)) = req
                 .extract_parts()
                 .await
@@ -210,17 +283,238 @@ where
     }
 }
 
+/// Uses the Discord bot state made once in [`crate::init`] (and its client) for every interaction,
+/// so nothing here needs the Discord token
 #[tracing::instrument(skip(app_state))]
 pub async fn handle_post(
     State(app_state): State<AppState>,
     Ed25519VerifiedJson(interaction): Ed25519VerifiedJson<Interaction>,
-) -> impl IntoResponse {
+) -> Response {
     match app_state
         .discord_interaction_handler
         .handle(app_state.discord_bot_state, interaction)
         .await
     {
-        Ok(response) => Json(response),
-        Err(error) => todo!(),
+        Ok(response) => Json(response).into_response(),
+        Err(error) => {
+            let report = Report::from_error(error);
+            tracing::error!(%report, "couldn't handle the interaction");
+
+            (StatusCode::INTERNAL_SERVER_ERROR, report.to_string()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use discord_bot::InteractionHandler;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn key_pair() -> KeyPair {
+        KeyPair::from_seed(Seed::new([0x07; Seed::BYTES]))
+    }
+
+    const TIMESTAMP: &[u8] = b"1718000000";
+    const BODY: &[u8] = br#"{"type":2,"data":{"name":"new-release"}}"#;
+
+    fn sign(timestamp: &[u8], body: &[u8]) -> Signature {
+        key_pair().sk.sign(signed_message(timestamp, body), None)
+    }
+
+    /// A request the way Discord sends one: the signature hex-encoded in its header, the timestamp as is
+    fn signed_request(signature: Signature, timestamp: &[u8], body: &'static [u8]) -> Request {
+        axum::http::Request::builder()
+            .method("POST")
+            .uri("/discord/interactions")
+            .header("x-signature-ed25519", hex::encode(*signature))
+            .header("x-signature-timestamp", timestamp)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    async fn extract(request: Request) -> Result<Bytes, StatusCode> {
+        Ed25519Verified::from_request(request, &key_pair().pk)
+            .await
+            .map(|Ed25519Verified(body)| body)
+            .map_err(|response| response.status())
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let signature = sign(TIMESTAMP, BODY);
+
+        assert!(verify(BODY, TIMESTAMP, signature, &key_pair().pk).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_tampered_body() {
+        let signature = sign(TIMESTAMP, BODY);
+        let tampered = br#"{"type":2,"data":{"name":"check-roles"}}"#;
+
+        assert!(verify(tampered, TIMESTAMP, signature, &key_pair().pk).is_err());
+    }
+
+    #[test]
+    fn rejects_a_swapped_timestamp() {
+        let signature = sign(TIMESTAMP, BODY);
+
+        assert!(verify(BODY, b"1718000001", signature, &key_pair().pk).is_err());
+    }
+
+    #[test]
+    fn accepts_a_ping_with_an_empty_body() {
+        let signature = sign(TIMESTAMP, b"");
+
+        assert!(verify(b"", TIMESTAMP, signature, &key_pair().pk).is_ok());
+        assert!(verify(br#"{"type":1}"#, TIMESTAMP, signature, &key_pair().pk).is_err());
+    }
+
+    /// The first test vector from RFC 8032, whose message is empty, so it stands in for an empty timestamp and body
+    #[test]
+    fn agrees_with_rfc_8032() {
+        let public_key = PublicKey::from_slice(
+            &hex::decode("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+                .unwrap(),
+        )
+        .unwrap();
+        let signature = Signature::from_slice(
+            &hex::decode(
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(verify(b"", b"", signature, &public_key).is_ok());
+    }
+
+    #[test]
+    fn self_test_passes() {
+        assert!(verification_self_test().is_ok());
+    }
+
+    #[tokio::test]
+    async fn extracts_the_body_of_a_signed_request() {
+        let request = signed_request(sign(TIMESTAMP, BODY), TIMESTAMP, BODY);
+
+        assert_eq!(extract(request).await, Ok(Bytes::from_static(BODY)));
+    }
+
+    #[tokio::test]
+    async fn refuses_a_request_with_a_tampered_body() {
+        let tampered = br#"{"type":2,"data":{"name":"check-roles"}}"#;
+        let request = signed_request(sign(TIMESTAMP, BODY), TIMESTAMP, tampered);
+
+        assert_eq!(extract(request).await, Err(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn refuses_a_request_with_a_swapped_timestamp() {
+        let request = signed_request(sign(TIMESTAMP, BODY), b"1718000001", BODY);
+
+        assert_eq!(extract(request).await, Err(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn extracts_the_empty_body_of_a_signed_ping() {
+        let request = signed_request(sign(TIMESTAMP, b""), TIMESTAMP, b"");
+
+        assert_eq!(extract(request).await, Ok(Bytes::new()));
+    }
+
+    fn request_with_headers(headers: &[(&str, &str)]) -> Request {
+        let mut builder = axum::http::Request::builder();
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn signature_headers_are_trimmed_whatever_their_case() {
+        let req = request_with_headers(&[("X-Signature-Timestamp", " 1718000000\t")]);
+
+        let XSignatureTimestamp(timestamp) = XSignatureTimestamp::decode(
+            &mut req.headers().get_all(XSignatureTimestamp::name()).iter(),
+        )
+        .unwrap();
+        assert_eq!(timestamp, TIMESTAMP);
+    }
+
+    #[test]
+    fn repeated_signature_headers_have_to_agree() {
+        let agreeing = request_with_headers(&[
+            ("x-signature-timestamp", "1718000000"),
+            ("x-signature-timestamp", " 1718000000 "),
+        ]);
+        assert!(ensure_unambiguous::<XSignatureTimestamp>(&agreeing).is_ok());
+
+        let conflicting = request_with_headers(&[
+            ("x-signature-timestamp", "1718000000"),
+            ("x-signature-timestamp", "1718000001"),
+        ]);
+        assert!(ensure_unambiguous::<XSignatureTimestamp>(&conflicting).is_err());
+    }
+
+    fn app_state() -> AppState {
+        let config = discord_bot::Config::default();
+
+        AppState {
+            discord_application_public_key: key_pair().pk,
+            discord_interaction_handler: InteractionHandler::new(&config).unwrap(),
+            discord_bot_state: discord_bot::State::new(
+                discord_bot::Client::new(String::new()),
+                discord_bot::Id::new(2),
+                Arc::new([]),
+                None,
+                config,
+            )
+            .unwrap(),
+        }
+    }
+
+    /// Sends a signed interaction through the same routes that [`crate::init`] serves
+    async fn post_interaction(body: &'static [u8]) -> (StatusCode, Bytes) {
+        let request = signed_request(sign(TIMESTAMP, body), TIMESTAMP, body);
+
+        let response = crate::routes::create_router()
+            .with_state(app_state())
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn a_signed_ping_is_ponged() {
+        let ping = br#"{"id":"1","application_id":"2","type":1,"token":"token","authorizing_integration_owners":{},"entitlements":[]}"#;
+
+        let (status, body) = post_interaction(ping).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({ "type": 1 })
+        );
+    }
+
+    #[tokio::test]
+    async fn an_interaction_that_cant_be_handled_is_a_server_error() {
+        let unknown_command = br#"{"id":"1","application_id":"2","type":2,"token":"token","authorizing_integration_owners":{},"entitlements":[],"data":{"id":"4","name":"no-such-command","type":1}}"#;
+
+        let (status, body) = post_interaction(unknown_command).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(
+            String::from_utf8_lossy(&body).contains("no-such-command"),
+            "{body:?}"
+        );
     }
 }